@@ -0,0 +1,86 @@
+//! Apache/nginx access logs in the common or combined format.
+
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::sync::LazyLock;
+
+/// `host ident user [time] "request" status bytes`, optionally followed by the combined
+/// format's `"referer" "agent"` and a trailing request time (as nginx's `$request_time`).
+static FORMAT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^(?P<host>\S+) (?P<ident>\S+) (?P<user>\S+) \[(?P<time>[^\]]+)\] "(?P<request>(?:(?P<method>[A-Z]+) (?P<path>\S+)(?: (?P<protocol>[^"\s]+))?)|[^"]*)" (?P<status>\d{3}) (?P<bytes>\d+|-)(?: "(?P<referer>[^"]*)" "(?P<agent>[^"]*)")?(?: (?P<latency>\d+(?:\.\d+)?))?"#,
+    )
+    .unwrap()
+});
+
+/// Fields available to `--field`/`--distinct` paths such as `.status`.
+const FIELDS: [&str; 13] = [
+    "host", "ident", "user", "time", "request", "method", "path", "protocol", "status", "bytes",
+    "referer", "agent", "latency",
+];
+
+/// The fields of an access log line as a JSON object, numbers where they are numeric.
+pub fn fields(line: &str) -> Option<Value> {
+    let captures = FORMAT.captures(line)?;
+    let mut map = Map::new();
+    for name in FIELDS {
+        let Some(value) = captures.name(name).map(|m| m.as_str()) else {
+            continue;
+        };
+        let value = match name {
+            "status" | "bytes" => value.parse::<u64>().map_or(Value::Null, Value::from),
+            "latency" => value.parse::<f64>().map_or(Value::Null, Value::from),
+            _ => Value::String(value.to_string()),
+        };
+        map.insert(name.to_string(), value);
+    }
+    Some(Value::Object(map))
+}
+
+/// The line split around its status code, which is colored by class.
+pub fn tokens(line: &str) -> Option<Vec<(String, Option<&'static str>)>> {
+    let status = FORMAT.captures(line)?.name("status")?;
+    let color = match status.as_str().as_bytes()[0] {
+        b'2' => "32",
+        b'3' => "36",
+        b'4' => "33",
+        b'5' => "1;31",
+        _ => "35",
+    };
+    Some(vec![
+        (line[..status.start()].to_string(), None),
+        (status.as_str().to_string(), Some(color)),
+        (line[status.end()..].to_string(), None),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMBINED: &str = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "http://www.example.com/start.html" "Mozilla/4.08" 0.042"#;
+
+    #[test]
+    fn test_access_fields() {
+        let fields = fields(COMBINED).unwrap();
+        assert_eq!(fields["status"], 200);
+        assert_eq!(fields["path"], "/apache_pb.gif");
+        assert_eq!(fields["bytes"], 2326);
+        assert_eq!(fields["latency"], 0.042);
+        assert_eq!(fields["agent"], "Mozilla/4.08");
+
+        let common = super::fields(r#"::1 - - [10/Oct/2000:13:55:36 +0000] "-" 400 -"#).unwrap();
+        assert_eq!(common["status"], 400);
+        assert_eq!(common["bytes"], Value::Null);
+        assert!(common.get("path").is_none());
+        assert!(super::fields("not an access log").is_none());
+    }
+
+    #[test]
+    fn test_status_tokens() {
+        let tokens = tokens(COMBINED).unwrap();
+        assert_eq!(tokens[1], ("200".to_string(), Some("32")));
+        let text: String = tokens.into_iter().map(|(t, _)| t).collect();
+        assert_eq!(text, COMBINED);
+    }
+}
@@ -0,0 +1,156 @@
+//! Terminal output saved as an asciicast v2 file, playable with `asciinema play`.
+//!
+//! The file is a JSON header line followed by one `[seconds, "o", data]` line per write.
+
+use crate::mirror::Mirror;
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+pub struct Cast {
+    out: BufWriter<File>,
+    started: Instant,
+    /// Writes made in quick succession, saved as one event.
+    pending: Vec<u8>,
+    pending_at: f64,
+}
+
+impl Cast {
+    /// Writes closer together than this are merged into one event.
+    const MERGE_SECONDS: f64 = 0.005;
+}
+
+impl Cast {
+    pub fn create(path: &Path, width: u16, height: u16) -> io::Result<Self> {
+        Self::new(File::create(path)?, width, height)
+    }
+
+    fn new(file: File, width: u16, height: u16) -> io::Result<Self> {
+        let mut out = BufWriter::new(file);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": timestamp,
+            "env": {"TERM": std::env::var("TERM").unwrap_or_default()},
+        });
+        writeln!(out, "{}", header)?;
+        Ok(Cast {
+            out,
+            started: Instant::now(),
+            pending: Vec::new(),
+            pending_at: 0.0,
+        })
+    }
+
+    /// Add bytes written to the terminal.
+    pub fn output(&mut self, data: &[u8]) -> io::Result<()> {
+        let seconds = self.started.elapsed().as_secs_f64();
+        if seconds - self.pending_at > Self::MERGE_SECONDS {
+            self.write_pending()?;
+        }
+        if self.pending.is_empty() {
+            self.pending_at = seconds;
+        }
+        self.pending.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        // The terminal turns newlines into CR LF; players expect the translated output.
+        let data = String::from_utf8_lossy(&self.pending).replace('\n', "\r\n");
+        let event = json!([(self.pending_at * 1e6).round() / 1e6, "o", data]);
+        self.pending.clear();
+        writeln!(self.out, "{}", event)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        self.out.flush()
+    }
+}
+
+/// A writer that also adds everything written through it to a cast and to attached
+/// mirrors, if there are any.
+pub struct Tee<W> {
+    inner: W,
+    cast: Option<Arc<Mutex<Cast>>>,
+    mirror: Option<Arc<Mutex<Mirror>>>,
+}
+
+impl<W> Tee<W> {
+    pub fn new(inner: W, cast: Option<Arc<Mutex<Cast>>>) -> Self {
+        Tee {
+            inner,
+            cast,
+            mirror: None,
+        }
+    }
+
+    /// Also pass everything written on to `mirror`'s clients.
+    pub fn mirrored(self, mirror: Option<Arc<Mutex<Mirror>>>) -> Self {
+        Tee { mirror, ..self }
+    }
+}
+
+impl Tee<File> {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let tee = Tee::new(self.inner.try_clone()?, self.cast.clone());
+        Ok(tee.mirrored(self.mirror.clone()))
+    }
+}
+
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(cast) = &self.cast {
+            // A broken cast must not break the session itself.
+            let _ = cast.lock().unwrap().output(&buf[..written]);
+        }
+        if let Some(mirror) = &self.mirror {
+            mirror.lock().unwrap().output(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_tee_records_output() {
+        let path = std::env::temp_dir().join(format!("pipe_tools-cast-{}", std::process::id()));
+        let cast = Arc::new(Mutex::new(Cast::create(&path, 80, 24).unwrap()));
+        let mut tee = Tee::new(Vec::new(), Some(cast.clone()));
+        write!(tee, "\x1B[1m").unwrap();
+        write!(tee, "hi\x1B[0m").unwrap();
+        cast.lock().unwrap().flush().unwrap();
+        assert_eq!(tee.inner, b"\x1B[1mhi\x1B[0m");
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 80);
+        assert_eq!(lines[1][1], "o");
+        assert_eq!(lines[1][2], "\x1B[1mhi\x1B[0m");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
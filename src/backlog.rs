@@ -0,0 +1,79 @@
+//! `--buffer`: a cap on lines read but not yet handled. Past it stdin is left unread, so
+//! the pipe fills and the producer blocks instead of pipe_tools holding everything.
+
+use std::time::{Duration, Instant};
+
+/// How long "throttling upstream" stays up after the reader last had to wait.
+const SHOWN_FOR: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default)]
+pub struct Backlog {
+    /// Lines allowed to wait; unlimited when `None`.
+    limit: Option<usize>,
+    /// Lines sent to the printer and not yet taken.
+    queued: usize,
+    /// When the reader last stopped for the backlog to drain.
+    throttled: Option<Instant>,
+}
+
+impl Backlog {
+    pub fn new(limit: Option<usize>) -> Backlog {
+        Backlog {
+            limit: limit.map(|limit| limit.max(1)),
+            ..Backlog::default()
+        }
+    }
+
+    pub fn add(&mut self) {
+        self.queued += 1;
+    }
+
+    pub fn take(&mut self) {
+        self.queued = self.queued.saturating_sub(1);
+    }
+
+    /// Whether the reader must stop until lines are taken; the time is noted for the
+    /// status.
+    pub fn is_full(&mut self, now: Instant) -> bool {
+        let full = self.limit.is_some_and(|limit| self.queued >= limit);
+        if full {
+            self.throttled = Some(now);
+        }
+        full
+    }
+
+    /// The status widget, such as `buffered 812/1000, throttling upstream`.
+    pub fn status(&self, now: Instant) -> String {
+        let limit = self
+            .limit
+            .map_or("∞".to_string(), |limit| limit.to_string());
+        let mut status = format!("buffered {}/{}", self.queued, limit);
+        if self
+            .throttled
+            .is_some_and(|at| now.duration_since(at) < SHOWN_FOR)
+        {
+            status.push_str(", throttling upstream");
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_and_draining() {
+        let start = Instant::now();
+        let mut backlog = Backlog::new(Some(2));
+        backlog.add();
+        assert!(!backlog.is_full(start));
+        backlog.add();
+        assert!(backlog.is_full(start));
+        assert_eq!(backlog.status(start), "buffered 2/2, throttling upstream");
+        backlog.take();
+        assert!(!backlog.is_full(start));
+        assert_eq!(backlog.status(start + SHOWN_FOR), "buffered 1/2");
+        assert!(!Backlog::new(None).is_full(start));
+    }
+}
@@ -0,0 +1,112 @@
+//! Values of the filter's capture group, each listed once with how often it turned up,
+//! such as every distinct error code seen.
+
+use std::collections::HashMap;
+
+/// Distinct values kept; new ones are only counted past this.
+const LIMIT: usize = 10_000;
+
+#[derive(Debug, Clone, Default)]
+pub struct Captures {
+    /// The filter the values were captured by; they start again when it changes.
+    filter: String,
+    /// Values in the order first seen, with their counts.
+    values: Vec<(String, u64)>,
+    index: HashMap<String, usize>,
+    /// Values not kept for being past `LIMIT`.
+    pub overflow: u64,
+    pub selected: usize,
+}
+
+impl Captures {
+    /// Count `values` captured from a line by `filter`.
+    pub fn record(&mut self, filter: &str, values: Vec<&str>) {
+        if self.filter != filter {
+            *self = Captures {
+                filter: filter.to_string(),
+                ..Captures::default()
+            };
+        }
+        for value in values {
+            match self.index.get(value) {
+                Some(&at) => self.values[at].1 += 1,
+                None if self.values.len() == LIMIT => self.overflow += 1,
+                None => {
+                    self.index.insert(value.to_string(), self.values.len());
+                    self.values.push((value.to_string(), 1));
+                }
+            }
+        }
+    }
+
+    /// The selected value, if it was captured by `filter`.
+    pub fn selected(&self, filter: &str) -> Option<&str> {
+        (self.filter == filter)
+            .then(|| self.values.get(self.selected))
+            .flatten()
+            .map(|(value, _)| value.as_str())
+    }
+
+    pub fn select(&mut self, down: bool) {
+        self.selected = match down {
+            true => (self.selected + 1).min(self.values.len().saturating_sub(1)),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    /// The panel listing the values captured by `filter` with their counts, scrolled to
+    /// keep the selected one, in reverse video, within `height` lines.
+    pub fn panel(&self, filter: &str, height: usize) -> Vec<String> {
+        let mut panel = vec![format!(
+            "\x1B[1mCaptures of {}\x1B[0m  (j/k select, y copies, c closes)",
+            filter
+        )];
+        if self.filter != filter || self.values.is_empty() {
+            panel.push(
+                "\x1B[2mNothing captured yet; a regex filter with a group such as \
+                 code=(\\d+) lists each value\x1B[0m"
+                    .to_string(),
+            );
+            return panel;
+        }
+        let rows = height.saturating_sub(2).max(1);
+        let top = self.selected.saturating_sub(rows - 1);
+        for (index, (value, count)) in self.values.iter().enumerate().skip(top).take(rows) {
+            let line = format!("{:>8}  {}", crate::progress::format_count(*count), value);
+            panel.push(match index == self.selected {
+                true => format!("\x1B[7m{}\x1B[0m", line),
+                false => line,
+            });
+        }
+        if self.overflow > 0 {
+            panel.push(format!(
+                "\x1B[2m{} more not listed\x1B[0m",
+                crate::progress::format_count(self.overflow)
+            ));
+        }
+        panel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_values() {
+        let mut captures = Captures::default();
+        captures.record("code=(\\d+)", vec!["500"]);
+        captures.record("code=(\\d+)", vec!["404", "500"]);
+        assert_eq!(
+            captures.values,
+            [("500".to_string(), 2), ("404".to_string(), 1)]
+        );
+        captures.select(true);
+        captures.select(true);
+        assert_eq!(captures.selected("code=(\\d+)"), Some("404"));
+        assert_eq!(captures.selected("other"), None);
+        captures.record("status=(\\d+)", vec!["200"]);
+        assert_eq!(captures.values, [("200".to_string(), 1)]);
+        assert_eq!(captures.selected, 0);
+    }
+}
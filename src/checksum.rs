@@ -0,0 +1,112 @@
+//! `--checksum`: stdin copied to stdout byte for byte while a SHA-256 of it is kept, so
+//! pipe_tools can sit in a data pipeline and prove it changed nothing.
+
+use sha2::Digest;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// SHA-256 over data fed to it in pieces.
+#[derive(Debug, Clone, Default)]
+pub struct Sha256 {
+    hash: sha2::Sha256,
+    /// Bytes fed in so far.
+    pub length: u64,
+}
+
+impl Sha256 {
+    pub fn update(&mut self, data: &[u8]) {
+        self.length += data.len() as u64;
+        self.hash.update(data);
+    }
+
+    /// The digest of everything fed in so far, as lowercase hex.
+    pub fn hex(&self) -> String {
+        self.hash
+            .clone()
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Reads through to `inner`, hashing every byte read and copying it to `copy` if given.
+pub struct Passthrough<R> {
+    inner: R,
+    copy: Option<io::Stdout>,
+    hash: Arc<Mutex<Sha256>>,
+}
+
+impl<R> Passthrough<R> {
+    pub fn new(inner: R, copy: Option<io::Stdout>, hash: Arc<Mutex<Sha256>>) -> Self {
+        Passthrough { inner, copy, hash }
+    }
+}
+
+/// Hash `data` and copy it on; once the copy fails, as when the next program in the
+/// pipeline exits, only the hash is kept up.
+fn pass(copy: &mut Option<io::Stdout>, hash: &Mutex<Sha256>, data: &[u8]) {
+    hash.lock().unwrap().update(data);
+    if let Some(out) = copy {
+        if out.write_all(data).and_then(|_| out.flush()).is_err() {
+            *copy = None;
+        }
+    }
+}
+
+impl<R: Read> Read for Passthrough<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        pass(&mut self.copy, &self.hash, &buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Passthrough<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        // Still buffered, so this does not read.
+        if let Ok(buffered) = self.inner.fill_buf() {
+            pass(&mut self.copy, &self.hash, &buffered[..amount]);
+        }
+        self.inner.consume(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            Sha256::default().hex(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let mut hash = Sha256::default();
+        hash.update(b"ab");
+        hash.update(b"c");
+        assert_eq!(
+            hash.hex(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let mut hash = Sha256::default();
+        hash.update(&[b'a'; 1000]);
+        assert_eq!(
+            hash.hex(),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_passthrough_hashes_lines_read() {
+        let hash = Arc::new(Mutex::new(Sha256::default()));
+        let mut input = Passthrough::new(&b"abc\r\nlast"[..], None, hash.clone());
+        let mut line = String::new();
+        while input.read_line(&mut line).unwrap() > 0 {}
+        assert_eq!(hash.lock().unwrap().length, 9);
+    }
+}
@@ -0,0 +1,700 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// What pipe_tools should do with its input.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Command {
+    /// Echo lines with highlighting.
+    #[default]
+    Run,
+    /// Show a live table of match counts instead of the lines themselves.
+    Stats,
+    /// Play back a file written by `--record` instead of reading stdin.
+    Replay(PathBuf),
+    /// Show the lines only one of two recordings has.
+    Diff(PathBuf, PathBuf),
+    /// Show what a `--share` session draws, read-only.
+    Mirror(PathBuf),
+    /// Rerun a shell command on an interval, marking what changed.
+    Watch(String),
+}
+
+/// An input read alongside stdin.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// A file, followed as it grows.
+    File(PathBuf),
+    /// A shell command, with stderr merged into stdout.
+    Exec(String),
+}
+
+/// Options collected from the command line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    /// Exit after this much time has passed, regardless of activity.
+    pub timeout: Option<Duration>,
+    /// Exit once no input line has arrived for this long.
+    pub idle_timeout: Option<Duration>,
+    /// Ring the bell and notify once no input line has arrived for this long.
+    pub alert_idle: Option<Duration>,
+    /// Write the scrollback to a snapshot file once no input line has arrived for this long.
+    pub snapshot_idle: Option<Duration>,
+    /// Config file to load and watch instead of the default location.
+    pub config: Option<PathBuf>,
+    /// Use the alternate screen; decided automatically when `None`.
+    pub alt_screen: Option<bool>,
+    /// Make URLs and file references clickable; decided automatically when `None`.
+    pub hyperlinks: Option<bool>,
+    /// Keep terminal traffic to a minimum for slow or high-latency links.
+    pub low_bandwidth: bool,
+    /// Keep the terminal title showing the match count.
+    pub title: bool,
+    /// Show new lines on a lit background that fades over a few seconds.
+    pub fade: bool,
+    /// Show a pv-style throughput gauge in the status area.
+    pub progress: bool,
+    /// Show lines and bytes a second through the printer in the status area.
+    pub meter: bool,
+    /// Show total bytes, lines and elapsed time, and print them on exit.
+    pub totals: bool,
+    /// Address to serve Prometheus metrics on (requires the `metrics` feature).
+    pub metrics: Option<String>,
+    /// Numeric field to aggregate: a regex, or a JSON path starting with `.`.
+    pub field: Option<String>,
+    /// Field whose distinct values are counted, in the same syntax as `field`.
+    pub distinct: Option<String>,
+    /// Fields shown as columns in the table view, instead of those of the first record.
+    pub columns: Vec<String>,
+    /// Patterns whose first capture group is the ID pairing start and end lines.
+    pub span_start: Option<String>,
+    pub span_end: Option<String>,
+    /// Where to write the session summary on exit; `-` is stderr.
+    pub stats_json: Option<PathBuf>,
+    /// Save input lines and filter changes, with their timing, to this file.
+    pub record: Option<PathBuf>,
+    /// Start with JSON lines pretty-printed (toggled with the pretty key).
+    pub pretty_json: bool,
+    /// logfmt keys to show as aligned columns.
+    pub logfmt: Vec<String>,
+    /// Show only these fields, split by `delimiter` (tab by default), as cut(1) does.
+    pub delimiter: Option<String>,
+    pub fields: Option<String>,
+    /// Field separator when the input is CSV (`,`) or TSV (tab).
+    pub csv: Option<char>,
+    /// Color every other CSV column.
+    pub bands: bool,
+    /// Decode and color syslog priorities.
+    pub syslog: bool,
+    /// Least severe syslog severity to show.
+    pub severity: Option<String>,
+    /// Comma-separated syslog facilities to show.
+    pub facility: Option<String>,
+    /// Pattern matching the first line of each multi-line record, or `timestamp`.
+    pub record_start: Option<String>,
+    /// Lines handled as errors are: shown ahead of those waiting and never dropped by
+    /// --max-rate.
+    pub priority: Option<String>,
+    /// Hide lines below this log level.
+    pub level: Option<String>,
+    /// Count lines of each log level in a status widget.
+    pub level_counts: bool,
+    /// Timezone to rewrite displayed timestamps into.
+    pub tz: Option<String>,
+    /// strftime format for rewritten timestamps.
+    pub time_format: Option<String>,
+    /// Save everything drawn on the terminal to this asciicast v2 file.
+    pub cast: Option<PathBuf>,
+    /// Unix socket that `mirror` sessions attach to.
+    pub share: Option<PathBuf>,
+    /// Commands run at startup, such as `filter ERROR` or `mode invert`.
+    pub commands: Vec<String>,
+    /// File the filter, terms and view settings are restored from and saved to on exit.
+    pub session: Option<PathBuf>,
+    /// Keep the filter history in a file, so Up recalls filters of earlier sessions.
+    pub save_history: bool,
+    /// File holding the byte offset stdin has been handled up to, to resume from.
+    pub cursor: Option<PathBuf>,
+    /// Patterns blanked out of lines before `diff` or `--known` compares them.
+    pub ignore: Vec<String>,
+    /// Reference log whose lines are hidden, so only new ones show.
+    pub known: Option<PathBuf>,
+    /// Playback speed multiplier for `replay`.
+    pub speed: f64,
+    /// Prefix each line shown with its number in the input.
+    pub line_numbers: bool,
+    /// Start matching the filter case-insensitively, always or unless it has capitals.
+    pub ignore_case: bool,
+    pub smart_case: bool,
+    /// Start matching the filter only as a whole word.
+    pub whole_word: bool,
+    /// Start matching the filter fuzzily.
+    pub fuzzy: bool,
+    /// Terms watched alongside the filter from the start, each in its own color; those
+    /// starting with `!` exclude lines instead, as from --exclude.
+    pub terms: Vec<String>,
+    /// Pin the first line as a header instead of printing it.
+    pub header: bool,
+    /// How often `watch` reruns its command.
+    pub interval: Option<Duration>,
+    /// Exit once this many lines have been shown.
+    pub head: Option<usize>,
+    /// Exit, or pause with `pause_at_max`, once this many lines matching the filter have
+    /// been shown.
+    pub max_count: Option<usize>,
+    pub pause_at_max: bool,
+    /// Show only this many of the last lines, once the input ends.
+    pub tail: Option<usize>,
+    /// Show at most this many lines a second, counting the rest.
+    pub max_rate: Option<usize>,
+    /// Lines read ahead of those shown before stdin is left unread.
+    pub buffer: Option<usize>,
+    /// Start in step mode, showing a line per key press.
+    pub step: bool,
+    /// Start showing only lines that match the filter, as grep does.
+    pub only_matching_lines: bool,
+    /// Unmatched lines still shown before and after each match while they are hidden.
+    pub before_context: usize,
+    pub after_context: usize,
+    /// Color theme, overriding the config file's.
+    pub theme: Option<String>,
+    /// Show secrets instead of masking them.
+    pub no_redact: bool,
+    /// Show every repeat of a line rather than folding bursts.
+    pub no_fold: bool,
+    /// Copy stdin to stdout unchanged, showing lines only on the terminal, and print a
+    /// SHA-256 of it at exit.
+    pub checksum: bool,
+    /// Inputs read alongside stdin, in the order given.
+    pub sources: Vec<Source>,
+}
+
+pub const USAGE: &str = "\
+usage: pipe_tools [stats | replay FILE | diff A B | watch COMMAND | mirror SOCKET]
+                  [options]
+
+commands:
+  stats                     show live match counts per pattern instead of
+                            echoing lines
+  replay FILE               play back a --record file with its original
+                            timing; in normal mode ] and [ change the speed
+                            and space pauses
+  diff A B                  print the lines of recording A without a match in
+                            B (marked -) and of B without a match in A
+                            (marked +); numbers and UUIDs are ignored, and
+                            exits with 1 if there are any
+  watch [-n DURATION] COMMAND
+                            run COMMAND with sh every DURATION (default 2s)
+                            and show its output in place, with what changed
+                            since the last run in reverse video
+  mirror SOCKET             show what the session started with --share SOCKET
+                            draws, read-only; best in a terminal of the same
+                            size
+
+options:
+  --timeout DURATION        exit after DURATION in total
+  --idle-timeout DURATION   exit after DURATION without input
+  --alert-idle DURATION     ring the bell, send a desktop notification and
+                            flag the status area once there has been no input
+                            for DURATION; again after input resumes
+  --snapshot-idle DURATION  write the buffer to pipe_tools-snapshot.log once
+                            there has been no input for DURATION, keeping the
+                            earlier ones as pipe_tools-snapshot.1.log to .5
+  --config FILE             read settings from FILE
+                            (default ~/.config/pipe_tools/config)
+  --no-redact               show secrets (AWS keys, bearer tokens, passwords,
+                            [redact] rules in the config) instead of masking
+                            them on screen and in recordings and saves
+  --theme NAME              color theme: default, or deuteranopia, protanopia
+                            or tritanopia for color-blind-safe colors with
+                            symbols marking syslog severities
+  --alt-screen              draw on the alternate screen (default inside
+                            tmux and screen)
+  --no-alt-screen           draw on the normal screen
+  --hyperlinks              make URLs and file:line references clickable
+                            (default on terminals known to support OSC 8)
+  --no-hyperlinks           never add hyperlinks
+  --title                   show the number of matches in the terminal title,
+                            so a background tab shows when something turns up
+  --fade                    light up new lines and fade them out over a few
+                            seconds
+  --low-bandwidth           redraw the status area at most once a second and
+                            only send what changed
+  --progress                show bytes, rate, lines and ETA (for file input)
+  --meter                   show lines/s and bytes/s over the last few
+                            seconds, counting every source
+  --totals                  show the bytes and lines seen from every source
+                            and the time since start, and print them to the
+                            terminal on exit
+  --metrics ADDR            serve Prometheus metrics on ADDR, e.g.
+                            127.0.0.1:9100 (needs the metrics feature)
+  --level LEVEL             hide lines below LEVEL (trace, debug, info, warn,
+                            error, fatal); + and - adjust it in normal mode
+  --level-counts            count lines of each level in the status area, such
+                            as E:12 W:340 I:12k; L in normal mode shows one
+                            level at a time
+  --record-start REGEX      join lines that do not match REGEX onto the record
+                            before them, e.g. for stack traces; 'timestamp'
+                            starts a record at each line with a date or time
+  --tz ZONE                 show timestamps in ZONE: local, UTC, an IANA name
+                            such as Europe/Berlin or an offset like +05:30
+                            (timestamps without an offset are read as UTC)
+  --time-format FORMAT      strftime format for rewritten timestamps
+                            (default %Y-%m-%d %H:%M:%S%.f %Z; implies --tz local)
+                            %c, %x and %X follow LC_TIME; 'locale' is %x %X
+  --head N                  exit after showing N lines
+  -m, --max-count N         exit after showing N lines that match the filter
+  --pause-at-max            with --max-count, pause output instead of exiting
+  --tail N                  hold lines back and show only the last N once the
+                            input ends
+  --max-rate N              show at most N lines a second; the lines dropped
+                            are counted in their place; lines at level error
+                            or above are never dropped
+  --priority REGEX          treat lines matching REGEX as errors are: shown
+                            ahead of lines waiting behind a pause or a slow
+                            view, and never dropped by --max-rate
+  --buffer N                read at most N lines ahead of those shown; past
+                            that stdin is left unread so a slow or paused
+                            view blocks the producer instead of filling memory
+  --no-fold                 show every repeat of a line; by default a burst
+                            of repeats less than a second apart is folded into
+                            one summary (e in normal mode shows them, F9
+                            toggles folding)
+  --only-matching-lines     print only lines matching the filter, as grep
+                            does, rather than highlighting it in every line
+                            (F4 toggles)
+  -A, --after-context N     while unmatched lines are hidden, still show the N
+                            lines after each match
+  -B, --before-context N    ... and the N lines before each match
+  -C, --context N           ... and N lines on both sides
+  --step                    start in step mode: in normal mode Enter shows the
+                            next line and f the next page (F8 toggles)
+  -i, --ignore-case         match the filter ignoring case (F2 toggles); the
+                            text is highlighted as it appears in the line
+  --smart-case              ignore case unless the filter has a capital letter
+                            in it (F10 toggles)
+  -w, --whole-word          match the filter only as a whole word, so err
+                            does not match in transferred (F12 toggles)
+  --fuzzy                   match the filter's characters in order with
+                            anything between, as fzf does, so cnterr matches
+                            connection_error; fuzzy in [function_keys] toggles
+  --term TEXT               also match and highlight TEXT in a color of its
+                            own; may be repeated (t in normal mode keeps the
+                            filter as a term, T drops the last one)
+  --exclude TEXT            never show lines with TEXT, as for a filter or
+                            term starting with !; may be repeated
+  -n, --line-numbers        number lines as they came in, so hidden lines leave
+                            gaps; after watch, -n is the interval instead, as
+                            in watch(1)
+  --pretty-json             pretty-print JSON lines (Ctrl+P toggles)
+  --logfmt KEYS             show these comma-separated logfmt keys as aligned
+                            columns, e.g. time,level,msg
+  --csv, --tsv              align CSV/TSV columns under the first record, which
+                            is pinned as a header; a filter such as status:500
+                            only highlights in the named column
+  --bands                   color every other CSV/TSV column
+  -d, --delimiter DELIM     with -f, split lines on DELIM (default tab)
+  -f, --fields LIST         show only these fields, such as 2,5 or 1,3-4, as
+                            cut does; the filter still sees the whole line
+  --header                  pin the first line, such as the header of ps
+                            output, above the status area; p in normal mode
+                            pins the latest or selected line instead
+  --syslog                  decode <PRI> prefixes and color lines by severity
+  --severity NAME           with --syslog, show only NAME (e.g. warning) and
+                            more severe messages
+  --facility NAMES          with --syslog, show only these facilities, e.g.
+                            auth,daemon
+  --field SPEC              show min/max/avg/p95 of a number in each line;
+                            SPEC is a regex (first group is the number) or
+                            a path such as .http.latency into JSON lines or
+                            access log fields (.status, .path, .bytes, ...);
+                            container logs add .time and .stream, and GELF,
+                            Logstash and OpenTelemetry records have .time,
+                            .level, .message, .host and .service
+  --distinct SPEC           count the distinct values of a field (same SPEC
+                            syntax as --field), e.g. unique client IPs
+  --columns FIELDS          fields of JSON and logfmt records to show in the
+                            table view (v in normal mode), such as
+                            time,level,http.status; by default the first
+                            record's
+  --span-start REGEX        with --span-end, time each start/end pair of lines;
+  --span-end REGEX          both capture the correlating ID, e.g. 'req=(\\w+)'
+  --record FILE             save every input line and filter change, with its
+                            time and the input it came from, to FILE
+  --cast FILE               save what is drawn, highlighting and status area
+                            included, as an asciicast v2 file for asciinema
+  --share SOCKET            let `pipe_tools mirror SOCKET` in another terminal
+                            watch this session
+  --speed FACTOR            replay FACTOR times faster, e.g. 4x or 0.5x
+  --file PATH               also read PATH, following it as it grows; its
+                            lines are labelled with its name (repeatable)
+  --exec COMMAND            also read the output of COMMAND, run with sh and
+                            labelled with its first word (repeatable); o in
+                            normal mode lists the sources to pause or mute
+  --checksum                copy stdin to stdout byte for byte, highlighting
+                            only on the terminal, and print its SHA-256 to
+                            stderr at exit
+  --known FILE              hide lines that also appear in FILE, such as the
+                            log of a good run, so only new output shows;
+                            numbers and UUIDs are ignored
+  --ignore REGEX            with diff, --known or F11 (dimming lines seen
+                            before), also ignore what REGEX matches, e.g. a
+                            host name; may be repeated
+  --cursor FILE             with stdin redirected from a file, start at the
+                            offset saved in FILE and keep it updated, so a
+                            restart resumes where the last run stopped
+  --session FILE            restore the filter and its match options, kept
+                            terms, patterns switched off, level and view from
+                            FILE and save them there on exit
+  --cmd COMMAND             run COMMAND at startup, after --session; one of
+                            'filter TEXT', 'mode TOGGLE [on|off]' (a toggle
+                            named as for function keys in the config),
+                            'pause', 'resume' or 'level LEVEL|all'
+                            (repeatable, run in order)
+  --save-history            keep the filters Up and Down recall across
+                            sessions, in ~/.local/share/pipe_tools/history
+  --stats-json FILE         on exit, write a JSON summary of the session to
+                            FILE (- for stderr)
+  -h, --help                show this help
+
+DURATION is a number with an optional unit: ms, s (default), m or h.";
+
+/// Parse the arguments (without the program name) into `Options`.
+pub fn parse_args<I>(args: I) -> Result<Options, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Options {
+        speed: 1.0,
+        ..Options::default()
+    };
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`.
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            // Some short flags take their value attached, as in -d, -f2,5 or -C3.
+            _ if arg.len() > 2
+                && ["-d", "-f", "-A", "-B", "-C"].contains(&arg.get(..2).unwrap_or("")) =>
+            {
+                (arg[..2].to_string(), Some(arg[2..].to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| -> Result<String, String> {
+            match inline_value.clone() {
+                Some(value) => Ok(value),
+                None => args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", name)),
+            }
+        };
+
+        match flag.as_str() {
+            "--timeout" => options.timeout = Some(parse_duration(&value("--timeout")?)?),
+            "--idle-timeout" => {
+                options.idle_timeout = Some(parse_duration(&value("--idle-timeout")?)?)
+            }
+            "--alert-idle" => options.alert_idle = Some(parse_duration(&value("--alert-idle")?)?),
+            "--snapshot-idle" => {
+                options.snapshot_idle = Some(parse_duration(&value("--snapshot-idle")?)?)
+            }
+            "--config" => options.config = Some(PathBuf::from(value("--config")?)),
+            "--alt-screen" => options.alt_screen = Some(true),
+            "--no-alt-screen" => options.alt_screen = Some(false),
+            "--hyperlinks" => options.hyperlinks = Some(true),
+            "--no-hyperlinks" => options.hyperlinks = Some(false),
+            "--low-bandwidth" => options.low_bandwidth = true,
+            "--title" => options.title = true,
+            "--fade" => options.fade = true,
+            "--progress" => options.progress = true,
+            "--meter" => options.meter = true,
+            "--totals" => options.totals = true,
+            "--metrics" => options.metrics = Some(value("--metrics")?),
+            "--level" => options.level = Some(value("--level")?),
+            "--level-counts" => options.level_counts = true,
+            "--record-start" => options.record_start = Some(value("--record-start")?),
+            "--priority" => options.priority = Some(value("--priority")?),
+            "--tz" => options.tz = Some(value("--tz")?),
+            "--time-format" => options.time_format = Some(value("--time-format")?),
+            "-n" | "--interval" if matches!(options.command, Command::Watch(_)) => {
+                options.interval = Some(parse_duration(&value("--interval")?)?)
+            }
+            "-n" | "--line-numbers" => options.line_numbers = true,
+            "-i" | "--ignore-case" => options.ignore_case = true,
+            "--smart-case" => options.smart_case = true,
+            "-w" | "--whole-word" => options.whole_word = true,
+            "--fuzzy" => options.fuzzy = true,
+            "--term" => options.terms.push(value("--term")?),
+            "--exclude" => options.terms.push(format!("!{}", value("--exclude")?)),
+            "--head" => options.head = Some(parse_count("--head", &value("--head")?)?),
+            "-m" | "--max-count" => {
+                // Zero would leave nothing to show before exiting or pausing.
+                match parse_count("--max-count", &value("--max-count")?)? {
+                    0 => return Err("--max-count must be at least 1".to_string()),
+                    count => options.max_count = Some(count),
+                }
+            }
+            "--pause-at-max" => options.pause_at_max = true,
+            "--tail" => options.tail = Some(parse_count("--tail", &value("--tail")?)?),
+            "--step" => options.step = true,
+            "--only-matching-lines" => options.only_matching_lines = true,
+            "-A" | "--after-context" => {
+                options.after_context = parse_count("--after-context", &value("-A")?)?
+            }
+            "-B" | "--before-context" => {
+                options.before_context = parse_count("--before-context", &value("-B")?)?
+            }
+            "-C" | "--context" => {
+                let lines = parse_count("--context", &value("-C")?)?;
+                (options.before_context, options.after_context) = (lines, lines);
+            }
+            "--theme" => options.theme = Some(value("--theme")?),
+            "--no-redact" => options.no_redact = true,
+            "--no-fold" => options.no_fold = true,
+            "--checksum" => options.checksum = true,
+            "--file" => options
+                .sources
+                .push(Source::File(PathBuf::from(value("--file")?))),
+            "--exec" => options.sources.push(Source::Exec(value("--exec")?)),
+            "--max-rate" => {
+                options.max_rate = Some(parse_count("--max-rate", &value("--max-rate")?)?)
+            }
+            "--buffer" => options.buffer = Some(parse_count("--buffer", &value("--buffer")?)?),
+            "--pretty-json" => options.pretty_json = true,
+            "--logfmt" => {
+                options.logfmt = value("--logfmt")?
+                    .split(',')
+                    .map(|key| key.trim().to_string())
+                    .filter(|key| !key.is_empty())
+                    .collect()
+            }
+            "--csv" => options.csv = Some(','),
+            "--tsv" => options.csv = Some('\t'),
+            "--bands" => options.bands = true,
+            "-d" | "--delimiter" => options.delimiter = Some(value("--delimiter")?),
+            "-f" | "--fields" => options.fields = Some(value("--fields")?),
+            "--header" => options.header = true,
+            "--syslog" => options.syslog = true,
+            "--severity" => options.severity = Some(value("--severity")?),
+            "--facility" => options.facility = Some(value("--facility")?),
+            "--field" => options.field = Some(value("--field")?),
+            "--distinct" => options.distinct = Some(value("--distinct")?),
+            "--columns" => {
+                options.columns = value("--columns")?
+                    .split(',')
+                    .map(|column| column.trim().trim_start_matches('.').to_string())
+                    .filter(|column| !column.is_empty())
+                    .collect()
+            }
+            "--span-start" => options.span_start = Some(value("--span-start")?),
+            "--span-end" => options.span_end = Some(value("--span-end")?),
+            "--record" => options.record = Some(PathBuf::from(value("--record")?)),
+            "--cast" => options.cast = Some(PathBuf::from(value("--cast")?)),
+            "--share" => options.share = Some(PathBuf::from(value("--share")?)),
+            "--cursor" => options.cursor = Some(PathBuf::from(value("--cursor")?)),
+            "--session" => options.session = Some(PathBuf::from(value("--session")?)),
+            "--cmd" => options.commands.push(value("--cmd")?),
+            "--save-history" => options.save_history = true,
+            "--ignore" => options.ignore.push(value("--ignore")?),
+            "--known" => options.known = Some(PathBuf::from(value("--known")?)),
+            "--speed" => options.speed = parse_speed(&value("--speed")?)?,
+            "--stats-json" => options.stats_json = Some(PathBuf::from(value("--stats-json")?)),
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            "stats" if options.command == Command::Run => options.command = Command::Stats,
+            "replay" if options.command == Command::Run => {
+                options.command = Command::Replay(PathBuf::from(value("replay")?))
+            }
+            "watch" if options.command == Command::Run => {
+                options.command = Command::Watch(String::new())
+            }
+            // The command to watch is the first argument that is not an option.
+            _ if options.command == Command::Watch(String::new()) && !arg.starts_with('-') => {
+                options.command = Command::Watch(arg.clone())
+            }
+            "diff" if options.command == Command::Run => {
+                let left = PathBuf::from(value("diff")?);
+                options.command = Command::Diff(left, PathBuf::from(value("diff")?))
+            }
+            "mirror" if options.command == Command::Run => {
+                options.command = Command::Mirror(PathBuf::from(value("mirror")?))
+            }
+            _ => return Err(format!("unknown argument '{}'\n\n{}", arg, USAGE)),
+        }
+    }
+
+    if options.command == Command::Watch(String::new()) {
+        return Err("watch requires a command".to_string());
+    }
+    if options.span_start.is_some() != options.span_end.is_some() {
+        return Err("--span-start and --span-end must be given together".to_string());
+    }
+    Ok(options)
+}
+
+/// Parse a duration such as `30`, `30s`, `250ms`, `5m` or `1h`.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", text))?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("invalid duration unit in '{}'", text)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid duration '{}'", text))
+}
+
+/// Parse a number of lines for `flag`.
+fn parse_count(flag: &str, text: &str) -> Result<usize, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("{}: invalid number of lines '{}'", flag, text))
+}
+
+/// Parse a playback speed such as `4x`, `0.5x` or `2`.
+pub fn parse_speed(text: &str) -> Result<f64, String> {
+    let number = text.trim().trim_end_matches(['x', 'X']);
+    match number.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("invalid speed '{}'", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1.5h").unwrap(), Duration::from_secs(5400));
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("99999999999999999999h").is_err());
+    }
+
+    #[test]
+    fn test_parse_timeouts() {
+        let options = parse_args(args(&["--timeout", "10", "--idle-timeout=2s"])).unwrap();
+        assert_eq!(options.timeout, Some(Duration::from_secs(10)));
+        assert_eq!(options.idle_timeout, Some(Duration::from_secs(2)));
+        assert!(parse_args(args(&["--timeout"])).is_err());
+        assert!(parse_args(args(&["--bogus"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let options = parse_args(args(&["--columns", "time, .http.status"])).unwrap();
+        assert_eq!(options.columns, ["time", "http.status"]);
+    }
+
+    #[test]
+    fn test_parse_fuzzy() {
+        assert!(parse_args(args(&["--fuzzy"])).unwrap().fuzzy);
+    }
+
+    #[test]
+    fn test_parse_whole_word() {
+        assert!(parse_args(args(&["-w"])).unwrap().whole_word);
+    }
+
+    #[test]
+    fn test_parse_snapshot_idle() {
+        let options = parse_args(args(&["--snapshot-idle", "30s"])).unwrap();
+        assert_eq!(options.snapshot_idle, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_parse_context() {
+        let options = parse_args(args(&["-C", "2", "-A5"])).unwrap();
+        assert_eq!((options.before_context, options.after_context), (2, 5));
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let options = parse_args(args(&["-d,", "-f2,5"])).unwrap();
+        assert_eq!(options.delimiter.as_deref(), Some(","));
+        assert_eq!(options.fields.as_deref(), Some("2,5"));
+    }
+
+    #[test]
+    fn test_parse_alert_idle() {
+        let options = parse_args(args(&["--alert-idle", "5m"])).unwrap();
+        assert_eq!(options.alert_idle, Some(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn test_parse_head_and_tail() {
+        let options = parse_args(args(&["--head", "10", "--tail=5"])).unwrap();
+        assert_eq!((options.head, options.tail), (Some(10), Some(5)));
+        assert!(parse_args(args(&["--head", "ten"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_line_numbers() {
+        assert!(parse_args(args(&["-n"])).unwrap().line_numbers);
+        assert!(parse_args(args(&["--line-numbers"])).unwrap().line_numbers);
+        assert!(
+            !parse_args(args(&["watch", "-n", "5", "top"]))
+                .unwrap()
+                .line_numbers
+        );
+    }
+
+    #[test]
+    fn test_parse_max_count() {
+        let options = parse_args(args(&["-m", "1", "--pause-at-max"])).unwrap();
+        assert_eq!((options.max_count, options.pause_at_max), (Some(1), true));
+        assert!(parse_args(args(&["--max-count", "0"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_args(args(&[])).unwrap().command, Command::Run);
+        let options = parse_args(args(&["stats", "--timeout", "1"])).unwrap();
+        assert_eq!(options.command, Command::Stats);
+        assert!(parse_args(args(&["stats", "stats"])).is_err());
+        assert!(parse_args(args(&["--span-start", "a(.)"])).is_err());
+        let options = parse_args(args(&["replay", "s.rec", "--speed", "4x"])).unwrap();
+        assert_eq!(options.command, Command::Replay(PathBuf::from("s.rec")));
+        assert_eq!(options.speed, 4.0);
+        assert!(parse_args(args(&["replay"])).is_err());
+        assert!(parse_speed("0x").is_err());
+        let options = parse_args(args(&["diff", "a.rec", "b.rec", "--ignore", "id=\\d+"])).unwrap();
+        assert_eq!(
+            options.command,
+            Command::Diff(PathBuf::from("a.rec"), PathBuf::from("b.rec"))
+        );
+        assert_eq!(options.ignore, ["id=\\d+"]);
+        assert!(parse_args(args(&["diff", "a.rec"])).is_err());
+        let options = parse_args(args(&["mirror", "/tmp/pt.sock"])).unwrap();
+        assert_eq!(
+            options.command,
+            Command::Mirror(PathBuf::from("/tmp/pt.sock"))
+        );
+        let options = parse_args(args(&["watch", "-n", "5", "kubectl get pods"])).unwrap();
+        assert_eq!(
+            options.command,
+            Command::Watch("kubectl get pods".to_string())
+        );
+        assert_eq!(options.interval, Some(Duration::from_secs(5)));
+        assert!(parse_args(args(&["watch", "-n", "5"])).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Copying text to the system clipboard.
+//!
+//! Local desktops use the platform clipboard tool. Over SSH there is no local clipboard
+//! to talk to, so the text is handed to the terminal emulator with an OSC 52 escape
+//! sequence instead; WSL uses the Windows `clip.exe`.
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// How text reaches the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    /// Ask the terminal emulator to set the clipboard.
+    Osc52,
+    /// Pipe the text into a helper program.
+    Command(&'static str, &'static [&'static str]),
+}
+
+impl Method {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Method::Osc52 => "OSC 52",
+            Method::Command(program, _) => program,
+        }
+    }
+}
+
+/// Pick the clipboard mechanism for the current environment.
+pub fn detect() -> Method {
+    let set = |name: &str| env::var_os(name).is_some_and(|value| !value.is_empty());
+
+    if set("WSL_DISTRO_NAME") || is_wsl_kernel() {
+        Method::Command("clip.exe", &[])
+    } else if set("SSH_TTY") || set("SSH_CONNECTION") {
+        Method::Osc52
+    } else if cfg!(target_os = "macos") {
+        Method::Command("pbcopy", &[])
+    } else if cfg!(windows) {
+        Method::Command("clip", &[])
+    } else if set("WAYLAND_DISPLAY") {
+        Method::Command("wl-copy", &[])
+    } else if set("DISPLAY") {
+        Method::Command("xclip", &["-selection", "clipboard"])
+    } else {
+        Method::Osc52
+    }
+}
+
+fn is_wsl_kernel() -> bool {
+    fs::read_to_string("/proc/version")
+        .map(|version| version.to_lowercase().contains("microsoft"))
+        .unwrap_or(false)
+}
+
+/// Copy `text` to the clipboard, returning the mechanism that was used.
+///
+/// `term_out` receives the OSC 52 sequence, which is also the fallback when the helper
+/// program is missing or fails.
+pub fn copy<W: Write>(text: &str, term_out: &mut W) -> io::Result<Method> {
+    let method = detect();
+    if let Method::Command(program, args) = method {
+        if run_command(program, args, text).is_ok() {
+            return Ok(method);
+        }
+    }
+    write!(term_out, "{}", osc52(text, env::var_os("TMUX").is_some()))?;
+    term_out.flush()?;
+    Ok(Method::Osc52)
+}
+
+fn run_command(program: &str, args: &[&str], text: &str) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} failed", program)))
+    }
+}
+
+/// The OSC 52 sequence setting the clipboard to `text`.
+///
+/// tmux swallows unknown sequences unless they are wrapped in its DCS passthrough.
+fn osc52(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1B]52;c;{}\x07", base64(text.as_bytes()));
+    if tmux {
+        format!("\x1BPtmux;\x1B{}\x1B\\", sequence)
+    } else {
+        sequence
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let group = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52("hi", false), "\x1B]52;c;aGk=\x07");
+        assert_eq!(osc52("hi", true), "\x1BPtmux;\x1B\x1B]52;c;aGk=\x07\x1B\\");
+    }
+}
@@ -0,0 +1,116 @@
+//! Commands such as `filter ERROR` or `mode invert`, typed at the `:` prompt or given
+//! with `--cmd` to set up a session from a script or shell history.
+
+use crate::config::Toggle;
+use crate::level::Level;
+use crate::theme::Theme;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Replace the filter.
+    Filter(String),
+    /// Keep a term matched and highlighted beside the filter.
+    AddTerm(String),
+    /// Switch a toggle on or off; `pause` and `resume` are the pause toggle.
+    Mode(Toggle, bool),
+    /// Hide lines below a level, or show every level with `None`.
+    Level(Option<Level>),
+    /// Write the scrollback to a new file, or to a dated one with `None`.
+    Save(Option<PathBuf>),
+    Theme(Theme),
+    /// Show at most this many lines a second, or any number with `None`.
+    MaxRate(Option<usize>),
+}
+
+/// Parse one command: `filter TEXT`, `filter add TEXT`, `mode TOGGLE [on|off]`, `pause`,
+/// `resume`, `level LEVEL|all`, `save [FILE]`, `theme NAME` or `set max-rate N|off`.
+pub fn parse(text: &str) -> Result<Command, String> {
+    let text = text.trim();
+    let (name, argument) = text.split_once(' ').unwrap_or((text, ""));
+    let argument = argument.trim();
+    match name {
+        "filter" => match argument.strip_prefix("add ") {
+            Some(term) if !term.trim().is_empty() => Ok(Command::AddTerm(term.trim().to_string())),
+            _ => Ok(Command::Filter(argument.to_string())),
+        },
+        "pause" | "resume" if argument.is_empty() => {
+            Ok(Command::Mode(Toggle::Pause, name == "pause"))
+        }
+        "mode" => {
+            let (toggle, state) = argument.split_once(' ').unwrap_or((argument, "on"));
+            let toggle =
+                Toggle::from_name(toggle).ok_or_else(|| format!("unknown toggle '{}'", toggle))?;
+            match state.trim() {
+                "on" => Ok(Command::Mode(toggle, true)),
+                "off" => Ok(Command::Mode(toggle, false)),
+                state => Err(format!("expected on or off, not '{}'", state)),
+            }
+        }
+        "level" if argument == "all" => Ok(Command::Level(None)),
+        "level" => Level::parse(argument)
+            .map(|level| Command::Level(Some(level)))
+            .ok_or_else(|| format!("unknown level '{}'", argument)),
+        "save" if argument.is_empty() => Ok(Command::Save(None)),
+        "save" => Ok(Command::Save(Some(PathBuf::from(argument)))),
+        "theme" => Theme::from_name(argument)
+            .map(Command::Theme)
+            .ok_or_else(|| format!("unknown theme '{}'", argument)),
+        "set" => match argument.split_once(' ') {
+            Some(("max-rate", "off")) => Ok(Command::MaxRate(None)),
+            Some(("max-rate", value)) => value
+                .trim()
+                .parse()
+                .map(|max| Command::MaxRate(Some(max)))
+                .map_err(|_| format!("max-rate: invalid number '{}'", value)),
+            _ => Err(format!("unknown setting '{}'", argument)),
+        },
+        _ => Err(format!("unknown command '{}'", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            parse("filter connection refused"),
+            Ok(Command::Filter("connection refused".to_string()))
+        );
+        assert_eq!(parse("filter"), Ok(Command::Filter(String::new())));
+        assert_eq!(
+            parse("mode invert"),
+            Ok(Command::Mode(Toggle::Invert, true))
+        );
+        assert_eq!(
+            parse("mode fold off"),
+            Ok(Command::Mode(Toggle::Fold, false))
+        );
+        assert_eq!(parse(" pause "), Ok(Command::Mode(Toggle::Pause, true)));
+        assert_eq!(parse("level warn"), Ok(Command::Level(Some(Level::Warn))));
+        assert_eq!(parse("level all"), Ok(Command::Level(None)));
+        assert!(parse("mode sparkle").is_err());
+        assert!(parse("mode regex maybe").is_err());
+        assert!(parse("resume now").is_err());
+        assert_eq!(
+            parse("filter add WARN"),
+            Ok(Command::AddTerm("WARN".to_string()))
+        );
+        assert_eq!(parse("save"), Ok(Command::Save(None)));
+        assert_eq!(
+            parse("save out.log"),
+            Ok(Command::Save(Some(PathBuf::from("out.log"))))
+        );
+        assert_eq!(
+            parse("theme protanopia"),
+            Ok(Command::Theme(Theme::Protanopia))
+        );
+        assert_eq!(parse("set max-rate 200"), Ok(Command::MaxRate(Some(200))));
+        assert_eq!(parse("set max-rate off"), Ok(Command::MaxRate(None)));
+        assert!(parse("set max-rate lots").is_err());
+        assert!(parse("set colour red").is_err());
+        assert!(parse("explode").is_err());
+    }
+}
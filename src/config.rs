@@ -0,0 +1,862 @@
+use crate::redact;
+use crate::theme::Theme;
+use regex::Regex;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Settings read from the config file.
+///
+/// The file is a small INI-style document:
+///
+/// ```text
+/// [colors]
+/// theme = default
+/// highlight = white on_bright_red
+/// status = on_blue
+///
+/// [patterns]
+/// ERROR = bold red
+/// timeout = yellow
+/// retry = auto
+///
+/// [keys]
+/// quit = q
+/// switch = tab
+/// copy = ctrl-x
+/// histogram = ctrl-g
+/// topk = ctrl-t
+/// pretty = ctrl-p
+/// normal = esc
+/// edit = /
+/// level_up = +
+/// level_down = -
+/// faster = ]
+/// slower = [
+/// pause = space
+/// save = S
+/// save_matches = s
+/// annotate = a
+/// search = ctrl-r
+/// down = j
+/// up = k
+/// next_match = n
+/// previous_match = N
+/// goto = G
+/// pin = p
+/// step = enter
+/// step_page = f
+/// expand = e
+/// replace = r
+/// yank = y
+/// copy_matches = Y
+/// sources = o
+/// mute = m
+/// undo = u
+/// redo = ctrl-r
+/// add_term = t
+/// remove_term = T
+/// patterns = P
+/// captures = c
+/// table = v
+/// left = h
+/// right = l
+/// severity = L
+/// preset = #
+/// command = :
+///
+/// [editing]
+/// style = emacs
+/// commit = live
+///
+/// [redact]
+/// password = none
+/// api_key = api_key=(?P<secret>\w+)
+///
+/// [function_keys]
+/// f1 = regex
+/// f2 = ignore_case
+/// f3 = invert
+/// f4 = hide
+/// f5 = pause
+/// f6 = follow
+/// f7 = pretty
+/// f8 = step
+/// f9 = fold
+/// f10 = smart_case
+/// f11 = dups
+/// f12 = whole_word
+///
+/// [preset errors]
+/// filter = ERROR|FATAL
+/// color = bold white on_red
+/// mode = regex hide
+///
+/// [preset slow queries]
+/// filter = took=\d{4,}ms
+/// mode = regex
+///
+/// [source app.log]
+/// label = app
+/// color = cyan
+/// prefix = {label} |
+///
+/// [source kubectl]
+/// muted = true
+/// ```
+///
+/// `fuzzy` can also be given to a function key in place of one of these. Presets are
+/// numbered in the order given, for `1#`, `2#` and so on in normal mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// SGR parameters used to highlight the filter string.
+    pub highlight: String,
+    /// SGR parameters used for the status area background.
+    pub status: String,
+    /// Extra words to highlight, with their SGR parameters. Patterns colored `auto` take
+    /// the theme's colors in turn.
+    pub patterns: Vec<(String, String)>,
+    /// Colors for whatever the config does not set itself.
+    pub theme: Theme,
+    /// Names and regexes of secrets to mask in every line, starting from
+    /// `redact::DEFAULT_RULES`; `none` turns a rule off.
+    pub redact: Vec<(String, String)>,
+    pub keys: KeyBindings,
+    /// Key bindings for editing the filter line.
+    pub editing: EditingStyle,
+    /// With `commit = enter`, the filter being typed only highlights; lines are hidden by
+    /// the filter as it was until Enter applies the new one, and Esc goes back to it.
+    pub commit_on_enter: bool,
+    /// What F1 to F12 toggle, in any mode.
+    pub function_keys: [Option<Toggle>; 12],
+    /// Named filters to switch to, from `[preset NAME]` sections.
+    pub presets: Vec<Preset>,
+    /// How --file and --exec sources are shown, from `[source NAME]` sections.
+    pub sources: Vec<SourceStyle>,
+}
+
+/// A filter with the matching options it is meant for, chosen by name or number.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub filter: String,
+    /// SGR parameters highlighting the filter while the preset is on, instead of the
+    /// usual highlight.
+    pub color: Option<String>,
+    /// Toggles switched on, with every other matching toggle and hiding switched off.
+    pub mode: Vec<Toggle>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum EditingStyle {
+    /// Always inserting, with Ctrl/Alt keys for movement.
+    #[default]
+    Emacs,
+    /// Esc switches to a command mode with hjkl, w/b/e and the d and c operators.
+    Vi,
+}
+
+/// Something a function key switches on and off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Toggle {
+    /// Treat the filter as a regex.
+    Regex,
+    IgnoreCase,
+    /// Ignore case unless the filter has a capital letter.
+    SmartCase,
+    /// Match lines without the filter.
+    Invert,
+    /// Hide lines that do not match, rather than only highlighting those that do.
+    Hide,
+    /// Hold new lines back until output resumes.
+    Pause,
+    /// Show new lines as they arrive; when off they are only counted and kept.
+    Follow,
+    Pretty,
+    /// Show a line, or a page, per key press.
+    Step,
+    /// Fold bursts of a repeated line into a summary.
+    Fold,
+    /// Dim lines already seen since this was turned on.
+    Dups,
+    /// Match the filter only as a whole word.
+    WholeWord,
+    /// Match the filter's characters in order with anything between; not with `Regex`.
+    Fuzzy,
+}
+
+impl Toggle {
+    pub fn from_name(name: &str) -> Option<Toggle> {
+        match name {
+            "regex" => Some(Toggle::Regex),
+            "ignore_case" => Some(Toggle::IgnoreCase),
+            "smart_case" => Some(Toggle::SmartCase),
+            "invert" => Some(Toggle::Invert),
+            "hide" => Some(Toggle::Hide),
+            "pause" => Some(Toggle::Pause),
+            "follow" => Some(Toggle::Follow),
+            "pretty" => Some(Toggle::Pretty),
+            "step" => Some(Toggle::Step),
+            "fold" => Some(Toggle::Fold),
+            "dups" => Some(Toggle::Dups),
+            "whole_word" => Some(Toggle::WholeWord),
+            "fuzzy" => Some(Toggle::Fuzzy),
+            _ => None,
+        }
+    }
+
+    /// Whether it decides which lines match or are shown, and so can be part of a preset.
+    pub fn is_matching(self) -> bool {
+        matches!(
+            self,
+            Toggle::Regex
+                | Toggle::IgnoreCase
+                | Toggle::SmartCase
+                | Toggle::Invert
+                | Toggle::Hide
+                | Toggle::WholeWord
+                | Toggle::Fuzzy
+        )
+    }
+
+    /// Description for the status area notice, such as `Regex on`.
+    pub fn label(self) -> &'static str {
+        match self {
+            Toggle::Regex => "Regex",
+            Toggle::IgnoreCase => "Ignore case",
+            Toggle::SmartCase => "Smart case",
+            Toggle::Invert => "Invert match",
+            Toggle::Hide => "Hide unmatched lines",
+            Toggle::Pause => "Pause output",
+            Toggle::Follow => "Follow new lines",
+            Toggle::Pretty => "Pretty JSON",
+            Toggle::Step => "Step mode",
+            Toggle::Fold => "Folding repeats",
+            Toggle::Dups => "Dimming lines seen before",
+            Toggle::WholeWord => "Whole word",
+            Toggle::Fuzzy => "Fuzzy matching",
+        }
+    }
+}
+
+/// Display settings for a --file or --exec source, from a section named after the label
+/// it would otherwise have: its file name or its command's first word.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceStyle {
+    pub name: String,
+    pub label: Option<String>,
+    /// SGR parameters for the prefix.
+    pub color: Option<String>,
+    /// What goes before each line, with `{label}` standing for the label; `[{label}]`
+    /// when not given. A space separates it from the line.
+    pub prefix: Option<String>,
+    /// Start muted, until unmuted from the sources overlay.
+    pub muted: bool,
+}
+
+/// Bytes read from the terminal that trigger commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyBindings {
+    pub quit: u8,
+    pub switch: u8,
+    /// Copy the most recent matching line to the clipboard.
+    pub copy: u8,
+    /// Cycle between the lines and the per-second/per-minute match histograms.
+    pub histogram: u8,
+    /// Toggle the most-frequent-lines panel.
+    pub topk: u8,
+    /// Toggle pretty-printing of JSON lines.
+    pub pretty: u8,
+    /// Leave filter editing for normal mode, where keys are commands.
+    pub normal: u8,
+    /// Go back from normal mode to editing the filter.
+    pub edit: u8,
+    /// Raise or lower the minimum log level shown (normal mode).
+    pub level_up: u8,
+    pub level_down: u8,
+    /// Change the replay speed, or pause it (normal mode).
+    pub faster: u8,
+    pub slower: u8,
+    pub pause: u8,
+    /// Save the recent lines, or only those containing the filter, to a file (normal mode).
+    pub save: u8,
+    pub save_matches: u8,
+    /// Attach a note to the most recent line, saved along with it (normal mode).
+    pub annotate: u8,
+    /// Search earlier filters while editing the filter.
+    pub search: u8,
+    /// Browse the scrollback a line at a time, or to the next or previous line matching
+    /// the filter (normal mode). A count typed first repeats them.
+    pub down: u8,
+    pub up: u8,
+    pub next_match: u8,
+    pub previous_match: u8,
+    /// Go to the line numbered by the count typed first, or without one back to the live
+    /// output (normal mode).
+    pub goto: u8,
+    /// Pin the most recent line, or the one selected in the scrollback, as a header;
+    /// again on the same line unpins it (normal mode).
+    pub pin: u8,
+    /// In step mode, show the next line or the next page (normal mode).
+    pub step: u8,
+    pub step_page: u8,
+    /// Show the lines of the latest folded burst in the scrollback (normal mode).
+    pub expand: u8,
+    /// Find and replace in the lines shown, with a preview before it applies (normal
+    /// mode).
+    pub replace: u8,
+    /// Copy only the text matching the filter in the line selected in the scrollback, or
+    /// else the most recent matching line (normal mode).
+    pub yank: u8,
+    /// Copy every line in the scrollback matching the filter, each once and without
+    /// colors (normal mode).
+    pub copy_matches: u8,
+    /// Show the --file and --exec sources (normal mode); there, pause pauses the selected
+    /// one and mute mutes it.
+    pub sources: u8,
+    pub mute: u8,
+    /// Undo or redo changes to the filter and toggles (normal mode); Ctrl+_ also undoes
+    /// while editing the filter. Ctrl+Z is left to suspend.
+    pub undo: u8,
+    pub redo: u8,
+    /// Keep the filter as a term matched and highlighted beside the next one, or drop
+    /// the term kept last (normal mode).
+    pub add_term: u8,
+    pub remove_term: u8,
+    /// Show the kept terms and configured patterns (normal mode); there, pause switches
+    /// the selected one on and off.
+    pub patterns: u8,
+    /// List each value the filter's capture group has matched (normal mode); there, yank
+    /// copies the selected one.
+    pub captures: u8,
+    /// Show JSON and logfmt records as a table (normal mode); there, left and right
+    /// select a column and pause sorts by it.
+    pub table: u8,
+    pub left: u8,
+    pub right: u8,
+    /// Show only lines of the most severe level counted, then the next, then all again
+    /// (normal mode, with --level-counts).
+    pub severity: u8,
+    /// Switch to the next preset, or with a count to that one, as `2#` (normal mode).
+    pub preset: u8,
+    /// Open the prompt for commands such as `save FILE` or `set max-rate 200` (normal
+    /// mode).
+    pub command: u8,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            highlight: "37;101".to_string(),
+            status: "44".to_string(),
+            patterns: Vec::new(),
+            theme: Theme::Default,
+            redact: redact::default_rules(),
+            keys: KeyBindings {
+                quit: b'q',
+                switch: b'\t',
+                copy: 24,     // ctrl-x
+                histogram: 7, // ctrl-g
+                topk: 20,     // ctrl-t
+                pretty: 16,   // ctrl-p
+                normal: 27,   // esc
+                edit: b'/',
+                level_up: b'+',
+                level_down: b'-',
+                faster: b']',
+                slower: b'[',
+                pause: b' ',
+                save: b'S',
+                save_matches: b's',
+                annotate: b'a',
+                search: 18,
+                down: b'j',
+                up: b'k',
+                next_match: b'n',
+                previous_match: b'N',
+                goto: b'G',
+                pin: b'p',
+                step: b'\r',
+                step_page: b'f',
+                expand: b'e',
+                replace: b'r',
+                yank: b'y',
+                copy_matches: b'Y',
+                sources: b'o',
+                mute: b'm',
+                undo: b'u',
+                redo: 18, // ctrl-r
+                add_term: b't',
+                remove_term: b'T',
+                patterns: b'P',
+                captures: b'c',
+                table: b'v',
+                left: b'h',
+                right: b'l',
+                severity: b'L',
+                preset: b'#',
+                command: b':',
+            },
+            editing: EditingStyle::Emacs,
+            commit_on_enter: false,
+            function_keys: [
+                Some(Toggle::Regex),
+                Some(Toggle::IgnoreCase),
+                Some(Toggle::Invert),
+                Some(Toggle::Hide),
+                Some(Toggle::Pause),
+                Some(Toggle::Follow),
+                Some(Toggle::Pretty),
+                Some(Toggle::Step),
+                Some(Toggle::Fold),
+                Some(Toggle::SmartCase),
+                Some(Toggle::Dups),
+                Some(Toggle::WholeWord),
+            ],
+            presets: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from `path`, falling back to defaults if it does not exist. A
+    /// `theme` given here wins over the file's.
+    pub fn load(path: &Path, theme: Option<Theme>) -> Result<Config, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                Config::parse(&text, theme).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::parse("", theme),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Parse `text`, using `theme` instead of any theme it names.
+    pub fn parse(text: &str, theme: Option<Theme>) -> Result<Config, String> {
+        let mut config = Config::default();
+        let mut section = String::new();
+        let mut highlight = None;
+        // Patterns colored `auto`, resolved once the theme is known.
+        let mut auto = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                if let Some(name) = section.strip_prefix("preset ") {
+                    config.presets.push(Preset {
+                        name: name.trim().to_string(),
+                        ..Preset::default()
+                    });
+                }
+                if let Some(name) = section.strip_prefix("source ") {
+                    config.sources.push(SourceStyle {
+                        name: name.trim().to_string(),
+                        ..SourceStyle::default()
+                    });
+                }
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .ok_or_else(|| format!("line {}: expected 'key = value'", number + 1))?;
+            let error = |message: String| format!("line {}: {}", number + 1, message);
+
+            match (section.as_str(), key) {
+                ("colors", "theme") => {
+                    config.theme = Theme::from_name(value)
+                        .ok_or_else(|| error(format!("unknown theme '{}'", value)))?
+                }
+                ("colors", "highlight") => highlight = Some(parse_color(value).map_err(error)?),
+                ("colors", "status") => config.status = parse_color(value).map_err(error)?,
+                ("editing", "style") => {
+                    config.editing = match value {
+                        "emacs" => EditingStyle::Emacs,
+                        "vi" => EditingStyle::Vi,
+                        _ => return Err(error(format!("unknown editing style '{}'", value))),
+                    }
+                }
+                ("editing", "commit") => {
+                    config.commit_on_enter = match value {
+                        "live" => false,
+                        "enter" => true,
+                        _ => return Err(error(format!("unknown commit '{}'", value))),
+                    }
+                }
+                ("function_keys", key) => {
+                    let index = key
+                        .strip_prefix('f')
+                        .and_then(|number| number.parse::<usize>().ok())
+                        .filter(|number| (1..=12).contains(number))
+                        .ok_or_else(|| error(format!("unknown function key '{}'", key)))?;
+                    config.function_keys[index - 1] = match value {
+                        "none" => None,
+                        _ => Some(
+                            Toggle::from_name(value)
+                                .ok_or_else(|| error(format!("unknown toggle '{}'", value)))?,
+                        ),
+                    };
+                }
+                ("redact", name) => {
+                    config.redact.retain(|(rule, _)| rule != name);
+                    if value != "none" {
+                        Regex::new(value).map_err(|e| error(e.to_string()))?;
+                        config.redact.push((name.to_string(), value.to_string()));
+                    }
+                }
+                (section, "filter") if section.starts_with("preset ") => {
+                    config.presets.last_mut().unwrap().filter = value.to_string()
+                }
+                (section, "color") if section.starts_with("preset ") => {
+                    config.presets.last_mut().unwrap().color =
+                        Some(parse_color(value).map_err(error)?)
+                }
+                (section, "mode") if section.starts_with("preset ") => {
+                    let mode = value
+                        .split_whitespace()
+                        .map(|name| match Toggle::from_name(name) {
+                            Some(toggle) if toggle.is_matching() => Ok(toggle),
+                            _ => Err(error(format!("unknown preset mode '{}'", name))),
+                        })
+                        .collect::<Result<_, _>>()?;
+                    config.presets.last_mut().unwrap().mode = mode;
+                }
+                (section, "label") if section.starts_with("source ") => {
+                    config.sources.last_mut().unwrap().label = Some(value.to_string())
+                }
+                (section, "color") if section.starts_with("source ") => {
+                    config.sources.last_mut().unwrap().color =
+                        Some(parse_color(value).map_err(error)?)
+                }
+                (section, "prefix") if section.starts_with("source ") => {
+                    config.sources.last_mut().unwrap().prefix = Some(value.to_string())
+                }
+                (section, "muted") if section.starts_with("source ") => {
+                    config.sources.last_mut().unwrap().muted = match value {
+                        "true" => true,
+                        "false" => false,
+                        _ => return Err(error(format!("expected true or false, not '{}'", value))),
+                    }
+                }
+                ("patterns", word) if value == "auto" => {
+                    auto.push(config.patterns.len());
+                    config.patterns.push((word.to_string(), String::new()));
+                }
+                ("patterns", word) => {
+                    let color = parse_color(value).map_err(error)?;
+                    config.patterns.push((word.to_string(), color));
+                }
+                ("keys", "quit") => config.keys.quit = parse_key(value).map_err(error)?,
+                ("keys", "switch") => config.keys.switch = parse_key(value).map_err(error)?,
+                ("keys", "copy") => config.keys.copy = parse_key(value).map_err(error)?,
+                ("keys", "histogram") => config.keys.histogram = parse_key(value).map_err(error)?,
+                ("keys", "topk") => config.keys.topk = parse_key(value).map_err(error)?,
+                ("keys", "pretty") => config.keys.pretty = parse_key(value).map_err(error)?,
+                ("keys", "normal") => config.keys.normal = parse_key(value).map_err(error)?,
+                ("keys", "edit") => config.keys.edit = parse_key(value).map_err(error)?,
+                ("keys", "level_up") => config.keys.level_up = parse_key(value).map_err(error)?,
+                ("keys", "level_down") => {
+                    config.keys.level_down = parse_key(value).map_err(error)?
+                }
+                ("keys", "faster") => config.keys.faster = parse_key(value).map_err(error)?,
+                ("keys", "slower") => config.keys.slower = parse_key(value).map_err(error)?,
+                ("keys", "pause") => config.keys.pause = parse_key(value).map_err(error)?,
+                ("keys", "save") => config.keys.save = parse_key(value).map_err(error)?,
+                ("keys", "save_matches") => {
+                    config.keys.save_matches = parse_key(value).map_err(error)?
+                }
+                ("keys", "annotate") => config.keys.annotate = parse_key(value).map_err(error)?,
+                ("keys", "search") => config.keys.search = parse_key(value).map_err(error)?,
+                ("keys", "down") => config.keys.down = parse_key(value).map_err(error)?,
+                ("keys", "up") => config.keys.up = parse_key(value).map_err(error)?,
+                ("keys", "next_match") => {
+                    config.keys.next_match = parse_key(value).map_err(error)?
+                }
+                ("keys", "previous_match") => {
+                    config.keys.previous_match = parse_key(value).map_err(error)?
+                }
+                ("keys", "goto") => config.keys.goto = parse_key(value).map_err(error)?,
+                ("keys", "pin") => config.keys.pin = parse_key(value).map_err(error)?,
+                ("keys", "step") => config.keys.step = parse_key(value).map_err(error)?,
+                ("keys", "step_page") => config.keys.step_page = parse_key(value).map_err(error)?,
+                ("keys", "expand") => config.keys.expand = parse_key(value).map_err(error)?,
+                ("keys", "replace") => config.keys.replace = parse_key(value).map_err(error)?,
+                ("keys", "yank") => config.keys.yank = parse_key(value).map_err(error)?,
+                ("keys", "sources") => config.keys.sources = parse_key(value).map_err(error)?,
+                ("keys", "mute") => config.keys.mute = parse_key(value).map_err(error)?,
+                ("keys", "undo") => config.keys.undo = parse_key(value).map_err(error)?,
+                ("keys", "redo") => config.keys.redo = parse_key(value).map_err(error)?,
+                ("keys", "add_term") => config.keys.add_term = parse_key(value).map_err(error)?,
+                ("keys", "patterns") => config.keys.patterns = parse_key(value).map_err(error)?,
+                ("keys", "captures") => config.keys.captures = parse_key(value).map_err(error)?,
+                ("keys", "table") => config.keys.table = parse_key(value).map_err(error)?,
+                ("keys", "left") => config.keys.left = parse_key(value).map_err(error)?,
+                ("keys", "right") => config.keys.right = parse_key(value).map_err(error)?,
+                ("keys", "severity") => config.keys.severity = parse_key(value).map_err(error)?,
+                ("keys", "preset") => config.keys.preset = parse_key(value).map_err(error)?,
+                ("keys", "command") => config.keys.command = parse_key(value).map_err(error)?,
+                ("keys", "remove_term") => {
+                    config.keys.remove_term = parse_key(value).map_err(error)?
+                }
+                ("keys", "copy_matches") => {
+                    config.keys.copy_matches = parse_key(value).map_err(error)?
+                }
+                _ => return Err(error(format!("unknown setting '{}' in [{}]", key, section))),
+            }
+        }
+
+        if let Some(theme) = theme {
+            config.theme = theme;
+        }
+        config.highlight = highlight.unwrap_or_else(|| config.theme.highlight().to_string());
+        for (turn, index) in auto.into_iter().enumerate() {
+            config.patterns[index].1 = config.theme.pattern(turn);
+        }
+        Ok(config)
+    }
+
+    /// The number of the preset a count picks, or without one of the preset after the one
+    /// whose filter is `filter`, going back to the first after the last.
+    pub fn preset_number(&self, filter: &str, count: Option<usize>) -> usize {
+        count.unwrap_or_else(|| {
+            self.preset_for(filter).map_or(0, |(number, _)| number) % self.presets.len().max(1) + 1
+        })
+    }
+
+    /// The preset whose filter is `filter`, numbered from 1.
+    pub fn preset_for(&self, filter: &str) -> Option<(usize, &Preset)> {
+        self.presets
+            .iter()
+            .enumerate()
+            .find(|(_, preset)| preset.filter == filter)
+            .map(|(index, preset)| (index + 1, preset))
+    }
+
+    /// The style highlighting `filter`: its preset's color, if it has one.
+    pub fn highlight_for(&self, filter: &str) -> &str {
+        self.preset_for(filter)
+            .and_then(|(_, preset)| preset.color.as_deref())
+            .unwrap_or(&self.highlight)
+    }
+}
+
+/// Default location of the config file.
+pub fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("pipe_tools").join("config"))
+}
+
+/// Turn a color description such as `bold white on_red` or `1;31` into SGR parameters.
+pub fn parse_color(value: &str) -> Result<String, String> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    let mut codes = Vec::new();
+
+    for word in value.split(|c: char| c.is_whitespace() || c == ';') {
+        if word.is_empty() {
+            continue;
+        }
+        if word.chars().all(|c| c.is_ascii_digit()) {
+            codes.push(word.to_string());
+            continue;
+        }
+        let (background, name) = match word.strip_prefix("on_") {
+            Some(name) => (true, name),
+            None => (false, word),
+        };
+        let (bright, name) = match name.strip_prefix("bright_") {
+            Some(name) => (true, name),
+            None => (false, name),
+        };
+        let code = match name {
+            "bold" if !background => 1,
+            "dim" if !background => 2,
+            "underline" if !background => 4,
+            _ => {
+                let index = NAMES
+                    .iter()
+                    .position(|n| *n == name)
+                    .ok_or_else(|| format!("unknown color '{}'", word))?;
+                let base = match (background, bright) {
+                    (false, false) => 30,
+                    (true, false) => 40,
+                    (false, true) => 90,
+                    (true, true) => 100,
+                };
+                base + index
+            }
+        };
+        codes.push(code.to_string());
+    }
+
+    if codes.is_empty() {
+        return Err("empty color".to_string());
+    }
+    Ok(codes.join(";"))
+}
+
+/// Turn a key description such as `q`, `tab` or `ctrl-x` into the byte the terminal sends.
+pub fn parse_key(value: &str) -> Result<u8, String> {
+    let lower = value.to_ascii_lowercase();
+    match lower.as_str() {
+        "tab" => return Ok(b'\t'),
+        "enter" => return Ok(b'\r'),
+        "esc" | "escape" => return Ok(27),
+        "space" => return Ok(b' '),
+        _ => {}
+    }
+    if let Some(letter) = lower.strip_prefix("ctrl-") {
+        if let [c @ b'a'..=b'z'] = letter.as_bytes() {
+            return Ok(c - b'a' + 1);
+        }
+    }
+    match value.as_bytes() {
+        [c @ 32..=126] => Ok(*c),
+        _ => Err(format!("unknown key '{}'", value)),
+    }
+}
+
+/// Call `on_change` whenever the file at `path` is written or replaced.
+pub fn watch<F>(path: PathBuf, mut on_change: F)
+where
+    F: FnMut() + Send + 'static,
+{
+    thread::spawn(move || {
+        #[cfg(target_os = "linux")]
+        if watch_inotify(&path, &mut on_change).is_ok() {
+            return;
+        }
+        watch_polling(&path, &mut on_change);
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn watch_inotify<F: FnMut()>(path: &Path, on_change: &mut F) -> nix::Result<()> {
+    use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+    // Watch the directory so editors that save by renaming over the file are seen too.
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let inotify = Inotify::init(InitFlags::empty())?;
+    inotify.add_watch(
+        dir,
+        AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO | AddWatchFlags::IN_CREATE,
+    )?;
+
+    loop {
+        let events = inotify.read_events()?;
+        if events
+            .iter()
+            .any(|event| event.name.as_deref() == path.file_name())
+        {
+            on_change();
+        }
+    }
+}
+
+/// Fallback for platforms without inotify: compare modification times once a second.
+fn watch_polling<F: FnMut()>(path: &Path, on_change: &mut F) {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last = modified(path);
+    loop {
+        thread::sleep(Duration::from_secs(1));
+        let current = modified(path);
+        if current != last {
+            last = current;
+            on_change();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            "# comment\n[colors]\nhighlight = black on_yellow\n\n[patterns]\nERROR = bold red\n[keys]\nquit = ctrl-x\n",
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.highlight, "30;43");
+        assert_eq!(config.status, Config::default().status);
+        assert_eq!(
+            config.patterns,
+            vec![("ERROR".to_string(), "1;31".to_string())]
+        );
+        assert_eq!(config.keys.quit, 24);
+        assert_eq!(config.keys.switch, b'\t');
+        assert_eq!(config.editing, EditingStyle::Emacs);
+        let config = Config::parse("[editing]\nstyle = vi\ncommit = enter\n", None).unwrap();
+        assert_eq!(config.editing, EditingStyle::Vi);
+        assert!(config.commit_on_enter);
+        let config = Config::parse("[function_keys]\nf1 = none\nf12 = invert\n", None).unwrap();
+        assert_eq!(config.function_keys[0], None);
+        assert_eq!(config.function_keys[1], Some(Toggle::IgnoreCase));
+        assert_eq!(config.function_keys[11], Some(Toggle::Invert));
+        let config = Config::parse("[redact]\npassword = none\ntoken = tok_\\w+\n", None).unwrap();
+        assert!(!config.redact.iter().any(|(name, _)| name == "password"));
+        assert_eq!(config.redact.last().unwrap().1, r"tok_\w+");
+        let text = "[preset errors]\nfilter = level=error\ncolor = red\nmode = regex hide\n\
+                    [preset all]\n";
+        let config = Config::parse(text, None).unwrap();
+        assert_eq!(config.presets[0].filter, "level=error");
+        assert_eq!(config.presets[0].mode, [Toggle::Regex, Toggle::Hide]);
+        assert_eq!(config.preset_for("").unwrap().1.name, "all");
+        assert_eq!(config.highlight_for("level=error"), "31");
+        assert_eq!(config.highlight_for("other"), config.highlight);
+        let text =
+            "[source app.log]\nlabel = app\ncolor = cyan\nprefix = {label} |\nmuted = true\n";
+        let source = &Config::parse(text, None).unwrap().sources[0];
+        assert_eq!(source.name, "app.log");
+        assert_eq!(source.label.as_deref(), Some("app"));
+        assert_eq!(source.color.as_deref(), Some("36"));
+        assert_eq!(source.prefix.as_deref(), Some("{label} |"));
+        assert!(source.muted);
+    }
+
+    #[test]
+    fn test_theme() {
+        let text = "[patterns]\nretry = auto\nslow = auto\n[colors]\ntheme = tritanopia\n";
+        let config = Config::parse(text, None).unwrap();
+        assert_eq!(config.theme, Theme::Tritanopia);
+        assert_eq!(config.highlight, Theme::Tritanopia.highlight());
+        assert_eq!(config.patterns[1].1, Theme::Tritanopia.pattern(1));
+        let config = Config::parse("[colors]\nhighlight = red\n", Some(Theme::Protanopia));
+        assert_eq!(config.unwrap().highlight, "31");
+    }
+
+    #[test]
+    fn test_parse_config_errors() {
+        assert!(Config::parse("[colors]\nhighlight = mauve\n", None).is_err());
+        assert!(Config::parse("[colors]\ntheme = sepia\n", None).is_err());
+        assert!(Config::parse("[redact]\ntoken = (\n", None).is_err());
+        assert!(Config::parse("[keys]\nquit = ctrl-1\n", None).is_err());
+        assert!(Config::parse("[other]\nx = 1\n", None).is_err());
+        assert!(Config::parse("[editing]\nstyle = ed\n", None).is_err());
+        assert!(Config::parse("[function_keys]\nf13 = regex\n", None).is_err());
+        assert!(Config::parse("[function_keys]\nf1 = sparkle\n", None).is_err());
+        assert!(Config::parse("[preset x]\nmode = pause\n", None).is_err());
+        assert!(Config::parse("[source x]\nmuted = maybe\n", None).is_err());
+        assert!(Config::parse("no equals sign\n", None).is_err());
+    }
+
+    #[test]
+    fn test_parse_color_codes() {
+        assert_eq!(parse_color("1;31").unwrap(), "1;31");
+        assert_eq!(parse_color("white on_bright_red").unwrap(), "37;101");
+    }
+}
@@ -0,0 +1,111 @@
+//! Unwrapping container runtime log formats to the line the program actually wrote.
+//!
+//! Docker's json-file driver writes `{"log":"...\n","stream":"stdout","time":"..."}` and
+//! CRI runtimes (containerd, CRI-O, so Kubernetes nodes) write
+//! `2016-10-06T00:17:09.669794202Z stdout F message`.
+
+use serde_json::{Map, Value};
+
+/// One line as written by a container runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub message: String,
+    pub stream: String,
+    pub time: String,
+    /// The message continues in the next entry (CRI splits long lines).
+    pub partial: bool,
+}
+
+/// Unwrap `line` if it is in the Docker JSON or CRI format.
+pub fn parse(line: &str) -> Option<Entry> {
+    if line.starts_with('{') {
+        return parse_docker(line);
+    }
+    let mut parts = line.splitn(4, ' ');
+    let time = parts.next()?;
+    let stream = parts.next()?;
+    let tag = parts.next()?;
+    let message = parts.next().unwrap_or("");
+    let looks_like_time = time.len() >= 20 && time.as_bytes()[4] == b'-' && time.contains('T');
+    if !looks_like_time || !matches!(stream, "stdout" | "stderr") || !matches!(tag, "F" | "P") {
+        return None;
+    }
+    Some(Entry {
+        message: message.to_string(),
+        stream: stream.to_string(),
+        time: time.to_string(),
+        partial: tag == "P",
+    })
+}
+
+fn parse_docker(line: &str) -> Option<Entry> {
+    let Value::Object(map) = serde_json::from_str(line).ok()? else {
+        return None;
+    };
+    let text = |key: &str| map.get(key)?.as_str().map(str::to_string);
+    let message = text("log")?;
+    let (stream, time) = (text("stream"), text("time"));
+    if stream.is_none() && time.is_none() {
+        return None;
+    }
+    Some(Entry {
+        // Docker keeps the newline, and splits lines over 16 KiB without one.
+        partial: !message.ends_with('\n'),
+        message: message.trim_end_matches(['\n', '\r']).to_string(),
+        stream: stream.unwrap_or_default(),
+        time: time.unwrap_or_default(),
+    })
+}
+
+impl Entry {
+    /// The message's own fields (when it is JSON) with `time` and `stream` added.
+    pub fn fields(&self, inner: Option<Value>) -> Value {
+        let mut map = match inner {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        map.entry("time")
+            .or_insert_with(|| Value::from(self.time.clone()));
+        map.entry("stream")
+            .or_insert_with(|| Value::from(self.stream.clone()));
+        map.entry("log")
+            .or_insert_with(|| Value::from(self.message.clone()));
+        Value::Object(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_docker() {
+        let entry =
+            parse(r#"{"log":"hello\n","stream":"stderr","time":"2024-01-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(entry.message, "hello");
+        assert_eq!(entry.stream, "stderr");
+        assert!(!entry.partial);
+        assert_eq!(parse(r#"{"log":"not a wrapper"}"#), None);
+        assert_eq!(parse(r#"{"msg":"x","time":"t"}"#), None);
+    }
+
+    #[test]
+    fn test_parse_cri() {
+        let entry = parse("2016-10-06T00:17:09.669794202Z stdout P first half").unwrap();
+        assert_eq!(entry.message, "first half");
+        assert!(entry.partial);
+        assert_eq!(entry.time, "2016-10-06T00:17:09.669794202Z");
+        let entry = parse("2016-10-06T00:17:09.669794202Z stdout F ").unwrap();
+        assert_eq!(entry.message, "");
+        assert_eq!(parse("2016-10-06 stdout F x"), None);
+        assert_eq!(parse("plain old line"), None);
+    }
+
+    #[test]
+    fn test_fields() {
+        let entry = parse("2016-10-06T00:17:09Z stdout F x").unwrap();
+        let fields = entry.fields(serde_json::from_str(r#"{"level":"info"}"#).ok());
+        assert_eq!(fields["level"], "info");
+        assert_eq!(fields["stream"], "stdout");
+    }
+}
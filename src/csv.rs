@@ -0,0 +1,132 @@
+//! CSV and TSV records shown as aligned columns under a pinned header.
+
+use crate::width;
+
+/// Color for every other column when banding is on.
+const BAND: &str = "36";
+
+/// Split one record at `separator`, honouring double quotes (`""` is a literal quote).
+pub fn parse_record(line: &str, separator: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().unwrap();
+        match c {
+            '"' if quoted && chars.next_if_eq(&'"').is_some() => field.push('"'),
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if c == separator && !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+/// The header row and how wide each column has grown.
+#[derive(Debug, Clone)]
+pub struct Table {
+    separator: char,
+    bands: bool,
+    header: Option<Vec<String>>,
+    widths: Vec<usize>,
+}
+
+impl Table {
+    pub fn new(separator: char, bands: bool) -> Self {
+        Table {
+            separator,
+            bands,
+            header: None,
+            widths: Vec::new(),
+        }
+    }
+
+    /// Take in a line: the first becomes the header and yields `None`, the others are
+    /// returned as aligned pieces of text with the SGR parameters to color them with.
+    ///
+    /// Cells are the even-numbered pieces, each followed by its padding.
+    pub fn render(&mut self, line: &str) -> Option<Vec<(String, Option<&'static str>)>> {
+        let fields = parse_record(line, self.separator);
+        self.grow(&fields);
+        if self.header.is_none() {
+            self.header = Some(fields);
+            return None;
+        }
+        Some(self.cells(&fields))
+    }
+
+    /// The header aligned to the current column widths, once it has been seen.
+    pub fn header(&self) -> Option<String> {
+        let header = self.header.as_ref()?;
+        Some(
+            self.cells(header)
+                .into_iter()
+                .map(|(text, _)| text)
+                .collect(),
+        )
+    }
+
+    /// For a filter such as `status:500` naming a column, that column and the text to find.
+    pub fn column_filter<'a>(&self, filter: &'a str) -> Option<(usize, &'a str)> {
+        let (name, text) = filter.split_once(':')?;
+        let column = self
+            .header
+            .as_ref()?
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))?;
+        Some((column, text))
+    }
+
+    fn grow(&mut self, fields: &[String]) {
+        if self.widths.len() < fields.len() {
+            self.widths.resize(fields.len(), 0);
+        }
+        for (width, field) in self.widths.iter_mut().zip(fields) {
+            *width = (*width).max(width::width(field).min(width::MAX_COLUMN));
+        }
+    }
+
+    fn cells(&self, fields: &[String]) -> Vec<(String, Option<&'static str>)> {
+        let mut tokens = Vec::new();
+        for (i, field) in fields.iter().enumerate() {
+            let color = (self.bands && i % 2 == 1).then_some(BAND);
+            let padding = if i + 1 == fields.len() {
+                0
+            } else {
+                self.widths[i].saturating_sub(width::width(field)) + 2
+            };
+            tokens.push((field.clone(), color));
+            tokens.push((" ".repeat(padding), None));
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        assert_eq!(
+            parse_record(r#"a,"b, c","say ""hi""",,e"#, ','),
+            vec!["a", "b, c", "say \"hi\"", "", "e"]
+        );
+        assert_eq!(parse_record("x\ty\r", '\t'), vec!["x", "y"]);
+    }
+
+    #[test]
+    fn test_table_render() {
+        let mut table = Table::new(',', false);
+        assert!(table.render("name,status").is_none());
+        let text = |tokens: Vec<(String, Option<&str>)>| -> String {
+            tokens.into_iter().map(|(t, _)| t).collect()
+        };
+        assert_eq!(text(table.render("alpha,200").unwrap()), "alpha  200");
+        assert_eq!(table.header().unwrap(), "name   status");
+        assert_eq!(table.column_filter("Status:500"), Some((1, "500")));
+        assert_eq!(table.column_filter("other:500"), None);
+        assert_eq!(table.column_filter("plain"), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! A saved read position, so a restarted pipe_tools picks up a file where it stopped.
+//!
+//! The offset only moves past lines that have been fully handled, so after a crash some
+//! lines may be shown twice but none are skipped.
+
+use crate::progress;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug)]
+pub struct Cursor {
+    path: PathBuf,
+    /// Offset just past the last handled line.
+    offset: u64,
+    /// Offsets just past each line read since the last handled one, and whether each has
+    /// been handled; lines can be handled out of order when errors go ahead.
+    read: VecDeque<(u64, bool)>,
+    /// Number of the first line in `read`, counting lines read from 1.
+    first: usize,
+    saved: u64,
+}
+
+impl Cursor {
+    /// Load the offset saved in `path`, starting at 0 if there is none yet.
+    pub fn load(path: &Path) -> Result<Cursor, String> {
+        let offset = match fs::read_to_string(path) {
+            Ok(text) => text
+                .trim()
+                .parse()
+                .map_err(|_| format!("{}: not a byte offset", path.display()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        Ok(Cursor {
+            path: path.to_path_buf(),
+            offset,
+            read: VecDeque::new(),
+            first: 1,
+            saved: offset,
+        })
+    }
+
+    /// Move stdin, which must be a regular file, to the saved offset. A file shorter than
+    /// the offset has been truncated or replaced, so it is read from the start.
+    pub fn seek_stdin(&mut self) -> io::Result<()> {
+        let mut file = progress::stdin_file()?;
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Err(io::Error::other("stdin is not a file"));
+        }
+        if metadata.len() < self.offset {
+            self.offset = 0;
+        }
+        // The duplicate shares its position with stdin.
+        file.seek(SeekFrom::Start(self.offset))?;
+        Ok(())
+    }
+
+    /// A line of `bytes` bytes, terminator included, was read.
+    pub fn read(&mut self, bytes: usize) {
+        let end = self.read.back().map_or(self.offset, |&(end, _)| end) + bytes as u64;
+        self.read.push_back((end, false));
+    }
+
+    /// `lines` lines starting with the one numbered `first` have been handled. The offset
+    /// only moves past lines with none before them still to handle.
+    pub fn handled(&mut self, first: usize, lines: usize) {
+        for number in first..first + lines {
+            if let Some(line) = number
+                .checked_sub(self.first)
+                .and_then(|index| self.read.get_mut(index))
+            {
+                line.1 = true;
+            }
+        }
+        while let Some(&(end, true)) = self.read.front() {
+            self.read.pop_front();
+            self.offset = end;
+            self.first += 1;
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Write the offset out if it moved since the last save.
+    pub fn save(&mut self) -> io::Result<()> {
+        if self.offset == self.saved {
+            return Ok(());
+        }
+        // Replace the file in one step so a crash never leaves half an offset behind.
+        let temporary = self.path.with_extension("tmp");
+        fs::write(&temporary, format!("{}\n", self.offset))?;
+        fs::rename(&temporary, &self.path)?;
+        self.saved = self.offset;
+        Ok(())
+    }
+}
+
+/// Marks a record's lines as handled when dropped, however handling it ends.
+pub struct Handling<'a> {
+    /// The cursor and the number of the record's first line.
+    cursor: Option<(&'a Mutex<Cursor>, usize)>,
+    lines: usize,
+}
+
+impl<'a> Handling<'a> {
+    pub fn new(cursor: Option<(&'a Mutex<Cursor>, usize)>, lines: usize) -> Self {
+        Handling { cursor, lines }
+    }
+}
+
+impl Drop for Handling<'_> {
+    fn drop(&mut self) {
+        if let Some((cursor, first)) = self.cursor {
+            cursor.lock().unwrap().handled(first, self.lines);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_follows_handled_lines() {
+        let path = std::env::temp_dir().join(format!("pipe_tools-cursor-{}", std::process::id()));
+        let mut cursor = Cursor::load(&path).unwrap();
+        assert_eq!(cursor.offset(), 0);
+        for line in ["one\n", "two\r\n", "three\n"] {
+            cursor.read(line.len());
+        }
+        cursor.handled(3, 1);
+        assert_eq!(cursor.offset(), 0);
+        cursor.handled(1, 1);
+        assert_eq!(cursor.offset(), 4);
+        cursor.handled(2, 1);
+        assert_eq!(cursor.offset(), 15);
+        cursor.read(4);
+        cursor.handled(4, 1);
+        assert_eq!(cursor.offset(), 19);
+        let mut cursor = Cursor::load(&path).unwrap();
+        for line in ["one\n", "two\r\n", "three\n"] {
+            cursor.read(line.len());
+        }
+        cursor.handled(1, 2);
+        assert_eq!(cursor.offset(), 9);
+        cursor.save().unwrap();
+        assert_eq!(Cursor::load(&path).unwrap().offset(), 9);
+
+        fs::write(&path, "lots").unwrap();
+        assert!(Cursor::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
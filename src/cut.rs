@@ -0,0 +1,76 @@
+//! `-d` and `-f`: show only some fields of each line, as cut(1) does, while the filter
+//! still looks at the whole line.
+
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone)]
+pub struct Cut {
+    delimiter: String,
+    /// Fields to show, numbered from 1.
+    fields: Vec<RangeInclusive<usize>>,
+}
+
+impl Cut {
+    /// Parse a list such as `2,5` or `1,3-4,7-`, where `-3` is the first three fields.
+    pub fn new(delimiter: &str, fields: &str) -> Result<Cut, String> {
+        if delimiter.is_empty() {
+            return Err("-d: the delimiter is empty".to_string());
+        }
+        let invalid = || format!("-f: invalid field list '{}'", fields);
+        let number = |text: &str| match text.trim().parse::<usize>() {
+            Ok(0) | Err(_) => Err(invalid()),
+            Ok(number) => Ok(number),
+        };
+        let mut ranges = Vec::new();
+        for part in fields.split(',') {
+            let range = match part.split_once('-') {
+                Some(("", "")) => return Err(invalid()),
+                Some(("", end)) => 1..=number(end)?,
+                Some((start, "")) => number(start)?..=usize::MAX,
+                Some((start, end)) => number(start)?..=number(end)?,
+                None => number(part)?..=number(part)?,
+            };
+            if range.is_empty() {
+                return Err(invalid());
+            }
+            ranges.push(range);
+        }
+        Ok(Cut {
+            delimiter: delimiter.to_string(),
+            fields: ranges,
+        })
+    }
+
+    /// The chosen fields of `line` joined by the delimiter, or the whole line if it has
+    /// no delimiter in it.
+    pub fn apply(&self, line: &str) -> String {
+        if !line.contains(&self.delimiter) {
+            return line.to_string();
+        }
+        line.split(&self.delimiter)
+            .enumerate()
+            .filter(|(index, _)| self.fields.iter().any(|range| range.contains(&(index + 1))))
+            .map(|(_, field)| field)
+            .collect::<Vec<_>>()
+            .join(&self.delimiter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        let cut = Cut::new(",", "2,5").unwrap();
+        assert_eq!(cut.apply("a,b,c,d,e,f"), "b,e");
+        assert_eq!(cut.apply("a,b"), "b");
+        assert_eq!(cut.apply("no delimiter"), "no delimiter");
+        let ranges = Cut::new(" ", "-2,4-").unwrap();
+        assert_eq!(ranges.apply("1 2 3 4 5"), "1 2 4 5");
+        assert!(Cut::new(",", "0").is_err());
+        assert!(Cut::new(",", "3-1").is_err());
+        assert!(Cut::new(",", "a").is_err());
+        assert!(Cut::new("", "1").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Lines that one recording has and the other does not, for comparing a good run with a
+//! bad one.
+
+use crate::topk;
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+/// Lines remembered for marking repeats; past this many the oldest are forgotten.
+const SEEN_LIMIT: usize = 1_000_000;
+
+/// Makes lines from different runs comparable by blanking out what always differs.
+#[derive(Debug, Clone)]
+pub struct Normalizer {
+    ignore: Vec<Regex>,
+}
+
+impl Normalizer {
+    /// Matches of each of `ignore` are replaced by `*`, then numbers and UUIDs are
+    /// collapsed as for the most-frequent-lines panel.
+    pub fn new(ignore: &[String]) -> Result<Self, String> {
+        let ignore = ignore
+            .iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| format!("invalid regex: {}", e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Normalizer { ignore })
+    }
+
+    pub fn normalize(&self, line: &str) -> String {
+        let mut line = line.to_string();
+        for regex in &self.ignore {
+            line = regex.replace_all(&line, "*").into_owned();
+        }
+        topk::normalize(&line)
+    }
+}
+
+/// The lines of a reference log, such as one from a good run, for hiding lines the current
+/// run has in common with it.
+#[derive(Debug, Clone)]
+pub struct Known {
+    normalizer: Normalizer,
+    keys: HashSet<String>,
+}
+
+impl Known {
+    /// Read the plain text log at `path`.
+    pub fn load(path: &Path, normalizer: Normalizer) -> Result<Known, String> {
+        let text = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let keys = String::from_utf8_lossy(&text)
+            .lines()
+            .map(|line| normalizer.normalize(line.trim_end_matches('\r')))
+            .collect();
+        Ok(Known { normalizer, keys })
+    }
+
+    /// Whether the reference has `line`, numbers and ignored parts aside.
+    pub fn contains(&self, line: &str) -> bool {
+        self.keys.contains(&self.normalizer.normalize(line))
+    }
+}
+
+/// Every line so far in this run, kept as hashes of the normalized lines so a long
+/// session stays small, for marking lines that are not new.
+#[derive(Debug, Clone)]
+pub struct Seen {
+    normalizer: Normalizer,
+    hashes: HashSet<u64>,
+    /// The same hashes in the order first seen, for forgetting the oldest.
+    order: VecDeque<u64>,
+}
+
+impl Seen {
+    pub fn new(normalizer: Normalizer) -> Seen {
+        Seen {
+            normalizer,
+            hashes: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Note `line`, returning whether it was seen before, numbers and ignored parts aside.
+    pub fn repeated(&mut self, line: &str) -> bool {
+        let mut hasher = DefaultHasher::new();
+        self.normalizer.normalize(line).hash(&mut hasher);
+        let hash = hasher.finish();
+        if !self.hashes.insert(hash) {
+            return true;
+        }
+        if self.order.len() == SEEN_LIMIT {
+            let oldest = self.order.pop_front().unwrap();
+            self.hashes.remove(&oldest);
+        }
+        self.order.push_back(hash);
+        false
+    }
+}
+
+/// Which recording a line came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Lines of each side without a counterpart on the other, in their original order: first
+/// those only in `left`, then those only in `right`. A line occurring more often on one
+/// side than the other is unmatched for the extra occurrences.
+pub fn unmatched<'a>(
+    left: &'a [String],
+    right: &'a [String],
+    normalizer: &Normalizer,
+) -> Vec<(Side, &'a str)> {
+    let left_keys: Vec<String> = left.iter().map(|line| normalizer.normalize(line)).collect();
+    let right_keys: Vec<String> = right
+        .iter()
+        .map(|line| normalizer.normalize(line))
+        .collect();
+    let count = |keys: &[String]| {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for key in keys {
+            *counts.entry(key.clone()).or_insert(0) += 1;
+        }
+        counts
+    };
+    let (mut in_left, mut in_right) = (count(&left_keys), count(&right_keys));
+    let mut lines = Vec::new();
+    for (side, keys, lines_of_side, other) in [
+        (Side::Left, &left_keys, left, &mut in_right),
+        (Side::Right, &right_keys, right, &mut in_left),
+    ] {
+        for (key, line) in keys.iter().zip(lines_of_side) {
+            match other.get_mut(key) {
+                Some(remaining) if *remaining > 0 => *remaining -= 1,
+                _ => lines.push((side, line.as_str())),
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_unmatched_lines() {
+        let normalizer = Normalizer::new(&[r"host=\w+".to_string()]).unwrap();
+        let good = lines(&["start 1 host=a", "ok 12ms", "ok 15ms", "done"]);
+        let bad = lines(&["start 2 host=b", "ok 9ms", "timeout", "done"]);
+        assert_eq!(
+            unmatched(&good, &bad, &normalizer),
+            vec![(Side::Left, "ok 15ms"), (Side::Right, "timeout")]
+        );
+        assert!(Normalizer::new(&["(".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_known_lines() {
+        let path = std::env::temp_dir().join(format!("pipe_tools-known-{}", std::process::id()));
+        fs::write(&path, "connected in 12ms\r\nready\n").unwrap();
+        let known = Known::load(&path, Normalizer::new(&[]).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(known.contains("connected in 30ms"));
+        assert!(known.contains("ready"));
+        assert!(!known.contains("test_login FAILED"));
+    }
+
+    #[test]
+    fn test_seen_lines() {
+        let mut seen = Seen::new(Normalizer::new(&[]).unwrap());
+        assert!(!seen.repeated("GET /health 12ms"));
+        assert!(!seen.repeated("disk full"));
+        assert!(seen.repeated("GET /health 9ms"));
+        assert!(!seen.repeated("GET /users 9ms"));
+    }
+}
@@ -0,0 +1,108 @@
+//! Counting distinct values, exactly while that is cheap and approximately after.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+/// Distinct values remembered exactly before switching to the HyperLogLog estimate.
+const EXACT_LIMIT: usize = 100_000;
+/// HyperLogLog uses 2^PRECISION registers, giving about 1.6% standard error.
+const PRECISION: u32 = 12;
+
+/// Number of distinct values seen.
+#[derive(Debug, Clone)]
+pub struct Distinct {
+    /// Hashes of every value seen, until there are too many to keep.
+    exact: Option<HashSet<u64>>,
+    registers: Vec<u8>,
+}
+
+impl Default for Distinct {
+    fn default() -> Self {
+        Distinct {
+            exact: Some(HashSet::new()),
+            registers: vec![0; 1 << PRECISION],
+        }
+    }
+}
+
+impl Distinct {
+    pub fn record(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - PRECISION)) as usize;
+        let rank = ((hash << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+
+        if let Some(exact) = &mut self.exact {
+            exact.insert(hash);
+            if exact.len() > EXACT_LIMIT {
+                self.exact = None;
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exact.as_ref().is_some_and(HashSet::is_empty)
+    }
+
+    /// Whether `count` is an estimate rather than an exact figure.
+    pub fn is_estimate(&self) -> bool {
+        self.exact.is_none()
+    }
+
+    pub fn count(&self) -> u64 {
+        if let Some(exact) = &self.exact {
+            return exact.len() as u64;
+        }
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            // Linear counting is more accurate for small cardinalities.
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+
+    /// Status area text such as `1234`, or `~250000` once estimated.
+    pub fn summary(&self) -> String {
+        if self.is_estimate() {
+            format!("~{}", self.count())
+        } else {
+            self.count().to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_count() {
+        let mut distinct = Distinct::default();
+        for value in ["a", "b", "a", "c", "b"] {
+            distinct.record(value);
+        }
+        assert_eq!(distinct.count(), 3);
+        assert_eq!(distinct.summary(), "3");
+    }
+
+    #[test]
+    fn test_estimate_beyond_limit() {
+        let mut distinct = Distinct::default();
+        let total = EXACT_LIMIT * 2;
+        for i in 0..total {
+            distinct.record(&i.to_string());
+        }
+        assert!(distinct.is_estimate());
+        let error = (distinct.count() as f64 - total as f64).abs() / total as f64;
+        assert!(error < 0.05, "estimate {} too far off", distinct.count());
+    }
+}
@@ -0,0 +1,344 @@
+//! Single-line text editing with a cursor and a kill buffer, in the style of Emacs and
+//! readline, with an optional vi command mode.
+
+#[derive(Debug, Clone, Default)]
+pub struct LineEditor {
+    text: String,
+    /// Byte offset of the cursor, always on a character boundary.
+    cursor: usize,
+    /// Text removed by the last kill, for yanking back.
+    killed: String,
+}
+
+impl LineEditor {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replace the text, leaving the cursor at the end of the line.
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Remove the character before the cursor.
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Remove the character under the cursor.
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Move to the start of the current or previous word.
+    pub fn word_left(&mut self) {
+        let before = &self.text[..self.cursor];
+        let end = before
+            .trim_end_matches(|c: char| !c.is_alphanumeric())
+            .len();
+        self.cursor = before[..end].trim_end_matches(char::is_alphanumeric).len();
+    }
+
+    /// Move to the end of the current or next word.
+    pub fn word_right(&mut self) {
+        let after = &self.text[self.cursor..];
+        let start = after.len()
+            - after
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .len();
+        let word = &after[start..];
+        let rest = word.trim_start_matches(char::is_alphanumeric).len();
+        self.cursor = self.text.len() - rest;
+    }
+
+    /// Cut from the cursor to the end of the line into the kill buffer.
+    pub fn kill_to_end(&mut self) {
+        self.killed = self.text.split_off(self.cursor);
+    }
+
+    /// Insert the most recently killed text at the cursor.
+    pub fn yank(&mut self) {
+        let killed = self.killed.clone();
+        self.insert(&killed);
+    }
+
+    /// Cut the bytes in `start..end` into the kill buffer, leaving the cursor at `start`.
+    fn kill(&mut self, start: usize, end: usize) {
+        self.killed = self.text.drain(start..end).collect();
+        self.cursor = start;
+    }
+
+    /// Offset just past the character starting at `at`.
+    fn after(&self, at: usize) -> usize {
+        self.text[at..]
+            .chars()
+            .next()
+            .map_or(at, |c| at + c.len_utf8())
+    }
+
+    /// Start of the last character, where the cursor stays in vi command mode.
+    fn last_char(&self) -> usize {
+        self.text.char_indices().next_back().map_or(0, |(at, _)| at)
+    }
+}
+
+/// What a key did in vi command mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViAction {
+    /// The text or the cursor may have changed.
+    Done,
+    /// Switch to inserting text.
+    Insert,
+    /// Show the previous (`k`) or next (`j`) filter from the history.
+    Older,
+    Newer,
+    Unbound,
+}
+
+/// vi command mode state.
+#[derive(Debug, Clone, Default)]
+pub struct Vi {
+    /// `d` or `c`, waiting for the motion it applies to.
+    operator: Option<u8>,
+}
+
+impl Vi {
+    pub fn key(&mut self, editor: &mut LineEditor, key: u8) -> ViAction {
+        if let Some(operator) = self.operator.take() {
+            // As in vi, `cw` leaves the space after the word alone.
+            let key = if operator == b'c' && key == b'w' {
+                b'e'
+            } else {
+                key
+            };
+            // `dd` and `cc` take the whole line.
+            let range = match motion(editor, key) {
+                _ if key == operator => Some((0, editor.text.len())),
+                Some((to, inclusive)) => {
+                    let end = if inclusive { editor.after(to) } else { to };
+                    Some((editor.cursor.min(end), editor.cursor.max(end)))
+                }
+                None => None,
+            };
+            let Some((start, end)) = range else {
+                return ViAction::Done;
+            };
+            editor.kill(start, end);
+            if operator == b'c' {
+                return ViAction::Insert;
+            }
+            editor.cursor = editor.cursor.min(editor.last_char());
+            return ViAction::Done;
+        }
+        let action = match key {
+            b'd' | b'c' => {
+                self.operator = Some(key);
+                return ViAction::Done;
+            }
+            b'D' | b'C' => {
+                editor.kill_to_end();
+                if key == b'C' {
+                    return ViAction::Insert;
+                }
+                ViAction::Done
+            }
+            b'x' | b's' => {
+                editor.kill(editor.cursor, editor.after(editor.cursor));
+                if key == b's' {
+                    return ViAction::Insert;
+                }
+                ViAction::Done
+            }
+            b'i' => return ViAction::Insert,
+            b'a' => {
+                editor.right();
+                return ViAction::Insert;
+            }
+            b'I' => {
+                editor.home();
+                return ViAction::Insert;
+            }
+            b'A' => {
+                editor.end();
+                return ViAction::Insert;
+            }
+            b'p' | b'P' => {
+                if key == b'p' {
+                    editor.right();
+                }
+                editor.yank();
+                editor.left();
+                ViAction::Done
+            }
+            b'k' => return ViAction::Older,
+            b'j' => return ViAction::Newer,
+            _ => match motion(editor, key) {
+                Some((to, _)) => {
+                    editor.cursor = to;
+                    ViAction::Done
+                }
+                None => ViAction::Unbound,
+            },
+        };
+        editor.cursor = editor.cursor.min(editor.last_char());
+        action
+    }
+}
+
+/// Where a motion key moves the cursor, and whether an operator includes the character
+/// there.
+fn motion(editor: &LineEditor, key: u8) -> Option<(usize, bool)> {
+    let text = editor.text.as_str();
+    let cursor = editor.cursor;
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+    match key {
+        b'h' => Some((
+            text[..cursor]
+                .char_indices()
+                .next_back()
+                .map_or(0, |(at, _)| at),
+            false,
+        )),
+        b'l' => Some((editor.after(cursor), false)),
+        b'0' | b'^' => Some((0, false)),
+        b'$' => Some((editor.last_char(), true)),
+        b'w' => {
+            // Past the rest of this word, then past the gap to the next one.
+            let rest = text[cursor..].trim_start_matches(word);
+            let next = rest.trim_start_matches(|c: char| !word(c));
+            Some((text.len() - next.len(), false))
+        }
+        b'b' => {
+            let before = text[..cursor].trim_end_matches(|c: char| !word(c));
+            Some((before.trim_end_matches(word).len(), false))
+        }
+        b'e' => {
+            // The last character of this word or, if already there, of the next one.
+            let from = editor.after(cursor);
+            let gap =
+                text[from..].len() - text[from..].trim_start_matches(|c: char| !word(c)).len();
+            let start = from + gap;
+            let end = text.len() - text[start..].trim_start_matches(word).len();
+            let last = text[..end]
+                .char_indices()
+                .next_back()
+                .map_or(0, |(at, _)| at);
+            Some((last.max(cursor), true))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_movement_and_editing() {
+        let mut editor = LineEditor::default();
+        editor.set("status=500 path");
+        editor.word_left();
+        assert_eq!(editor.cursor(), 11);
+        editor.word_left();
+        editor.word_left();
+        assert_eq!(editor.cursor(), 0);
+        editor.word_right();
+        assert_eq!(editor.cursor(), 6);
+        editor.right();
+        editor.kill_to_end();
+        assert_eq!(editor.text(), "status=");
+        editor.insert("404");
+        editor.end();
+        editor.yank();
+        assert_eq!(editor.text(), "status=404500 path");
+        editor.home();
+        editor.delete();
+        assert_eq!(editor.text(), "tatus=404500 path");
+    }
+
+    fn vi(text: &str, cursor: usize, keys: &str) -> (String, usize) {
+        let mut editor = LineEditor::default();
+        editor.set(text);
+        editor.cursor = cursor;
+        let mut vi = Vi::default();
+        for key in keys.bytes() {
+            vi.key(&mut editor, key);
+        }
+        (editor.text, editor.cursor)
+    }
+
+    #[test]
+    fn test_vi_motions_and_operators() {
+        assert_eq!(vi("foo bar baz", 0, "w"), ("foo bar baz".to_string(), 4));
+        assert_eq!(vi("foo bar baz", 0, "ww"), ("foo bar baz".to_string(), 8));
+        assert_eq!(vi("foo bar baz", 8, "b"), ("foo bar baz".to_string(), 4));
+        assert_eq!(vi("foo bar baz", 0, "e"), ("foo bar baz".to_string(), 2));
+        assert_eq!(vi("foo bar baz", 0, "$"), ("foo bar baz".to_string(), 10));
+        assert_eq!(vi("foo bar baz", 4, "dw"), ("foo baz".to_string(), 4));
+        assert_eq!(vi("foo bar baz", 4, "de"), ("foo  baz".to_string(), 4));
+        assert_eq!(vi("foo bar baz", 4, "d$"), ("foo ".to_string(), 3));
+        assert_eq!(vi("foo bar baz", 4, "dd"), ("".to_string(), 0));
+        assert_eq!(vi("foo bar baz", 4, "xp"), ("foo abr baz".to_string(), 5));
+        assert_eq!(vi("foo bar baz", 4, "dwP"), ("foo bar baz".to_string(), 7));
+    }
+
+    #[test]
+    fn test_vi_change_switches_to_insert() {
+        let mut editor = LineEditor::default();
+        editor.set("status=500");
+        editor.home();
+        let mut vi = Vi::default();
+        assert_eq!(vi.key(&mut editor, b'c'), ViAction::Done);
+        assert_eq!(vi.key(&mut editor, b'w'), ViAction::Insert);
+        assert_eq!(editor.text(), "=500");
+        assert_eq!(vi.key(&mut editor, b'k'), ViAction::Older);
+        assert_eq!(vi.key(&mut editor, b'Z'), ViAction::Unbound);
+    }
+
+    #[test]
+    fn test_multibyte_characters() {
+        let mut editor = LineEditor::default();
+        editor.set("héé");
+        editor.left();
+        editor.backspace();
+        assert_eq!(editor.text(), "hé");
+        assert_eq!(editor.cursor(), 1);
+        editor.right();
+        assert_eq!(editor.cursor(), 3);
+    }
+}
@@ -0,0 +1,265 @@
+//! Filters combining terms with AND (`&`), OR (`|`), NOT and parentheses, such as
+//! `error AND NOT test` or `foo|bar & baz`. AND binds tighter than OR.
+//!
+//! Parsing is lenient so a half-typed expression still filters: an operator missing an
+//! operand, as in `error AND `, is ignored, as is an unclosed parenthesis.
+
+use regex::Regex;
+use std::ops::Range;
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Term(Regex),
+    /// A fuzzy term, with a group for each of its characters; those are highlighted
+    /// rather than everything between them.
+    Fuzzy(Regex),
+    Not(Box<Expr>),
+    All(Vec<Expr>),
+    Any(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Text(String),
+}
+
+/// Split `text` into terms and operators. With `regex`, `|` and parentheses belong to the
+/// regex, so only `&` and the words are operators.
+fn tokenize(text: &str, regex: bool) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let push_text = |tokens: &mut Vec<Token>, text: &str| {
+        if !text.trim().is_empty() {
+            tokens.push(Token::Text(text.trim().to_string()));
+        }
+    };
+    let is_operator = |c: u8| c == b'&' || (!regex && matches!(c, b'|' | b'(' | b')'));
+    let bytes = text.as_bytes();
+    // Start of the text not yet made a term; every split is next to ASCII, so on a char
+    // boundary.
+    let mut run = 0;
+    let mut i = 0;
+    'scan: while i < bytes.len() {
+        let token = match bytes[i] {
+            b'&' => Some(Token::And),
+            b'|' if !regex => Some(Token::Or),
+            b'(' if !regex => Some(Token::Open),
+            b')' if !regex => Some(Token::Close),
+            _ => None,
+        };
+        if let Some(token) = token {
+            push_text(&mut tokens, &text[run..i]);
+            tokens.push(token);
+            i += 1;
+            run = i;
+            continue;
+        }
+        let starts_word = i == 0 || bytes[i - 1].is_ascii_whitespace() || is_operator(bytes[i - 1]);
+        if starts_word {
+            for (word, token) in [("AND", Token::And), ("OR", Token::Or), ("NOT", Token::Not)] {
+                let end = i + word.len();
+                let ends_word = bytes
+                    .get(end)
+                    .is_none_or(|&c| c.is_ascii_whitespace() || is_operator(c));
+                if text[i..].starts_with(word) && ends_word {
+                    push_text(&mut tokens, &text[run..i]);
+                    tokens.push(token);
+                    i = end;
+                    run = i;
+                    continue 'scan;
+                }
+            }
+        }
+        i += 1;
+    }
+    push_text(&mut tokens, &text[run..]);
+    tokens
+}
+
+struct Parser<'a, F> {
+    tokens: &'a [Token],
+    at: usize,
+    build: F,
+}
+
+impl<F: Fn(&str) -> Option<Expr>> Parser<'_, F> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn any(&mut self) -> Option<Expr> {
+        let mut list = Vec::from_iter(self.all());
+        while self.peek() == Some(&Token::Or) {
+            self.at += 1;
+            list.extend(self.all());
+        }
+        combine(list, Expr::Any)
+    }
+
+    fn all(&mut self) -> Option<Expr> {
+        let mut list = Vec::from_iter(self.unary());
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.at += 1,
+                // Terms side by side, as in `error (a|b)`, must both be there.
+                Some(Token::Not | Token::Open | Token::Text(_)) => {}
+                _ => break,
+            }
+            list.extend(self.unary());
+        }
+        combine(list, Expr::All)
+    }
+
+    fn unary(&mut self) -> Option<Expr> {
+        let token = self.peek()?.clone();
+        match token {
+            Token::Not => {
+                self.at += 1;
+                Some(Expr::Not(Box::new(self.unary()?)))
+            }
+            Token::Open => {
+                self.at += 1;
+                let inner = self.any();
+                if self.peek() == Some(&Token::Close) {
+                    self.at += 1;
+                }
+                inner
+            }
+            Token::Text(text) => {
+                self.at += 1;
+                (self.build)(&text)
+            }
+            // Left for the caller, or a stray `)` skipped.
+            Token::And | Token::Or => None,
+            Token::Close => {
+                self.at += 1;
+                None
+            }
+        }
+    }
+}
+
+fn combine(mut list: Vec<Expr>, make: fn(Vec<Expr>) -> Expr) -> Option<Expr> {
+    match list.len() {
+        0 => None,
+        1 => list.pop(),
+        _ => Some(make(list)),
+    }
+}
+
+/// The expression in `text`, with each term compiled by `build`; `None` if `text` has no
+/// operators, so it is a plain filter.
+pub fn parse(text: &str, regex: bool, build: impl Fn(&str) -> Option<Expr>) -> Option<Expr> {
+    let tokens = tokenize(text, regex);
+    if !tokens
+        .iter()
+        .any(|token| matches!(token, Token::And | Token::Or | Token::Not))
+    {
+        return None;
+    }
+    let mut parser = Parser {
+        tokens: &tokens,
+        at: 0,
+        build,
+    };
+    let mut list = Vec::new();
+    // Whatever a stray operator leaves after it is still part of the filter.
+    while parser.at < tokens.len() {
+        list.extend(parser.any());
+        parser.at += 1;
+    }
+    // An expression of nothing but operators matches everything, as an empty filter does.
+    Some(combine(list, Expr::Any).unwrap_or(Expr::All(Vec::new())))
+}
+
+impl Expr {
+    pub fn is_match(&self, line: &str) -> bool {
+        match self {
+            Expr::Term(regex) | Expr::Fuzzy(regex) => regex.is_match(line),
+            Expr::Not(inner) => !inner.is_match(line),
+            Expr::All(list) => list.iter().all(|expr| expr.is_match(line)),
+            Expr::Any(list) => list.iter().any(|expr| expr.is_match(line)),
+        }
+    }
+
+    /// What the first group of each term not under a NOT captured in `line`, for each
+    /// time it matched.
+    pub fn captures<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match self {
+            Expr::Term(regex) => regex
+                .captures_iter(line)
+                .filter_map(|captures| captures.iter().skip(1).flatten().next())
+                .map(|group| group.as_str())
+                .collect(),
+            Expr::Fuzzy(_) | Expr::Not(_) => Vec::new(),
+            Expr::All(list) | Expr::Any(list) => {
+                list.iter().flat_map(|expr| expr.captures(line)).collect()
+            }
+        }
+    }
+
+    /// Where the terms not under a NOT are found in `line`.
+    pub fn ranges(&self, line: &str) -> Vec<Range<usize>> {
+        match self {
+            Expr::Term(regex) => regex.find_iter(line).map(|found| found.range()).collect(),
+            Expr::Fuzzy(regex) => regex
+                .captures_iter(line)
+                .flat_map(|captures| {
+                    let groups: Vec<_> = captures.iter().skip(1).flatten().collect();
+                    groups.into_iter().map(|group| group.range())
+                })
+                .collect(),
+            Expr::Not(_) => Vec::new(),
+            Expr::All(list) | Expr::Any(list) => {
+                list.iter().flat_map(|expr| expr.ranges(line)).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> Expr {
+        parse(text, false, |term| {
+            Regex::new(&regex::escape(term)).ok().map(Expr::Term)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_operators() {
+        let expr = parsed("error AND NOT test");
+        assert!(expr.is_match("error in prod"));
+        assert!(!expr.is_match("error in test"));
+        let expr = parsed("foo|bar & baz");
+        assert!(expr.is_match("foo"));
+        assert!(expr.is_match("bar baz"));
+        assert!(!expr.is_match("bar"));
+        let expr = parsed("(foo OR bar) AND connection refused");
+        assert!(expr.is_match("bar: connection refused"));
+        assert!(!expr.is_match("foo: connection reset"));
+        assert_eq!(parsed("a & NOT b").ranges("a b"), vec![0..1]);
+        assert!(parse("f(x) ANDROID", false, |_| None).is_none());
+    }
+
+    #[test]
+    fn test_half_typed() {
+        assert!(parsed("error AND ").is_match("error"));
+        assert!(parsed("error OR").is_match("error"));
+        assert!(!parsed("error OR").is_match("other"));
+        assert!(parsed("(error AND NOT").is_match("error"));
+        assert!(parsed("NOT").is_match("anything"));
+        let regex = parse("a|b AND c", true, |term| {
+            Regex::new(term).ok().map(Expr::Term)
+        })
+        .unwrap();
+        assert!(regex.is_match("b c"));
+        assert!(!regex.is_match("a"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Running statistics over a numeric field pulled out of each line.
+
+use crate::access;
+use crate::container;
+use crate::profile;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Number of recent values the p95 is computed over.
+const PERCENTILE_WINDOW: usize = 10_000;
+
+/// Where the number lives in a line.
+#[derive(Debug, Clone)]
+pub enum FieldSpec {
+    /// First capture group of a regex (or the whole match if it has none).
+    Regex(Regex),
+    /// A jq-style path into a JSON object, such as `.http.latency_ms`, or into the fields
+    /// of an access log line, such as `.status`.
+    Json(Vec<String>),
+}
+
+impl FieldSpec {
+    /// Specs starting with `.` are JSON paths, anything else is a regex.
+    pub fn parse(spec: &str) -> Result<FieldSpec, String> {
+        match spec.strip_prefix('.') {
+            Some(path) => Ok(FieldSpec::Json(
+                path.split('.')
+                    .filter(|key| !key.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            )),
+            None => Regex::new(spec)
+                .map(FieldSpec::Regex)
+                .map_err(|e| format!("invalid field regex: {}", e)),
+        }
+    }
+
+    /// The field's value in `line` as a number.
+    pub fn extract(&self, line: &str) -> Option<f64> {
+        self.text(line)?.trim().parse().ok()
+    }
+
+    /// The field's value in `line` as text; JSON numbers and booleans are formatted.
+    pub fn text(&self, line: &str) -> Option<String> {
+        match self {
+            FieldSpec::Regex(regex) => {
+                let entry = container::parse(line);
+                let line = entry.as_ref().map_or(line, |entry| entry.message.as_str());
+                let captures = regex.captures(line)?;
+                let text = captures.get(1).or_else(|| captures.get(0))?.as_str();
+                Some(text.to_string())
+            }
+            FieldSpec::Json(path) => lookup(&record(line)?, path),
+        }
+    }
+}
+
+/// The value at `path` in `value` as text; numbers and booleans are formatted.
+pub fn lookup(value: &Value, path: &[String]) -> Option<String> {
+    let mut value = value;
+    for key in path {
+        value = match value {
+            Value::Object(map) => map.get(key)?,
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// The fields of `line`: a JSON object (with the common fields of known formats added)
+/// or an access log line, possibly wrapped by a container runtime (which adds `time` and
+/// `stream`).
+pub fn record(line: &str) -> Option<Value> {
+    if let Some(entry) = container::parse(line) {
+        return Some(entry.fields(record(&entry.message)));
+    }
+    serde_json::from_str(line)
+        .ok()
+        .map(profile::normalize)
+        .or_else(|| access::fields(line))
+}
+
+/// min/max/mean over all values, p95 over the most recent ones.
+#[derive(Debug, Clone, Default)]
+pub struct FieldStats {
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    sum: f64,
+    recent: VecDeque<f64>,
+}
+
+impl FieldStats {
+    pub fn record(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.recent.push_back(value);
+        if self.recent.len() > PERCENTILE_WINDOW {
+            self.recent.pop_front();
+        }
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    /// The nearest-rank 95th percentile of the recent values.
+    pub fn p95(&self) -> Option<f64> {
+        if self.recent.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.recent.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (sorted.len() as f64 * 0.95).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1)])
+    }
+
+    /// Status area summary such as `n=120 min=3 max=812 avg=41.2 p95=190`.
+    pub fn summary(&self) -> String {
+        match (self.mean(), self.p95()) {
+            (Some(mean), Some(p95)) => format!(
+                "n={} min={} max={} avg={} p95={}",
+                self.count,
+                format_number(self.min),
+                format_number(self.max),
+                format_number(mean),
+                format_number(p95)
+            ),
+            _ => "n=0".to_string(),
+        }
+    }
+}
+
+/// Whole numbers without a fraction, others with up to two decimals.
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_field() {
+        let regex = FieldSpec::parse(r"took (\d+(?:\.\d+)?)ms").unwrap();
+        assert_eq!(regex.extract("GET / took 12.5ms"), Some(12.5));
+        assert_eq!(regex.extract("no timing"), None);
+
+        let json = FieldSpec::parse(".http.latency").unwrap();
+        assert_eq!(json.extract(r#"{"http":{"latency":42}}"#), Some(42.0));
+        assert_eq!(json.extract(r#"{"http":{"latency":"7"}}"#), Some(7.0));
+        assert_eq!(json.extract("not json"), None);
+        assert_eq!(
+            FieldSpec::parse(".ip")
+                .unwrap()
+                .text(r#"{"ip":"10.0.0.1"}"#),
+            Some("10.0.0.1".to_string())
+        );
+
+        let access = r#"1.2.3.4 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.1" 503 12"#;
+        assert_eq!(
+            FieldSpec::parse(".status").unwrap().extract(access),
+            Some(503.0)
+        );
+
+        let index = FieldSpec::parse(".samples.1").unwrap();
+        assert_eq!(index.extract(r#"{"samples":[1,2,3]}"#), Some(2.0));
+        assert!(FieldSpec::parse("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_field_stats() {
+        let mut stats = FieldStats::default();
+        assert_eq!(stats.summary(), "n=0");
+        for value in 1..=100 {
+            stats.record(value as f64);
+        }
+        assert_eq!(stats.summary(), "n=100 min=1 max=100 avg=50.5 p95=95");
+    }
+}
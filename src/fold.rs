@@ -0,0 +1,132 @@
+//! Folding a burst of one repeated line, such as a crash loop, into a single summary.
+
+use crate::{progress, stats, topk};
+use std::time::{Duration, Instant};
+
+/// Repeats further apart than this start over rather than extending a burst.
+pub const GAP: Duration = Duration::from_secs(1);
+
+/// How often the summary of a running burst is redrawn.
+const REDRAW: Duration = Duration::from_millis(100);
+
+/// Repeats of the last line shown, folded away.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Burst {
+    /// Scrollback number of the line that repeats, shown before the burst.
+    pub line: usize,
+    pub repeats: u64,
+    pub started: Instant,
+    pub last: Instant,
+}
+
+impl Burst {
+    pub fn summary(&self) -> String {
+        format!(
+            "\x1B[2m  ↻ repeated {} times over {} (e shows them)\x1B[0m",
+            progress::format_count(self.repeats),
+            stats::format_duration(self.last - self.started)
+        )
+    }
+
+    /// The summary drawn over itself on the line above the cursor.
+    pub fn summary_in_place(&self) -> String {
+        format!("\x1B[1A\r\x1B[2K{}", self.summary())
+    }
+}
+
+/// Keeps track of the last line shown and any burst of repeats after it.
+#[derive(Debug, Default)]
+pub struct Folder {
+    /// The last line shown with numbers and UUIDs collapsed, its scrollback number and when
+    /// it or its latest repeat arrived.
+    last: Option<(String, usize, Instant)>,
+    burst: Option<Burst>,
+    /// When the summary of the burst was last drawn.
+    drawn: Option<Instant>,
+}
+
+/// What to do with a line after passing it to `Folder::push`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fold {
+    /// Show the line.
+    Show,
+    /// Show the line, after the final summary of the burst it ended.
+    EndBurst(Burst),
+    /// Fold the line away; the first repeat of a burst starts its summary line.
+    Start(Burst),
+    /// Fold the line away and redraw the summary in place.
+    Redraw(Burst),
+    /// Fold the line away.
+    Hide,
+}
+
+impl Folder {
+    /// Decide what to do with `line`, number `number` in the scrollback.
+    pub fn push(&mut self, line: &str, number: usize, now: Instant) -> Fold {
+        let key = topk::normalize(line);
+        match &mut self.last {
+            Some((last, shown, at)) if *last == key && now - *at <= GAP => {
+                *at = now;
+                match &mut self.burst {
+                    Some(burst) => {
+                        burst.repeats += 1;
+                        burst.last = now;
+                        let burst = *burst;
+                        match self.drawn {
+                            Some(drawn) if now - drawn < REDRAW => Fold::Hide,
+                            _ => {
+                                self.drawn = Some(now);
+                                Fold::Redraw(burst)
+                            }
+                        }
+                    }
+                    None => {
+                        let burst = Burst {
+                            line: *shown,
+                            repeats: 1,
+                            started: now,
+                            last: now,
+                        };
+                        self.burst = Some(burst);
+                        self.drawn = Some(now);
+                        Fold::Start(burst)
+                    }
+                }
+            }
+            _ => {
+                self.last = Some((key, number, now));
+                self.drawn = None;
+                match self.burst.take() {
+                    Some(burst) => Fold::EndBurst(burst),
+                    None => Fold::Show,
+                }
+            }
+        }
+    }
+
+    /// The burst in progress, whose summary may need a final redraw.
+    pub fn burst(&self) -> Option<Burst> {
+        self.burst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folds_close_repeats() {
+        let mut folder = Folder::default();
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        assert_eq!(folder.push("error 1 at 10", 0, at(0)), Fold::Show);
+        let Fold::Start(burst) = folder.push("error 2 at 11", 1, at(10)) else {
+            panic!("no burst started");
+        };
+        assert_eq!(burst.line, 0);
+        assert_eq!(folder.push("error 3 at 12", 2, at(20)), Fold::Hide);
+        assert!(matches!(folder.push("error", 3, at(200)), Fold::EndBurst(b) if b.repeats == 2));
+        // Too far apart to count as a burst.
+        assert_eq!(folder.push("error", 4, at(1500)), Fold::Show);
+    }
+}
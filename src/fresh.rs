@@ -0,0 +1,147 @@
+//! `--fade`: new lines start on a lit background that fades out over a few seconds, so
+//! what arrived while looking away stands out.
+//!
+//! Lines are repainted where they sit, found by counting rows up from the cursor, so
+//! everything the printer writes is noted here to keep the count right.
+
+use crate::width;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Backgrounds a new line steps through, each shown for `STEP`.
+const STAGES: [&str; 4] = ["48;5;240", "48;5;238", "48;5;236", "48;5;234"];
+const STEP: Duration = Duration::from_millis(750);
+
+/// Lines written recently enough to be on screen, however tall the terminal.
+const KEPT: usize = 500;
+
+#[derive(Debug, Clone)]
+struct Written {
+    text: String,
+    /// When a fading line arrived, and the stage it was last painted in; `None` once it
+    /// has faded or for rows that never fade.
+    fading: Option<(Instant, usize)>,
+}
+
+/// Rows written below the scroll region's top, newest last.
+#[derive(Debug, Default)]
+pub struct Fresh {
+    written: VecDeque<Written>,
+}
+
+/// `text` on the background of `stage`, kept through any resets inside it and filled to
+/// the end of each row.
+fn styled(text: &str, stage: usize) -> String {
+    let sgr = STAGES[stage];
+    let text = text
+        .replace("\x1B[0m", &format!("\x1B[0;{}m", sgr))
+        .replace('\n', "\x1B[K\n");
+    format!("\x1B[{}m{}\x1B[K\x1B[0m", sgr, text)
+}
+
+fn stage(age: Duration) -> Option<usize> {
+    let stage = (age.as_millis() / STEP.as_millis()) as usize;
+    (stage < STAGES.len()).then_some(stage)
+}
+
+impl Fresh {
+    fn keep(&mut self, written: Written) {
+        if self.written.len() == KEPT {
+            self.written.pop_front();
+        }
+        self.written.push_back(written);
+    }
+
+    /// Note a new line, returning it as it should be written now.
+    pub fn line(&mut self, text: &str, now: Instant) -> String {
+        self.keep(Written {
+            text: text.to_string(),
+            fading: Some((now, 0)),
+        });
+        styled(text, 0)
+    }
+
+    /// Note rows written that do not fade, such as separators.
+    pub fn other(&mut self, text: &str) {
+        for row in text.split('\n') {
+            self.keep(Written {
+                text: row.to_string(),
+                fading: None,
+            });
+        }
+    }
+
+    /// Forget what was written, as when the screen is drawn over.
+    pub fn forget(&mut self) {
+        self.written.clear();
+    }
+
+    /// Escape sequences repainting the lines whose stage has moved on, for a scroll region
+    /// `height` rows tall and `columns` wide with the cursor on the row after the last
+    /// line. Lines scrolled out of the region are forgotten.
+    pub fn repaint(&mut self, height: usize, columns: usize, now: Instant) -> String {
+        let mut repaint = String::new();
+        let mut above = 0;
+        let mut kept = 0;
+        for written in self.written.iter_mut().rev() {
+            above += written
+                .text
+                .split('\n')
+                .map(|row| width::width(row).div_ceil(columns.max(1)).max(1))
+                .sum::<usize>();
+            if above > height {
+                break;
+            }
+            kept += 1;
+            let Some((arrived, painted)) = written.fading else {
+                continue;
+            };
+            let now_stage = stage(now - arrived);
+            if now_stage == Some(painted) {
+                continue;
+            }
+            let text = match now_stage {
+                Some(stage) => {
+                    written.fading = Some((arrived, stage));
+                    styled(&written.text, stage)
+                }
+                None => {
+                    written.fading = None;
+                    format!("{}\x1B[K", written.text.replace('\n', "\x1B[K\n"))
+                }
+            };
+            repaint.push_str(&format!("\x1B7\x1B[{}A\r{}\x1B8", above, text));
+        }
+        let forgotten = self.written.len() - kept;
+        self.written.drain(..forgotten);
+        repaint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fades_in_place() {
+        let mut fresh = Fresh::default();
+        let start = Instant::now();
+        assert_eq!(fresh.line("new", start), "\x1B[48;5;240mnew\x1B[K\x1B[0m");
+        fresh.other("── 2 lines hidden ──");
+        assert_eq!(fresh.repaint(10, 80, start), "");
+        assert_eq!(
+            fresh.repaint(10, 80, start + STEP),
+            "\x1B7\x1B[2A\r\x1B[48;5;238mnew\x1B[K\x1B[0m\x1B8"
+        );
+        assert_eq!(
+            fresh.repaint(10, 80, start + STEP * 4),
+            "\x1B7\x1B[2A\rnew\x1B[K\x1B8"
+        );
+        assert_eq!(fresh.repaint(10, 80, start + STEP * 5), "");
+        // Scrolled off the top.
+        fresh.line("a", start);
+        fresh.other("b\nc");
+        assert_eq!(fresh.repaint(2, 80, start + STEP), "");
+        assert!(fresh.written.len() <= 2);
+    }
+}
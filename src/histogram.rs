@@ -0,0 +1,173 @@
+//! Matches bucketed over time, to tell a burst from a steady leak.
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Width of a histogram bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    Second,
+    Minute,
+}
+
+impl Resolution {
+    fn seconds(self) -> u64 {
+        match self {
+            Resolution::Second => 1,
+            Resolution::Minute => 60,
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            Resolution::Second => "s",
+            Resolution::Minute => "m",
+        }
+    }
+}
+
+/// Match counts per second and per minute since `started`, keeping a bounded history.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    started: Instant,
+    seconds: Buckets,
+    minutes: Buckets,
+}
+
+/// Counts for consecutive buckets, the last of which is bucket number `last`.
+#[derive(Debug, Clone)]
+struct Buckets {
+    counts: VecDeque<u64>,
+    last: u64,
+    capacity: usize,
+}
+
+impl Buckets {
+    fn new(capacity: usize) -> Self {
+        Buckets {
+            counts: VecDeque::from([0]),
+            last: 0,
+            capacity,
+        }
+    }
+
+    /// Make `bucket` the newest bucket, filling any gap with empty buckets.
+    fn advance(&mut self, bucket: u64) {
+        while self.last < bucket {
+            self.counts.push_back(0);
+            self.last += 1;
+            if self.counts.len() > self.capacity {
+                self.counts.pop_front();
+            }
+        }
+    }
+}
+
+impl Histogram {
+    pub fn new(started: Instant) -> Self {
+        Histogram {
+            started,
+            seconds: Buckets::new(3600),
+            minutes: Buckets::new(24 * 60),
+        }
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.started).as_secs();
+        for (buckets, resolution) in [
+            (&mut self.seconds, Resolution::Second),
+            (&mut self.minutes, Resolution::Minute),
+        ] {
+            buckets.advance(elapsed / resolution.seconds());
+            *buckets.counts.back_mut().unwrap() += 1;
+        }
+    }
+
+    /// All retained bucket counts up to `now`, oldest first.
+    pub fn counts(&mut self, resolution: Resolution, now: Instant) -> Vec<u64> {
+        let elapsed = now.saturating_duration_since(self.started).as_secs();
+        let buckets = match resolution {
+            Resolution::Second => &mut self.seconds,
+            Resolution::Minute => &mut self.minutes,
+        };
+        buckets.advance(elapsed / resolution.seconds());
+        buckets.counts.iter().copied().collect()
+    }
+
+    /// Render the most recent buckets as horizontal bars, newest at the bottom.
+    pub fn render(
+        &mut self,
+        resolution: Resolution,
+        now: Instant,
+        width: usize,
+        height: usize,
+    ) -> Vec<String> {
+        let counts = self.counts(resolution, now);
+        let shown = height.saturating_sub(1).min(counts.len());
+        let recent = &counts[counts.len() - shown..];
+        let max = recent.iter().copied().max().unwrap_or(0).max(1);
+        let label_width = format!("-{}{}", shown, resolution.unit()).len();
+        let bar_width = width.saturating_sub(label_width + 12).max(1);
+
+        let mut lines = vec![format!(
+            "matches per {} (max {})",
+            match resolution {
+                Resolution::Second => "second",
+                Resolution::Minute => "minute",
+            },
+            max
+        )];
+        for (i, count) in recent.iter().enumerate() {
+            let age = shown - 1 - i;
+            let label = if age == 0 {
+                "now".to_string()
+            } else {
+                format!("-{}{}", age, resolution.unit())
+            };
+            let bar = (*count as usize * bar_width).div_ceil(max as usize);
+            lines.push(format!(
+                "{:>label_width$} |{:<bar_width$} {}",
+                label,
+                "#".repeat(bar),
+                count
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let start = Instant::now();
+        let mut histogram = Histogram::new(start);
+        histogram.record(start);
+        histogram.record(start + Duration::from_millis(500));
+        histogram.record(start + Duration::from_secs(2));
+
+        let lines = histogram.render(Resolution::Second, start + Duration::from_secs(3), 30, 10);
+        assert_eq!(lines[0], "matches per second (max 2)");
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("-3s |") && lines[1].ends_with(" 2"));
+        assert!(lines[2].ends_with(" 0"));
+        assert!(lines[3].ends_with(" 1"));
+        assert!(lines[4].starts_with("now |"));
+
+        let lines = histogram.render(Resolution::Minute, start + Duration::from_secs(3), 30, 10);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with(" 3"));
+    }
+
+    #[test]
+    fn test_histogram_limits_history() {
+        let start = Instant::now();
+        let mut histogram = Histogram::new(start);
+        histogram.record(start + Duration::from_secs(7200));
+        assert_eq!(histogram.seconds.counts.len(), 3600);
+        assert_eq!(histogram.minutes.counts.len(), 121);
+    }
+}
@@ -0,0 +1,169 @@
+//! Filters used earlier in the session, recalled with Up and Down, and with
+//! --save-history in earlier sessions too.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Entries kept in the history file; older ones are dropped when it is loaded.
+const SAVED_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// File each committed filter is appended to.
+    file: Option<PathBuf>,
+    /// Entry being shown while browsing, or `None` when editing a new filter.
+    position: Option<usize>,
+    /// The filter being edited before browsing started, shown again past the newest entry.
+    draft: String,
+}
+
+impl History {
+    /// The history saved in `path`, one filter per line, to which new filters are added;
+    /// a file that does not exist yet is created.
+    pub fn load(path: &Path) -> io::Result<History> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                String::new()
+            }
+            Err(e) => return Err(e),
+        };
+        let mut entries: Vec<String> = text.lines().map(str::to_string).collect();
+        if entries.len() > SAVED_ENTRIES {
+            entries.drain(..entries.len() - SAVED_ENTRIES);
+            fs::write(path, entries.join("\n") + "\n")?;
+        }
+        Ok(History {
+            entries,
+            file: Some(path.to_path_buf()),
+            ..History::default()
+        })
+    }
+
+    /// Remember `filter`, unless it is empty or the same as the newest entry.
+    pub fn commit(&mut self, filter: &str) {
+        self.position = None;
+        if !filter.is_empty() && self.entries.last().is_none_or(|last| last != filter) {
+            self.entries.push(filter.to_string());
+            // A filter on several lines would come back as several.
+            if let Some(file) = self.file.as_ref().filter(|_| !filter.contains('\n')) {
+                // Losing the history is not worth interrupting the session for.
+                let _ = OpenOptions::new()
+                    .append(true)
+                    .create(true)
+                    .open(file)
+                    .and_then(|mut file| writeln!(file, "{}", filter));
+            }
+        }
+    }
+
+    /// Step back to an older filter, given the one currently being edited.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(position) => position - 1,
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Step forward to a newer filter, ending with the one that was being edited.
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// The newest entry containing `query` that is older than entry `before` (or any
+    /// entry, if `before` is `None`), with its index.
+    pub fn search(&self, query: &str, before: Option<usize>) -> Option<(usize, &str)> {
+        let end = before.unwrap_or(self.entries.len());
+        self.entries[..end]
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, entry)| entry.contains(query))
+            .map(|(index, entry)| (index, entry.as_str()))
+    }
+
+    /// Stop browsing, keeping whatever is shown as the filter being edited.
+    pub fn edited(&mut self) {
+        self.position = None;
+    }
+}
+
+/// Where --save-history keeps filters: `$XDG_DATA_HOME/pipe_tools/history`, by default
+/// under `~/.local/share`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(base.join("pipe_tools").join("history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_browse_history() {
+        let mut history = History::default();
+        assert_eq!(history.previous("x"), None);
+        for filter in ["ERROR", "WARN", "WARN", ""] {
+            history.commit(filter);
+        }
+        assert_eq!(history.previous("draft"), Some("WARN"));
+        assert_eq!(history.previous("WARN"), Some("ERROR"));
+        assert_eq!(history.previous("ERROR"), None);
+        assert_eq!(history.next(), Some("WARN"));
+        assert_eq!(history.next(), Some("draft"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut history = History::default();
+        for filter in [r"status=5\d\d", "timeout", r"status=4\d\d"] {
+            history.commit(filter);
+        }
+        assert_eq!(history.search("status", None), Some((2, r"status=4\d\d")));
+        assert_eq!(
+            history.search("status", Some(2)),
+            Some((0, r"status=5\d\d"))
+        );
+        assert_eq!(history.search("status", Some(0)), None);
+        assert_eq!(history.search("nothing", None), None);
+    }
+
+    #[test]
+    fn test_saved_history() {
+        let dir = std::env::temp_dir().join(format!("pipe_tools-history-{}", std::process::id()));
+        let path = dir.join("history");
+        let mut history = History::load(&path).unwrap();
+        history.commit("ERROR");
+        history.commit("timeout");
+        let mut history = History::load(&path).unwrap();
+        assert_eq!(history.previous(""), Some("timeout"));
+        assert_eq!(history.previous("timeout"), Some("ERROR"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
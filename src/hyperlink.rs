@@ -0,0 +1,119 @@
+//! Making URLs and `file:line` references clickable with OSC 8 hyperlinks.
+
+use crate::width;
+use regex::Regex;
+use std::env;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// URLs, and paths with an extension followed by a line number such as `src/main.rs:12:5`.
+static LINKABLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\b(?P<url>(?:https?|ftp|file)://[^\s<>"'`]+)|(?:^|[\s(\['"=])(?P<path>[\w./~-]*\w\.[A-Za-z0-9]+):(?P<line>\d+)(?::\d+)?"#,
+    )
+    .unwrap()
+});
+
+/// Whether the terminal is known to understand OSC 8, judging by what it puts in the
+/// environment. Multiplexers are left out, since whether they pass links on depends on
+/// their version and settings.
+pub fn supported() -> bool {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    if !var("TMUX").is_empty() || var("TERM").starts_with("screen") {
+        return false;
+    }
+    let vte = var("VTE_VERSION").parse::<u32>().is_ok_and(|v| v >= 5000);
+    let program = matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper"
+    );
+    let term = ["kitty", "foot", "alacritty", "wezterm", "ghostty"]
+        .iter()
+        .any(|name| var("TERM").contains(name));
+    vte || program || term || !var("WT_SESSION").is_empty() || !var("KONSOLE_VERSION").is_empty()
+}
+
+/// `rendered`, which may already contain SGR escapes, with each URL and each reference to
+/// an existing file under `cwd` wrapped in an OSC 8 hyperlink.
+pub fn link(rendered: &str, cwd: &Path) -> String {
+    // The visible text, and where each of its bytes sits in `rendered`.
+    let mut visible = String::new();
+    let mut offsets = Vec::new();
+    let mut at = 0;
+    for (piece, escape) in width::pieces(rendered) {
+        if !escape {
+            visible.push_str(piece);
+            offsets.extend(at..at + piece.len());
+        }
+        at += piece.len();
+    }
+
+    let mut links = Vec::new();
+    for captures in LINKABLE.captures_iter(&visible) {
+        if let Some(url) = captures.name("url") {
+            // Sentence punctuation after a URL is rarely part of it.
+            let text = url
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', ')', '!', '?']);
+            links.push((url.start(), url.start() + text.len(), text.to_string()));
+        } else if let (Some(path), Some(line)) = (captures.name("path"), captures.name("line")) {
+            let file = cwd.join(path.as_str());
+            if file.is_file() {
+                let target = format!("file://{}#{}", file.display(), line.as_str());
+                links.push((path.start(), captures.get(0).unwrap().end(), target));
+            }
+        }
+    }
+    if links.is_empty() {
+        return rendered.to_string();
+    }
+
+    let mut linked = String::with_capacity(rendered.len() + links.len() * 32);
+    let mut copied = 0;
+    for (start, end, target) in links {
+        let (start, end) = (offsets[start], offsets[end - 1] + 1);
+        linked.push_str(&rendered[copied..start]);
+        linked.push_str(&format!("\x1B]8;;{}\x1B\\", target));
+        linked.push_str(&rendered[start..end]);
+        linked.push_str("\x1B]8;;\x1B\\");
+        copied = end;
+    }
+    linked.push_str(&rendered[copied..]);
+    linked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_links_urls_around_escapes() {
+        let cwd = Path::new("/nonexistent");
+        assert_eq!(
+            link("see https://example.com/a.", cwd),
+            "see \x1B]8;;https://example.com/a\x1B\\https://example.com/a\x1B]8;;\x1B\\."
+        );
+        // A highlight inside the URL stays inside the link.
+        assert_eq!(
+            link("at http://x.io/\x1B[31mq\x1B[0m ok", cwd),
+            "at \x1B]8;;http://x.io/q\x1B\\http://x.io/\x1B[31mq\x1B]8;;\x1B\\\x1B[0m ok"
+        );
+        assert_eq!(
+            link("main.rs:12 is not here", cwd),
+            "main.rs:12 is not here"
+        );
+    }
+
+    #[test]
+    fn test_links_existing_files() {
+        let cwd = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let target = format!("file://{}/src/main.rs#12", cwd.display());
+        assert_eq!(
+            link("error at src/main.rs:12:5 here", cwd),
+            format!(
+                "error at \x1B]8;;{}\x1B\\src/main.rs:12:5\x1B]8;;\x1B\\ here",
+                target
+            )
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! Keys and pasted text read from the terminal.
+//!
+//! Special keys arrive as escape sequences such as `ESC [A` (Up) or `ESC [5~` (Page Up),
+//! which are decoded here so their bytes never reach the filter. With bracketed paste
+//! enabled the terminal also wraps pasted text in `ESC [200~` and `ESC [201~`, which lets
+//! a paste be told apart from typing: pasted text is always literal input, never commands.
+//!
+//! Characters beyond ASCII arrive as several UTF-8 bytes (from an input method too), and
+//! are put back together into one character.
+
+use crate::terminal::KeyReader;
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+const ESC: u8 = 27;
+const PASTE_END: &[u8] = b"\x1B[201~";
+
+/// How long to wait for the rest of an escape sequence before taking Esc as a key.
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(25);
+
+/// How long a paste may stall before what arrived so far is used.
+const PASTE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Something that produces the bytes typed at the terminal.
+pub trait ByteSource {
+    fn read_byte(&mut self, timeout: Duration) -> io::Result<Option<u8>>;
+}
+
+impl ByteSource for KeyReader {
+    fn read_byte(&mut self, timeout: Duration) -> io::Result<Option<u8>> {
+        KeyReader::read_byte(self, timeout)
+    }
+}
+
+/// A key that sends an escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Special {
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    Insert,
+    Delete,
+    PageUp,
+    PageDown,
+    /// F1 to F12.
+    F(u8),
+}
+
+impl Special {
+    /// The key for a CSI sequence with the given parameters and final byte, such as
+    /// `ESC [1;5A` (Ctrl+Up, modifiers are ignored) or `ESC [15~` (F5).
+    fn from_csi(params: &[u8], last: u8) -> Option<Special> {
+        let first: u32 = std::str::from_utf8(params)
+            .ok()?
+            .split(';')
+            .next()?
+            .parse()
+            .unwrap_or(1);
+        match last {
+            b'~' => match first {
+                1 | 7 => Some(Special::Home),
+                2 => Some(Special::Insert),
+                3 => Some(Special::Delete),
+                4 | 8 => Some(Special::End),
+                5 => Some(Special::PageUp),
+                6 => Some(Special::PageDown),
+                11..=15 => Some(Special::F(first as u8 - 10)),
+                17..=21 => Some(Special::F(first as u8 - 11)),
+                23 | 24 => Some(Special::F(first as u8 - 12)),
+                _ => None,
+            },
+            _ => Self::from_final(last),
+        }
+    }
+
+    /// The key for sequences identified by their final byte alone, after `ESC [` or
+    /// `ESC O`.
+    fn from_final(last: u8) -> Option<Special> {
+        match last {
+            b'A' => Some(Special::Up),
+            b'B' => Some(Special::Down),
+            b'C' => Some(Special::Right),
+            b'D' => Some(Special::Left),
+            b'H' => Some(Special::Home),
+            b'F' => Some(Special::End),
+            b'P'..=b'S' => Some(Special::F(last - b'P' + 1)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    Key(u8),
+    /// A key pressed with Alt (or Meta), sent as ESC and the key.
+    Alt(u8),
+    Special(Special),
+    /// A character beyond ASCII.
+    Char(char),
+    Paste(String),
+}
+
+pub struct InputReader<S> {
+    source: S,
+    /// Bytes read while looking for an escape sequence that turned out not to be one.
+    pending: VecDeque<u8>,
+}
+
+impl<S: ByteSource> InputReader<S> {
+    pub fn new(source: S) -> Self {
+        InputReader {
+            source,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn next_byte(&mut self, timeout: Duration) -> io::Result<Option<u8>> {
+        match self.pending.pop_front() {
+            Some(byte) => Ok(Some(byte)),
+            None => self.source.read_byte(timeout),
+        }
+    }
+
+    /// Wait up to `timeout` for the next key or paste.
+    pub fn read(&mut self, timeout: Duration) -> io::Result<Option<Input>> {
+        loop {
+            let Some(byte) = self.next_byte(timeout)? else {
+                return Ok(None);
+            };
+            if byte >= 0x80 {
+                match self.utf8(byte)? {
+                    Some(c) => return Ok(Some(Input::Char(c))),
+                    // Malformed characters are dropped.
+                    None => continue,
+                }
+            }
+            if byte != ESC {
+                return Ok(Some(Input::Key(byte)));
+            }
+            match self.escape_sequence()? {
+                Sequence::Input(input) => return Ok(Some(input)),
+                // Keys nothing is known about are dropped whole.
+                Sequence::Unknown => continue,
+                Sequence::None(rest) => {
+                    // Esc was a key of its own, and what followed it is read again.
+                    self.pending.extend(rest);
+                    return Ok(Some(Input::Key(ESC)));
+                }
+            }
+        }
+    }
+
+    /// The character starting with the UTF-8 lead byte `first`.
+    fn utf8(&mut self, first: u8) -> io::Result<Option<char>> {
+        let length = match first {
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => return Ok(None),
+        };
+        let mut bytes = vec![first];
+        while bytes.len() < length {
+            match self.next_byte(ESCAPE_TIMEOUT)? {
+                Some(byte @ 0x80..=0xBF) => bytes.push(byte),
+                Some(byte) => {
+                    // The start of the next key, read again.
+                    self.pending.push_front(byte);
+                    return Ok(None);
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|text| text.chars().next()))
+    }
+
+    /// The next byte of an escape sequence, also kept in `sequence`.
+    fn sequence_byte(&mut self, sequence: &mut Vec<u8>) -> io::Result<Option<u8>> {
+        let byte = self.next_byte(ESCAPE_TIMEOUT)?;
+        sequence.extend(byte);
+        Ok(byte)
+    }
+
+    /// Decode what follows an ESC byte.
+    fn escape_sequence(&mut self) -> io::Result<Sequence> {
+        let mut sequence = Vec::new();
+        match self.sequence_byte(&mut sequence)? {
+            Some(b'[') => {
+                // Parameter and intermediate bytes, then a final byte from @ to ~.
+                let mut params = Vec::new();
+                loop {
+                    match self.sequence_byte(&mut sequence)? {
+                        Some(byte @ 0x20..=0x3F) => params.push(byte),
+                        Some(b'~') if params == b"200" => {
+                            return Ok(Sequence::Input(self.paste()?))
+                        }
+                        Some(last @ 0x40..=0x7E) => {
+                            return Ok(match Special::from_csi(&params, last) {
+                                Some(key) => Sequence::Input(Input::Special(key)),
+                                None => Sequence::Unknown,
+                            });
+                        }
+                        _ => return Ok(Sequence::None(sequence)),
+                    }
+                }
+            }
+            Some(byte @ 32..=126) if byte != b'O' => Ok(Sequence::Input(Input::Alt(byte))),
+            Some(b'O') => match self
+                .sequence_byte(&mut sequence)?
+                .and_then(Special::from_final)
+            {
+                Some(key) => Ok(Sequence::Input(Input::Special(key))),
+                None => Ok(Sequence::None(sequence)),
+            },
+            _ => Ok(Sequence::None(sequence)),
+        }
+    }
+
+    /// Read pasted text up to the end marker.
+    fn paste(&mut self) -> io::Result<Input> {
+        let mut text = Vec::new();
+        while !text.ends_with(PASTE_END) {
+            match self.next_byte(PASTE_TIMEOUT)? {
+                Some(byte) => text.push(byte),
+                None => break,
+            }
+        }
+        if text.ends_with(PASTE_END) {
+            text.truncate(text.len() - PASTE_END.len());
+        }
+        Ok(Input::Paste(String::from_utf8_lossy(&text).into_owned()))
+    }
+}
+
+enum Sequence {
+    Input(Input),
+    /// A well-formed sequence for a key that is not decoded.
+    Unknown,
+    /// Not an escape sequence; holds the bytes read after the ESC.
+    None(Vec<u8>),
+}
+
+/// Pasted text with line breaks and other control characters removed, for single-line
+/// fields such as the filter.
+pub fn single_line(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl ByteSource for VecDeque<u8> {
+        fn read_byte(&mut self, _timeout: Duration) -> io::Result<Option<u8>> {
+            Ok(self.pop_front())
+        }
+    }
+
+    fn read_all(bytes: &[u8]) -> Vec<Input> {
+        let mut reader = InputReader::new(bytes.iter().copied().collect::<VecDeque<u8>>());
+        std::iter::from_fn(|| reader.read(Duration::ZERO).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_paste_is_one_input() {
+        assert_eq!(
+            read_all(b"a\x1B[200~quit now\x1B[201~b"),
+            [
+                Input::Key(b'a'),
+                Input::Paste("quit now".to_string()),
+                Input::Key(b'b')
+            ]
+        );
+    }
+
+    #[test]
+    fn test_special_keys() {
+        assert_eq!(
+            read_all(b"\x1B[A\x1BOB\x1B[1;5C\x1B[3~\x1B[6~\x1BOP\x1B[15~\x1B[24~"),
+            [
+                Input::Special(Special::Up),
+                Input::Special(Special::Down),
+                Input::Special(Special::Right),
+                Input::Special(Special::Delete),
+                Input::Special(Special::PageDown),
+                Input::Special(Special::F(1)),
+                Input::Special(Special::F(5)),
+                Input::Special(Special::F(12)),
+            ]
+        );
+        // Sequences for keys that are not decoded leave nothing behind.
+        assert_eq!(read_all(b"\x1B[99~x"), [Input::Key(b'x')]);
+    }
+
+    #[test]
+    fn test_utf8_characters() {
+        assert_eq!(
+            read_all("é日🙂".as_bytes()),
+            [Input::Char('é'), Input::Char('日'), Input::Char('🙂')]
+        );
+        // A cut-off character is dropped, but not the key after it.
+        assert_eq!(read_all(b"\xE6\x97a\xFF"), [Input::Key(b'a')]);
+    }
+
+    #[test]
+    fn test_lone_escape_is_a_key() {
+        assert_eq!(read_all(b"\x1B"), [Input::Key(27)]);
+        assert_eq!(read_all(b"\x1Bb"), [Input::Alt(b'b')]);
+        assert_eq!(
+            read_all(b"\x1B[\x01"),
+            [Input::Key(27), Input::Key(b'['), Input::Key(1)]
+        );
+        assert_eq!(single_line("a\r\nb\tc"), "abc");
+    }
+}
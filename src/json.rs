@@ -0,0 +1,101 @@
+//! Pretty-printing JSON lines, split into colored tokens.
+
+use serde_json::Value;
+
+/// SGR parameters for object keys.
+const KEY: &str = "34";
+const STRING: &str = "32";
+const NUMBER: &str = "33";
+/// `true`, `false` and `null`.
+const LITERAL: &str = "35";
+
+/// Indented rendering of `line` if it is a JSON object or array, as pieces of text
+/// with the SGR parameters to color them with (`None` for punctuation and whitespace).
+pub fn pretty(line: &str) -> Option<Vec<(String, Option<&'static str>)>> {
+    let trimmed = line.trim_start();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+    let value: Value = serde_json::from_str(line).ok()?;
+    let text = serde_json::to_string_pretty(&value).ok()?;
+    Some(tokenize(&text))
+}
+
+fn tokenize(text: &str) -> Vec<(String, Option<&'static str>)> {
+    let mut tokens: Vec<(String, Option<&'static str>)> = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        let color = match c {
+            '"' => {
+                let mut escaped = false;
+                for (i, c) in chars.by_ref() {
+                    end = i + c.len_utf8();
+                    if c == '"' && !escaped {
+                        break;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+                let is_key = text[end..].trim_start().starts_with(':');
+                Some(if is_key { KEY } else { STRING })
+            }
+            '-' | '0'..='9' | 't' | 'f' | 'n' => {
+                while let Some((i, c)) = chars.peek() {
+                    if !c.is_ascii_alphanumeric() && !matches!(c, '.' | '-' | '+') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                Some(if c.is_ascii_alphabetic() {
+                    LITERAL
+                } else {
+                    NUMBER
+                })
+            }
+            _ => None,
+        };
+        match tokens.last_mut() {
+            // Runs of punctuation and whitespace become one token.
+            Some((previous, None)) if color.is_none() => previous.push_str(&text[start..end]),
+            _ => tokens.push((text[start..end].to_string(), color)),
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pretty_tokens() {
+        let tokens = pretty(r#"{"a":[1,true],"b":"x\"y"}"#).unwrap();
+        let text: String = tokens.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(
+            text,
+            "{\n  \"a\": [\n    1,\n    true\n  ],\n  \"b\": \"x\\\"y\"\n}"
+        );
+        let colored: Vec<(&str, &str)> = tokens
+            .iter()
+            .filter_map(|(t, c)| Some((t.as_str(), (*c)?)))
+            .collect();
+        assert_eq!(
+            colored,
+            vec![
+                ("\"a\"", KEY),
+                ("1", NUMBER),
+                ("true", LITERAL),
+                ("\"b\"", KEY),
+                ("\"x\\\"y\"", STRING)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pretty_ignores_other_lines() {
+        assert!(pretty("plain text").is_none());
+        assert!(pretty("{broken").is_none());
+        assert!(pretty("42").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! The pipe from the readers to the printer, with a lane for lines at level error or
+//! above and ones matching --priority; the printer takes those ahead of any backlog, so
+//! they show as soon as it gets to them while bulk lines wait behind a pause or --buffer.
+//!
+//! Records are numbered as they are sent, so one that jumps ahead keeps its own line
+//! number and its place for --cursor.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+
+type Classify = dyn Fn(&str) -> bool + Send + Sync;
+
+/// A record on its way to the printer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Input {
+    pub text: String,
+    /// Input line number of its first line, from 1; joined records count every line.
+    pub number: usize,
+    /// For records read from stdin, the number of its first line among those, which
+    /// --cursor counts.
+    pub stdin: Option<usize>,
+}
+
+#[derive(Default)]
+struct Queue {
+    priority: VecDeque<Input>,
+    /// Everything else, including the `None` marking the end of the input.
+    bulk: VecDeque<Option<Input>>,
+    senders: usize,
+    /// Lines sent so far, and those of them from stdin.
+    lines: usize,
+    stdin_lines: usize,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+    is_priority: Box<Classify>,
+}
+
+pub struct Sender {
+    shared: Arc<Shared>,
+}
+
+pub struct Receiver {
+    shared: Arc<Shared>,
+}
+
+/// A pipe where lines `is_priority` picks out go ahead of the rest.
+pub fn channel(is_priority: impl Fn(&str) -> bool + Send + Sync + 'static) -> (Sender, Receiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(Queue {
+            senders: 1,
+            ..Queue::default()
+        }),
+        ready: Condvar::new(),
+        is_priority: Box::new(is_priority),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+impl Sender {
+    /// Queue `line` from anywhere but stdin, or `None` for the end of the input; the
+    /// receiver never goes away before the senders, so this can't fail.
+    pub fn send(&self, line: Option<String>) {
+        match line {
+            Some(text) => self.push(text, false),
+            None => {
+                self.shared.queue.lock().unwrap().bulk.push_back(None);
+                self.shared.ready.notify_one();
+            }
+        }
+    }
+
+    /// Queue a record read from stdin. Records must be sent in the order their lines were
+    /// read.
+    pub fn send_stdin(&self, text: String) {
+        self.push(text, true);
+    }
+
+    fn push(&self, text: String, stdin: bool) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let lines = text.split('\n').count();
+        let input = Input {
+            text,
+            number: queue.lines + 1,
+            stdin: stdin.then_some(queue.stdin_lines + 1),
+        };
+        queue.lines += lines;
+        if stdin {
+            queue.stdin_lines += lines;
+        }
+        match (self.shared.is_priority)(&input.text) {
+            true => queue.priority.push_back(input),
+            false => queue.bulk.push_back(Some(input)),
+        }
+        self.shared.ready.notify_one();
+    }
+}
+
+impl Clone for Sender {
+    fn clone(&self) -> Sender {
+        self.shared.queue.lock().unwrap().senders += 1;
+        Sender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().senders -= 1;
+        self.shared.ready.notify_one();
+    }
+}
+
+impl Iterator for Receiver {
+    type Item = Option<Input>;
+
+    /// The next priority line, or else the next of the rest; `None` once every sender is
+    /// gone and nothing is left.
+    fn next(&mut self) -> Option<Option<Input>> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        loop {
+            if let Some(line) = queue.priority.pop_front() {
+                return Some(Some(line));
+            }
+            if let Some(line) = queue.bulk.pop_front() {
+                return Some(line);
+            }
+            if queue.senders == 0 {
+                return None;
+            }
+            queue = self.shared.ready.wait(queue).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_jumps_the_backlog() {
+        let (tx, rx) = channel(|line| line.contains("ERROR"));
+        for line in ["a", "b", "ERROR disk full"] {
+            tx.send_stdin(line.to_string());
+        }
+        tx.send(Some("c".to_string()));
+        tx.send(None);
+        let other = tx.clone();
+        drop(tx);
+        other.send(Some("ERROR late".to_string()));
+        drop(other);
+        let order: Vec<_> = rx.collect();
+        let line = |text: &str, number: usize, stdin: Option<usize>| {
+            Some(Input {
+                text: text.to_string(),
+                number,
+                stdin,
+            })
+        };
+        assert_eq!(
+            order,
+            [
+                line("ERROR disk full", 3, Some(3)),
+                line("ERROR late", 5, None),
+                line("a", 1, Some(1)),
+                line("b", 2, Some(2)),
+                line("c", 4, None),
+                None
+            ]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Detecting the log level of a line, for hiding everything below a threshold and
+//! counting lines of each level.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// The first level-like word in a line, in any case, such as `WARN`, `[error]` or `level=info`.
+static WORD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(trace|debug|info|notice|warn|warning|error|err|fatal|crit|critical|panic|emerg|alert)\b")
+        .unwrap()
+});
+
+/// Log levels from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    const ALL: [Level; 6] = [
+        Level::Trace,
+        Level::Debug,
+        Level::Info,
+        Level::Warn,
+        Level::Error,
+        Level::Fatal,
+    ];
+
+    pub fn parse(name: &str) -> Option<Level> {
+        match name.to_ascii_lowercase().as_str() {
+            "trace" => Some(Level::Trace),
+            "debug" => Some(Level::Debug),
+            "info" | "notice" => Some(Level::Info),
+            "warn" | "warning" => Some(Level::Warn),
+            "error" | "err" => Some(Level::Error),
+            "fatal" | "crit" | "critical" | "panic" | "emerg" | "alert" => Some(Level::Fatal),
+            _ => None,
+        }
+    }
+
+    /// The level's initial, as shown in the counts widget.
+    fn initial(self) -> char {
+        self.name().chars().next().unwrap()
+    }
+
+    /// SGR foreground for the level in the counts widget.
+    fn color(self) -> &'static str {
+        match self {
+            Level::Fatal | Level::Error => "31",
+            Level::Warn => "33",
+            Level::Info => "32",
+            Level::Debug | Level::Trace => "2",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+            Level::Fatal => "FATAL",
+        }
+    }
+
+    /// The next more severe level, staying at the most severe.
+    pub fn raise(self) -> Level {
+        Level::ALL[(self as usize + 1).min(Level::ALL.len() - 1)]
+    }
+
+    /// The next less severe level, staying at the least severe.
+    pub fn lower(self) -> Level {
+        Level::ALL[(self as usize).saturating_sub(1)]
+    }
+}
+
+/// Lines seen of each level.
+#[derive(Debug, Clone, Default)]
+pub struct Counts {
+    counts: [u64; 6],
+}
+
+impl Counts {
+    pub fn record(&mut self, level: Level) {
+        self.counts[level as usize] += 1;
+    }
+
+    /// Levels seen, most severe first.
+    fn seen(&self) -> impl Iterator<Item = Level> + '_ {
+        Level::ALL
+            .into_iter()
+            .rev()
+            .filter(|&level| self.counts[level as usize] > 0)
+    }
+
+    /// The level shown alone after `only`: the next less severe one seen, then all again.
+    pub fn next(&self, only: Option<Level>) -> Option<Level> {
+        self.seen()
+            .find(|&level| only.is_none_or(|only| level < only))
+    }
+
+    /// Counts such as `E:12 W:340 I:12k` in each level's color, with `only` in reverse
+    /// video; the colors end without resetting the status bar's background.
+    pub fn widget(&self, only: Option<Level>) -> String {
+        let counts: Vec<String> = self
+            .seen()
+            .map(|level| {
+                let count = format!(
+                    "\x1B[{}m{}:{}\x1B[39;22m",
+                    level.color(),
+                    level.initial(),
+                    compact(self.counts[level as usize])
+                );
+                match only == Some(level) {
+                    true => format!("\x1B[7m{}\x1B[27m", count),
+                    false => count,
+                }
+            })
+            .collect();
+        match counts.is_empty() {
+            true => "no levels yet".to_string(),
+            false => counts.join(" "),
+        }
+    }
+}
+
+/// `count` in at most four characters, such as `950`, `1.2k`, `12k` or `3.4M`.
+fn compact(count: u64) -> String {
+    let scaled = |unit: u64, suffix: &str| match count < unit * 10 {
+        true => format!("{:.1}{}", (count / (unit / 10)) as f64 / 10.0, suffix),
+        false => format!("{}{}", count / unit, suffix),
+    };
+    match count {
+        0..1_000 => count.to_string(),
+        1_000..1_000_000 => scaled(1_000, "k"),
+        _ => scaled(1_000_000, "M"),
+    }
+}
+
+/// The level of `line`, from a syslog priority, a JSON `level`/`severity` field or the
+/// first level-like word.
+pub fn detect(line: &str) -> Option<Level> {
+    if let Some(message) = crate::syslog::parse(line) {
+        return Some(match message.severity {
+            0..=2 => Level::Fatal,
+            3 => Level::Error,
+            4 => Level::Warn,
+            5 | 6 => Level::Info,
+            _ => Level::Debug,
+        });
+    }
+    if line.trim_start().starts_with('{') {
+        if let Ok(value) = serde_json::from_str(line) {
+            let serde_json::Value::Object(map) = crate::profile::normalize(value) else {
+                return None;
+            };
+            let level = ["level", "severity", "lvl", "log.level"]
+                .iter()
+                .find_map(|key| map.get(*key)?.as_str().and_then(Level::parse));
+            if level.is_some() {
+                return level;
+            }
+        }
+    }
+    WORD.find(line).and_then(|word| Level::parse(word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_level() {
+        assert_eq!(detect("2024-01-01 WARN disk low"), Some(Level::Warn));
+        assert_eq!(
+            detect("[error] failed, retrying at info"),
+            Some(Level::Error)
+        );
+        assert_eq!(detect("ts=1 level=debug msg=x"), Some(Level::Debug));
+        assert_eq!(
+            detect(r#"{"msg":"an error happened","level":"info"}"#),
+            Some(Level::Info)
+        );
+        assert_eq!(detect("<11>su: failed"), Some(Level::Error));
+        assert_eq!(
+            detect(r#"{"version":"1.1","short_message":"x","level":4}"#),
+            Some(Level::Warn)
+        );
+        assert_eq!(detect("information is not a level"), None);
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut counts = Counts::default();
+        assert_eq!(counts.next(None), None);
+        for _ in 0..12_345 {
+            counts.record(Level::Info);
+        }
+        counts.record(Level::Error);
+        assert_eq!(
+            counts.widget(None),
+            "\x1B[31mE:1\x1B[39;22m \x1B[32mI:12k\x1B[39;22m"
+        );
+        assert_eq!(counts.next(None), Some(Level::Error));
+        assert_eq!(counts.next(Some(Level::Error)), Some(Level::Info));
+        assert_eq!(counts.next(Some(Level::Info)), None);
+        assert_eq!(compact(999), "999");
+        assert_eq!(compact(1_250), "1.2k");
+        assert_eq!(compact(3_400_000), "3.4M");
+    }
+
+    #[test]
+    fn test_raise_and_lower() {
+        assert_eq!(Level::Info.raise(), Level::Warn);
+        assert_eq!(Level::Fatal.raise(), Level::Fatal);
+        assert_eq!(Level::Trace.lower(), Level::Trace);
+        assert!(Level::Warn > Level::Info);
+    }
+}
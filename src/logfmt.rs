@@ -0,0 +1,146 @@
+//! logfmt records (`level=info msg="hello world" took=12ms`) shown as aligned columns.
+
+use crate::width;
+
+/// Colors cycled through for the selected keys.
+const PALETTE: [&str; 6] = ["36", "33", "32", "35", "34", "31"];
+
+/// Split a logfmt line into key/value pairs, or `None` if it has no `key=value` in it.
+///
+/// Values may be double-quoted with backslash escapes; a bare key is taken as `true`.
+pub fn parse(line: &str) -> Option<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    let mut chars = line.chars().peekable();
+    let mut any_value = false;
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            key.push(c);
+        }
+        if key.is_empty() {
+            if chars.peek().is_none() {
+                break;
+            }
+            return None;
+        }
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            any_value = true;
+            if chars.next_if_eq(&'"').is_some() {
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        '"' if !escaped => break,
+                        '\\' if !escaped => escaped = true,
+                        _ => {
+                            value.push(match (escaped, c) {
+                                (true, 'n') => '\n',
+                                (true, 't') => '\t',
+                                _ => c,
+                            });
+                            escaped = false;
+                        }
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+        } else {
+            value.push_str("true");
+        }
+        pairs.push((key, value));
+    }
+    any_value.then_some(pairs)
+}
+
+/// The selected keys and how wide their columns have grown.
+#[derive(Debug, Clone)]
+pub struct Columns {
+    keys: Vec<String>,
+    widths: Vec<usize>,
+}
+
+impl Columns {
+    pub fn new(keys: Vec<String>) -> Self {
+        let widths = keys.iter().map(|_| 0).collect();
+        Columns { keys, widths }
+    }
+
+    /// The selected keys in aligned columns followed by any other pairs, as pieces of text
+    /// with the SGR parameters to color them with. `None` if no selected key is present.
+    pub fn render(
+        &mut self,
+        pairs: &[(String, String)],
+    ) -> Option<Vec<(String, Option<&'static str>)>> {
+        let value = |key: &str| pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+        if !self.keys.iter().any(|key| value(key).is_some()) {
+            return None;
+        }
+        let mut tokens = Vec::new();
+        for (i, key) in self.keys.iter().enumerate() {
+            let text = value(key).map(String::as_str).unwrap_or("-");
+            let length = width::width(text);
+            self.widths[i] = self.widths[i].max(length.min(width::MAX_COLUMN));
+            tokens.push((text.to_string(), Some(PALETTE[i % PALETTE.len()])));
+            tokens.push((" ".repeat(self.widths[i].saturating_sub(length) + 1), None));
+        }
+        for (key, value) in pairs {
+            if self.keys.contains(key) {
+                continue;
+            }
+            let quoted = if value.is_empty() || value.contains([' ', '"', '=']) {
+                format!("{:?}", value)
+            } else {
+                value.clone()
+            };
+            tokens.push((format!("{}={} ", key, quoted), None));
+        }
+        if let Some((last, None)) = tokens.last_mut() {
+            last.truncate(last.trim_end().len());
+        }
+        Some(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_logfmt() {
+        assert_eq!(
+            parse(r#"level=info msg="hello \"you\"" debug took=5ms"#).unwrap(),
+            pairs(&[
+                ("level", "info"),
+                ("msg", "hello \"you\""),
+                ("debug", "true"),
+                ("took", "5ms")
+            ])
+        );
+        assert_eq!(parse("just some words"), None);
+        assert_eq!(parse("=oops"), None);
+    }
+
+    #[test]
+    fn test_render_columns() {
+        let mut columns = Columns::new(vec!["level".to_string(), "msg".to_string()]);
+        let text = |tokens: Vec<(String, Option<&str>)>| -> String {
+            tokens.into_iter().map(|(t, _)| t).collect()
+        };
+        let first = columns.render(&pairs(&[("level", "error"), ("msg", "boom"), ("id", "7")]));
+        assert_eq!(text(first.unwrap()), "error boom id=7");
+        let second = columns.render(&pairs(&[("level", "info"), ("user", "a b")]));
+        assert_eq!(text(second.unwrap()), "info  -    user=\"a b\"");
+        assert!(columns.render(&pairs(&[("other", "x")])).is_none());
+    }
+}
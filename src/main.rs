@@ -1,46 +1,240 @@
-use nix::sys::signal::{signal, SigHandler, Signal};
-use nix::sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg};
-use nix::unistd::isatty;
-use std::fs::OpenOptions;
-use std::io::Read;
-use std::io::{self, BufRead, Write};
-use std::os::unix::io::AsRawFd;
+mod access;
+mod asciicast;
+mod backlog;
+mod captures;
+mod checksum;
+mod cli;
+mod clipboard;
+mod commands;
+mod config;
+mod container;
+mod csv;
+mod cursor;
+mod cut;
+mod diff;
+mod distinct;
+mod editor;
+mod expr;
+mod field;
+mod fold;
+mod fresh;
+mod histogram;
+mod history;
+mod hyperlink;
+mod input;
+mod json;
+mod lane;
+mod level;
+mod logfmt;
+mod matcher;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod mirror;
+mod patterns;
+mod profile;
+mod progress;
+mod ratelimit;
+mod record;
+mod recording;
+mod records;
+mod redact;
+mod scrollback;
+mod session;
+mod source;
+mod span;
+mod stats;
+mod substitute;
+mod syslog;
+mod terminal;
+mod terminfo;
+mod theme;
+mod timestamp;
+mod topk;
+mod undo;
+mod watch;
+mod width;
+mod words;
+
+use asciicast::Tee;
+use config::{Config, EditingStyle, Preset, Toggle};
+use editor::LineEditor;
+use histogram::Resolution;
+use jiff::Timestamp;
+use matcher::{MatchOptions, Matcher};
+use patterns::Patterns;
+use progress::{Meter, Progress};
+use redact::Redactor;
+use scrollback::{Browse, Scrollback};
+use stats::Stats;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+use substitute::Substitution;
+use terminfo::Capabilities;
+use watch::Watch;
+
+/// Number of recent lines kept for browsing and saving.
+const SCROLLBACK_LINES: usize = 100_000;
+
+/// Where --snapshot-idle writes the scrollback, in the current directory.
+const SNAPSHOT_FILE: &str = "pipe_tools-snapshot.log";
+
+/// Normal mode commands of two keys, and how long to wait for the second one.
+const CHORDS: [&[u8; 2]; 4] = [b"gg", b"zt", b"zz", b"zb"];
+const CHORD_TIMEOUT: Duration = Duration::from_secs(1);
+/// Largest count typed before a normal mode command; further digits leave it there.
+const MAX_COUNT: usize = 999_999_999;
 
 struct StatusArea {
     status_lines: Vec<String>,
+    /// A pinned line shown above the status lines, such as a CSV header.
+    header: Option<String>,
+    term_out: Tee<File>,
+    caps: Capabilities,
+    /// SGR parameters for the status area background.
+    style: String,
+    /// Rate limit redraws and only send lines that changed, for slow links.
+    low_bandwidth: bool,
+    /// The lines as last drawn on the terminal, in low-bandwidth mode.
+    drawn_lines: Vec<String>,
+    last_redraw: Instant,
 }
 
 impl StatusArea {
-    fn new() -> Self {
+    /// Minimum time between redraws in low-bandwidth mode.
+    const LOW_BANDWIDTH_INTERVAL: Duration = Duration::from_secs(1);
+
+    fn new(term_out: Tee<File>, style: &str, caps: Capabilities, low_bandwidth: bool) -> Self {
         StatusArea {
             status_lines: vec![String::new(); 3],
+            header: None,
+            // Use the terminal for status updates instead of stdout
+            term_out,
+            caps,
+            style: style.to_string(),
+            low_bandwidth,
+            drawn_lines: vec![String::new(); 3],
+            last_redraw: Instant::now() - Self::LOW_BANDWIDTH_INTERVAL,
+        }
+    }
+
+    /// Rows taken up at the bottom of the terminal.
+    fn height(&self) -> u16 {
+        3 + self.header.is_some() as u16
+    }
+
+    /// Pin `header` above the status lines, shrinking the scroll region to make room.
+    fn set_header(&mut self, header: String) {
+        if self.header.as_ref() == Some(&header) {
+            return;
+        }
+        if self.header.is_none() {
+            // Scroll the output up a row so the header does not cover the last line,
+            // and move the cursor along with it.
+            let (_, rows) = terminal::size().unwrap();
+            let caps = &self.caps;
+            write!(
+                self.term_out,
+                "{}\x1B[{};1H\n{}\x1B[A",
+                caps.save_cursor,
+                rows - 3,
+                caps.restore_cursor
+            )
+            .unwrap();
+        }
+        self.header = Some(header);
+        self.redraw();
+    }
+
+    /// Unpin the header, giving its row back to the output.
+    fn clear_header(&mut self) {
+        if self.header.take().is_some() {
+            let (_, rows) = terminal::size().unwrap();
+            let caps = &self.caps;
+            write!(
+                self.term_out,
+                "{}\x1B[{};1H\x1B[2K{}",
+                caps.save_cursor,
+                rows - 3,
+                caps.restore_cursor
+            )
+            .unwrap();
+            self.redraw();
         }
     }
 
     fn update(&mut self, line: usize, text: &str) {
         if line < 3 {
             self.status_lines[line] = text.to_string();
-            self.redraw();
+            if self.low_bandwidth {
+                self.flush();
+            } else {
+                self.redraw();
+            }
+        }
+    }
+
+    /// Draw changed lines if the low-bandwidth rate limit allows it.
+    ///
+    /// Called periodically so that the last update in a burst is not lost.
+    fn flush(&mut self) {
+        if self.status_lines == self.drawn_lines
+            || self.last_redraw.elapsed() < Self::LOW_BANDWIDTH_INTERVAL
+        {
+            return;
+        }
+        let (columns, rows) = terminal::size().unwrap();
+        let term_out = &mut self.term_out;
+
+        write!(term_out, "{}", self.caps.save_cursor).unwrap();
+        for (i, line) in self.status_lines.iter().enumerate() {
+            if *line != self.drawn_lines[i] {
+                // Position, color, text and erase-to-end instead of clearing the whole area
+                let row = rows as usize - 2 + i;
+                write!(
+                    term_out,
+                    "\x1B[{};1H\x1B[{}m{}\x1B[K",
+                    row,
+                    self.style,
+                    width::truncate(line, columns as usize)
+                )
+                .unwrap();
+            }
         }
+        write!(term_out, "\x1B[m{}", self.caps.restore_cursor).unwrap();
+        term_out.flush().unwrap();
+
+        self.drawn_lines.clone_from(&self.status_lines);
+        self.last_redraw = Instant::now();
     }
 
-    fn redraw(&self) {
-        // Use /dev/tty for status updates instead of stdout
-        let mut term_out = OpenOptions::new()
-            .write(true)
-            .open("/dev/tty")
-            .expect("Could not open /dev/tty for writing");
+    fn redraw(&mut self) {
+        let height = self.height();
+        let term_out = &mut self.term_out;
 
-        let (_, rows) = get_terminal_size().unwrap();
+        let (columns, rows) = terminal::size().unwrap();
 
         // save the current cursor position
-        write!(term_out, "\x1B[s").unwrap();
+        write!(term_out, "{}", self.caps.save_cursor).unwrap();
+
+        // The header keeps the terminal's own colors, like the lines it labels
+        if let Some(header) = &self.header {
+            write!(
+                term_out,
+                "\x1B[{};1H\x1B[2K\x1B[1m{}\x1B[0m",
+                rows - 3,
+                header
+            )
+            .unwrap();
+        }
 
         // Move cursor to the beginning of the status area
         write!(term_out, "\x1B[{};1H", rows - 2).unwrap();
-        write!(term_out, "\x1b[44m").unwrap();
+        write!(term_out, "\x1b[{}m", self.style).unwrap();
 
         // Clear the status area
         for _ in 0..3 {
@@ -51,231 +245,3280 @@ impl StatusArea {
         // Move cursor back to the beginning of the status area
         write!(term_out, "\x1B[{};1H", rows - 2).unwrap();
 
-        // Print the status lines
+        // Print the status lines, cut to fit so they never wrap
         for line in &self.status_lines {
-            writeln!(term_out, "{}", line).unwrap();
+            writeln!(term_out, "{}", width::truncate(line, columns as usize)).unwrap();
         }
 
         // Reset scroll region
-        set_scroll_region_on_term(&mut term_out, 0, rows - 4).unwrap();
+        if self.caps.scroll_region {
+            terminal::set_scroll_region(term_out, 0, rows - 1 - height).unwrap();
+        }
 
         // restore the cursor position
-        write!(term_out, "\x1B[u").unwrap();
+        write!(term_out, "{}", self.caps.restore_cursor).unwrap();
         write!(term_out, "\x1b[0m").unwrap();
+
+        self.drawn_lines.clone_from(&self.status_lines);
+        self.last_redraw = Instant::now();
     }
 }
 
-// Add this helper function for setting scroll region on /dev/tty
-fn set_scroll_region_on_term<W: Write>(term: &mut W, top: u16, bottom: u16) -> io::Result<()> {
-    write!(term, "\x1B[{};{}r", top + 1, bottom + 1)?;
-    term.flush()?;
-    Ok(())
+/// Highlight the filter and every configured pattern switched on in `line`.
+fn highlight_line(line: &str, filter: &Matcher, config: &Config, patterns: &Patterns) -> String {
+    let style = config.highlight_for(filter.filter());
+    filter.highlight(line, style, patterns.ranges(line))
 }
 
-fn set_scroll_region(top: u16, bottom: u16) -> io::Result<()> {
-    let mut stdout = io::stdout();
-    write!(stdout, "\x1B[{};{}r", top + 1, bottom + 1)?;
-    stdout.flush()?;
-    Ok(())
+/// Join colored pieces of a reformatted line, highlighting matches within each piece.
+///
+/// With `only`, the filter is highlighted in that piece alone, as for a column filter.
+fn render_tokens(
+    tokens: Vec<(String, Option<&str>)>,
+    filter: &Matcher,
+    config: &Config,
+    patterns: &Patterns,
+    only: Option<usize>,
+) -> String {
+    let nothing = Matcher::new("", MatchOptions::default());
+    let mut rendered = String::new();
+    for (index, (token, color)) in tokens.into_iter().enumerate() {
+        let filter = if only.is_none_or(|only| only == index) {
+            filter
+        } else {
+            &nothing
+        };
+        let highlighted = highlight_line(&token, filter, config, patterns);
+        match color {
+            Some(color) => {
+                // Highlights end with a reset, after which the token color resumes.
+                let resumed = format!("\x1B[0m\x1B[{}m", color);
+                rendered.push_str(&format!(
+                    "\x1B[{}m{}\x1B[0m",
+                    color,
+                    highlighted.replace("\x1B[0m", &resumed)
+                ));
+            }
+            None => rendered.push_str(&highlighted),
+        }
+    }
+    rendered
+}
+
+/// The scrollback from the top of the screen in `browse`, `height` lines of it, with the
+/// selected line in reverse video.
+fn scrollback_panel(
+    scrollback: &Scrollback,
+    browse: &mut Browse,
+    height: usize,
+    filter: &Matcher,
+    config: &Config,
+    patterns: &Patterns,
+) -> Vec<String> {
+    browse.scroll(scrollback, height);
+    (browse.top..scrollback.end())
+        .take(height)
+        .filter_map(|number| {
+            let line = highlight_line(scrollback.get(number)?, filter, config, patterns);
+            Some(match number == browse.line {
+                true => format!("\x1B[7m{}\x1B[0m", line.replace("\x1B[0m", "\x1B[0;7m")),
+                false => line,
+            })
+        })
+        .collect()
+}
+
+/// The most recent lines `proposed` would change, with the change marked in each.
+fn replace_panel(
+    scrollback: &Scrollback,
+    proposed: Option<&Substitution>,
+    height: usize,
+) -> Vec<String> {
+    let Some(proposed) = proposed else {
+        return vec!["\x1B[2mType pattern/replacement to preview it here\x1B[0m".to_string()];
+    };
+    let mut panel: Vec<String> = (scrollback.start()..scrollback.end())
+        .rev()
+        .filter_map(|number| proposed.preview(scrollback.get(number)?))
+        .take(height)
+        .collect();
+    if panel.is_empty() {
+        panel.push("\x1B[2mNo recent line would change\x1B[0m".to_string());
+    }
+    panel.reverse();
+    panel
+}
+
+/// The latest output of a watched command, with what changed since the run before in
+/// reverse video.
+fn watch_panel(
+    watch: &Watch,
+    filter: &Matcher,
+    hide: bool,
+    config: &Config,
+    patterns: &Patterns,
+) -> Vec<String> {
+    (0..watch.lines.len())
+        .filter(|&index| !hide || filter.is_match(&watch.lines[index]))
+        .map(|index| {
+            let pieces = watch
+                .pieces(index)
+                .into_iter()
+                .map(|(text, changed)| (text, changed.then_some("7")))
+                .collect();
+            render_tokens(pieces, filter, config, patterns, None)
+        })
+        .collect()
+}
+
+/// The filter and then each of `terms` compiled on its own with `options`, for counting
+/// their matches apart.
+fn counters(filter: &str, terms: &[(String, String)], options: MatchOptions) -> Vec<Matcher> {
+    let term_options = MatchOptions {
+        invert: false,
+        ..options
+    };
+    let mut counters = vec![Matcher::new(filter, options)];
+    counters.extend(
+        terms
+            .iter()
+            .map(|(term, _)| Matcher::new(term, term_options)),
+    );
+    counters
+}
+
+/// Each of `counters` and each configured pattern with whether `line` has it, for the
+/// counts kept in `Stats`. The configured patterns are only ever highlighted as they are
+/// written, so they are counted that way too.
+fn counted<'a>(
+    line: &str,
+    counters: &'a [Matcher],
+    configured: &'a [(String, String)],
+) -> Vec<(&'a str, bool)> {
+    let mut counted: Vec<_> = counters
+        .iter()
+        .map(|counter| (counter.filter(), counter.is_match(line)))
+        .collect();
+    counted.extend(
+        configured
+            .iter()
+            .map(|(word, _)| (word.as_str(), line.contains(word.as_str()))),
+    );
+    counted
+}
+
+/// The count typed so far in normal mode after `digit`, a key from `0` to `9`.
+fn typed_count(count: Option<usize>, digit: u8) -> usize {
+    count
+        .unwrap_or(0)
+        .saturating_mul(10)
+        .saturating_add((digit - b'0') as usize)
+        .min(MAX_COUNT)
+}
+
+/// Terminal title for --title: matches of the filter so far, or lines without one.
+fn window_title(filter: &str, stats: &Stats) -> String {
+    if filter.is_empty() {
+        return format!("pipe_tools: {} lines", progress::format_count(stats.lines));
+    }
+    let count = stats
+        .patterns
+        .iter()
+        .find(|pattern| pattern.pattern == filter)
+        .map_or(0, |pattern| pattern.count);
+    // Control characters would end the title sequence early.
+    let filter: String = filter.chars().filter(|c| !c.is_control()).collect();
+    format!(
+        "pipe_tools: {} {} matches",
+        progress::format_count(count),
+        filter
+    )
+}
+
+/// Line standing in for the lines --max-rate kept off the screen.
+fn dropped_separator(dropped: u64) -> String {
+    format!(
+        "\x1B[2m── {} lines dropped (--max-rate) ──\x1B[0m",
+        progress::format_count(dropped)
+    )
+}
+
+/// Draw `lines` over the output region (the `height` rows above the status area),
+/// cut to `columns` wide.
+fn draw_panel<W: Write>(
+    out: &mut W,
+    caps: &Capabilities,
+    lines: &[String],
+    columns: u16,
+    height: u16,
+) -> io::Result<()> {
+    write!(out, "{}", caps.save_cursor)?;
+    for row in 0..height as usize {
+        let line = lines.get(row).map(String::as_str).unwrap_or("");
+        let line = width::truncate(line, columns as usize);
+        write!(out, "\x1B[{};1H\x1B[2K{}", row + 1, line)?;
+    }
+    write!(out, "{}", caps.restore_cursor)?;
+    out.flush()
+}
+
+/// What the output region shows.
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    /// The lines themselves, as they arrive.
+    Lines,
+    /// The per-pattern table of the `stats` command.
+    Stats,
+    /// Matches per bucket of time.
+    Histogram(Resolution),
+    /// The most frequent lines.
+    TopK,
+    /// Earlier lines, browsed from normal mode.
+    Scrollback,
+    /// Recent lines a find-and-replace being typed would change.
+    Replace,
+    /// The --file and --exec inputs, to pause or mute.
+    Sources,
+    /// Kept terms and configured patterns, to switch on and off.
+    Patterns,
+    /// Distinct values of the filter's capture group.
+    Captures,
+    /// JSON and logfmt records as rows of chosen fields.
+    Table,
+    /// The latest output of the `watch` command.
+    Watch,
+}
+
+impl View {
+    /// The view shown after pressing the histogram key.
+    fn next_histogram(self) -> View {
+        match self {
+            View::Lines
+            | View::Stats
+            | View::TopK
+            | View::Scrollback
+            | View::Replace
+            | View::Sources
+            | View::Patterns
+            | View::Captures
+            | View::Table
+            | View::Watch => View::Histogram(Resolution::Second),
+            View::Histogram(Resolution::Second) => View::Histogram(Resolution::Minute),
+            View::Histogram(Resolution::Minute) => View::Lines,
+        }
+    }
+
+    /// Name stored in session files.
+    fn name(self) -> &'static str {
+        match self {
+            // Browsing is not resumed; the session starts on the live lines.
+            View::Lines
+            | View::Scrollback
+            | View::Replace
+            | View::Sources
+            | View::Patterns
+            | View::Captures
+            | View::Table
+            | View::Watch => "lines",
+            View::Stats => "stats",
+            View::Histogram(Resolution::Second) => "histogram",
+            View::Histogram(Resolution::Minute) => "histogram-minute",
+            View::TopK => "topk",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<View> {
+        match name {
+            "lines" => Some(View::Lines),
+            "stats" => Some(View::Stats),
+            "histogram" => Some(View::Histogram(Resolution::Second)),
+            "histogram-minute" => Some(View::Histogram(Resolution::Minute)),
+            "topk" => Some(View::TopK),
+            _ => None,
+        }
+    }
+}
+
+/// Switches flipped by the function keys.
+#[derive(Clone, Copy, PartialEq)]
+struct Toggles {
+    matching: MatchOptions,
+    /// Only lines matching the filter are shown.
+    hide: bool,
+    /// Lines wait until output resumes.
+    paused: bool,
+    /// New lines are shown; otherwise they are only counted and kept in the scrollback.
+    follow: bool,
+    /// Each line waits for a key press, which adds to `steps`.
+    step: bool,
+    steps: usize,
+    /// Bursts of a repeated line are folded into a summary.
+    fold: bool,
+    /// Lines seen earlier while this is on are dimmed.
+    dups: bool,
+}
+
+impl Toggles {
+    /// Switch on the toggles of `preset`'s mode and the other matching toggles off.
+    fn apply(&mut self, preset: &Preset) {
+        self.matching = MatchOptions::default();
+        self.hide = false;
+        for &toggle in &preset.mode {
+            self.set(toggle, true, &mut false);
+        }
+    }
+
+    /// Flip `toggle`, returning whether it is now on.
+    fn flip(&mut self, toggle: Toggle, pretty: &mut bool) -> bool {
+        let on = !*self.flag(toggle, pretty);
+        self.set(toggle, on, pretty);
+        on
+    }
+
+    /// Switch `toggle` on or off. Regex and fuzzy matching each switch the other off.
+    fn set(&mut self, toggle: Toggle, on: bool, pretty: &mut bool) {
+        match toggle {
+            Toggle::Regex if on => self.matching.fuzzy = false,
+            Toggle::Fuzzy if on => self.matching.regex = false,
+            _ => {}
+        }
+        *self.flag(toggle, pretty) = on;
+    }
+
+    fn flag<'a>(&'a mut self, toggle: Toggle, pretty: &'a mut bool) -> &'a mut bool {
+        match toggle {
+            Toggle::Regex => &mut self.matching.regex,
+            Toggle::IgnoreCase => &mut self.matching.ignore_case,
+            Toggle::SmartCase => &mut self.matching.smart_case,
+            Toggle::WholeWord => &mut self.matching.whole_word,
+            Toggle::Fuzzy => &mut self.matching.fuzzy,
+            Toggle::Invert => &mut self.matching.invert,
+            Toggle::Hide => &mut self.hide,
+            Toggle::Pause => &mut self.paused,
+            Toggle::Follow => &mut self.follow,
+            Toggle::Pretty => pretty,
+            Toggle::Step => &mut self.step,
+            Toggle::Fold => &mut self.fold,
+            Toggle::Dups => &mut self.dups,
+        }
+    }
+}
+
+impl Default for Toggles {
+    fn default() -> Self {
+        Toggles {
+            matching: MatchOptions::default(),
+            hide: false,
+            paused: false,
+            follow: true,
+            step: false,
+            steps: 0,
+            fold: true,
+            dups: false,
+        }
+    }
+}
+
+/// Carry out a command changing the filter, a toggle or the level, from `--cmd` or the `:`
+/// prompt; the others are handled where they are typed.
+fn run_command(
+    command: &commands::Command,
+    filter: &mut String,
+    toggles: &mut Toggles,
+    pretty: &mut bool,
+    min_level: &mut Option<level::Level>,
+) {
+    match command {
+        commands::Command::Filter(text) => text.clone_into(filter),
+        commands::Command::Mode(toggle, on) => toggles.set(*toggle, *on, pretty),
+        commands::Command::Level(level) => *min_level = *level,
+        _ => {}
+    }
+}
+
+/// Keep `term` matched and highlighted beside the filter, unless it already is.
+fn add_term(patterns: &mut Patterns, term: &str) {
+    if !patterns.terms.iter().any(|(kept, _)| kept == term) {
+        let style = next_term_style(&patterns.terms);
+        patterns.terms.push((term.to_string(), style));
+    }
+}
+
+fn set_theme(config: &mut Config, theme: theme::Theme) {
+    config.theme = theme;
+    config.highlight = theme.highlight().to_string();
+}
+
+/// What undo goes back to: the filter and toggles, leaving out pausing and stepping,
+/// which come and go as output flows.
+type Setting = (String, Toggles);
+
+fn setting(filter: &str, toggles: Toggles) -> Setting {
+    let toggles = Toggles {
+        paused: false,
+        steps: 0,
+        ..toggles
+    };
+    (filter.to_string(), toggles)
+}
+
+/// Which status line typed keys are edited into.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    /// Keys edit the filter string.
+    Filter,
+    /// Keys build up a line of interactive input (when stdin is a TTY).
+    Input,
+    /// Keys are commands, such as changing the minimum log level.
+    Normal,
+    /// Keys build up a note for the most recent line.
+    Note,
+    /// Keys build up a command for the `:` prompt.
+    Command,
+    /// Keys build up a find-and-replace, previewed on recent lines.
+    Replace,
+    /// Keys build up a query searching earlier filters.
+    Search,
+}
+
+/// The first style no kept term has yet, or the first of all once every one is taken.
+fn next_term_style(terms: &[(String, String)]) -> String {
+    let style = matcher::TERM_STYLES
+        .iter()
+        .find(|style| !terms.iter().any(|(_, used)| used == *style))
+        .unwrap_or(&matcher::TERM_STYLES[terms.len() % matcher::TERM_STYLES.len()]);
+    style.to_string()
+}
+
+/// The kept terms, each in its style.
+fn terms_status(terms: &[(String, String)]) -> String {
+    match terms.is_empty() {
+        true => "No terms kept".to_string(),
+        false => {
+            let shown: Vec<String> = terms
+                .iter()
+                .map(|(term, style)| format!("\x1B[{}m{}\x1B[0m", style, term))
+                .collect();
+            format!("Terms: {}", shown.join(" "))
+        }
+    }
+}
+
+fn note_status(note: &str) -> String {
+    format!(">Note   [{}]  (Enter saves, Esc cancels)", note)
+}
+
+fn command_status(command: &str) -> String {
+    format!(
+        ">:{}  (Enter runs, Esc cancels; filter [add] TEXT, mode TOGGLE [off], level LEVEL, \
+         save [FILE], theme NAME, set max-rate N|off)",
+        command
+    )
+}
+
+fn replace_status(text: &str, error: Option<&str>) -> String {
+    let error = error.map(|e| format!("  - {}", e)).unwrap_or_default();
+    format!(
+        ">Replace [{}]  (pattern/replacement; Enter applies, Esc cancels){}",
+        text, error
+    )
+}
+
+fn search_status(query: &str, found: Option<&str>) -> String {
+    let found = found.unwrap_or("no match");
+    format!(
+        ">Search [{}]: {}  (Enter uses it, Esc cancels)",
+        query, found
+    )
+}
+
+/// The filter line while it is being edited, with the cursor shown in reverse video.
+fn editing_status(editor: &LineEditor, active: bool, config: &Config) -> String {
+    let (before, after) = editor.text().split_at(editor.cursor());
+    let mut rest = after.chars();
+    let under = rest.next().unwrap_or(' ');
+    let text = format!("{}\x1B[7m{}\x1B[27m{}", before, under, rest.as_str());
+    filter_status(&text, active, config)
+}
+
+/// Apply `edit` to the filter through `editor`, returning whether the text changed. The
+/// editor first picks up changes made elsewhere, such as by a replay.
+fn edit_filter(
+    editor: &mut LineEditor,
+    filter: &mut String,
+    edit: impl FnOnce(&mut LineEditor),
+) -> bool {
+    if editor.text() != filter.as_str() {
+        editor.set(filter);
+    }
+    edit(editor);
+    if editor.text() == filter.as_str() {
+        return false;
+    }
+    *filter = editor.text().to_string();
+    true
+}
+
+fn filter_status(filter: &str, active: bool, config: &Config) -> String {
+    let marker = if active { ">" } else { " " };
+    let preset = match config.preset_for(filter) {
+        Some((number, preset)) => format!("  preset {}: {}", number, preset.name),
+        None => String::new(),
+    };
+    format!(
+        "{}Filter [\x1B[{}m{}\x1b[0;{}m]{}",
+        marker,
+        config.highlight_for(filter),
+        filter,
+        config.status,
+        preset
+    )
+}
+
+fn input_status(input: &str, active: bool) -> String {
+    let marker = if active { ">" } else { " " };
+    format!("{}Input  [{}]  (Tab switches, Ctrl+D quits)", marker, input)
+}
+
+/// Print the `--checksum` digest to stderr.
+fn print_checksum(hash: &checksum::Sha256) {
+    eprintln!(
+        "sha256 {}  ({} bytes)",
+        hash.hex(),
+        progress::format_count(hash.length)
+    );
+}
+
+/// Write the `--stats-json` summary to `path`, or to stderr when it is `-`.
+fn write_stats_json(path: &std::path::Path, summary: &serde_json::Value) -> io::Result<()> {
+    let text = serde_json::to_string_pretty(summary)?;
+    if path.as_os_str() == "-" {
+        writeln!(io::stderr(), "{}", text)
+    } else {
+        std::fs::write(path, text + "\n")
+    }
+}
+
+/// Lines of `input`, each with the number of bytes it took including its terminator.
+/// Invalid UTF-8 is shown replaced rather than ending the input, so every byte is read.
+fn lines_with_size(mut input: impl BufRead) -> impl Iterator<Item = (String, usize)> {
+    std::iter::from_fn(move || {
+        let mut bytes = Vec::new();
+        match input.read_until(b'\n', &mut bytes) {
+            Ok(0) | Err(_) => None,
+            Ok(size) => {
+                let mut line = String::from_utf8_lossy(&bytes).into_owned();
+                let end = line.trim_end_matches('\n').trim_end_matches('\r').len();
+                line.truncate(end);
+                Some((line, size))
+            }
+        }
+    })
 }
 
-fn reset_scroll_region() -> io::Result<()> {
-    let mut stdout = io::stdout();
-    write!(stdout, "\x1B[0r")?;
-    stdout.flush()?;
+/// Print the lines only one of two recordings has, exiting with 1 if there are any.
+fn run_diff(left: &std::path::Path, right: &std::path::Path, ignore: &[String]) -> io::Result<()> {
+    let normalizer = match diff::Normalizer::new(ignore) {
+        Ok(normalizer) => normalizer,
+        Err(message) => {
+            eprintln!("--ignore: {}", message);
+            std::process::exit(2);
+        }
+    };
+    let read_lines = |path: &std::path::Path| -> Result<Vec<String>, String> {
+        let error = |e: io::Error| format!("{}: {}", path.display(), e);
+        let mut lines = Vec::new();
+        for event in recording::Reader::open(path).map_err(error)? {
+            if let recording::Event::Line(line) = event.map_err(error)?.event {
+                lines.push(line);
+            }
+        }
+        Ok(lines)
+    };
+    let (left_lines, right_lines) = match (read_lines(left), read_lines(right)) {
+        (Ok(left_lines), Ok(right_lines)) => (left_lines, right_lines),
+        (Err(message), _) | (_, Err(message)) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    let color = io::stdout().is_terminal();
+    let mut out = io::stdout().lock();
+    let lines = diff::unmatched(&left_lines, &right_lines, &normalizer);
+    for (side, line) in &lines {
+        let (marker, sgr) = match side {
+            diff::Side::Left => ('-', "31"),
+            diff::Side::Right => ('+', "32"),
+        };
+        if color {
+            writeln!(out, "\x1B[{}m{} {}\x1B[0m", sgr, marker, line)?;
+        } else {
+            writeln!(out, "{} {}", marker, line)?;
+        }
+    }
+    out.flush()?;
+    if !lines.is_empty() {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
-fn get_terminal_size() -> io::Result<(u16, u16)> {
-    let size = crossterm::terminal::size()?;
-    Ok(size)
+/// Copy stdin to stdout with only secrets masked, for dumb terminals or when there is no
+/// terminal at all.
+fn run_plain(
+    options: &cli::Options,
+    started: Instant,
+    redactor: Redactor,
+    cut: Option<cut::Cut>,
+) -> io::Result<()> {
+    if !options.sources.is_empty() {
+        eprintln!("--file and --exec need a terminal");
+        std::process::exit(2);
+    }
+    if options.checksum {
+        let hash = Arc::new(Mutex::new(checksum::Sha256::default()));
+        let mut input =
+            checksum::Passthrough::new(io::stdin().lock(), Some(io::stdout()), hash.clone());
+        io::copy(&mut input, &mut io::sink())?;
+        print_checksum(&hash.lock().unwrap());
+        return Ok(());
+    }
+    let (tx, rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        // Decoded lossily, as on a terminal, so a line that isn't UTF-8 doesn't end the input.
+        for (line, _) in lines_with_size(io::stdin().lock()) {
+            let line = redactor.redact(&line);
+            let line = match &cut {
+                Some(cut) => cut.apply(&line),
+                None => line.into_owned(),
+            };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    copy_plain(&rx, &mut io::stdout().lock(), options, started)
 }
 
-fn highlight_word_in_string(string: &str, word: &str) -> String {
-    match string.find(word) {
-        Some(_position) => {
-            let highlighted = format!("{}{}{}", "\x1B[37;101m", word, "\x1B[0m");
-            // Replace the word with the highlighted version in the original string.
-            string.replace(word, &highlighted)
+/// Write the lines from `rx` to `out` as --head, --tail and the timeouts allow.
+fn copy_plain(
+    rx: &mpsc::Receiver<String>,
+    out: &mut impl Write,
+    options: &cli::Options,
+    started: Instant,
+) -> io::Result<()> {
+    let mut last_input = Instant::now();
+    let (mut printed, mut tail) = (0, VecDeque::new());
+    loop {
+        // Checked before reading, so --head 0 shows nothing.
+        if options.head.is_some_and(|limit| printed >= limit) {
+            break;
+        }
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(line) => {
+                last_input = Instant::now();
+                if let Some(limit) = options.tail {
+                    if tail.len() == limit {
+                        tail.pop_front();
+                    }
+                    if limit > 0 {
+                        tail.push_back(line);
+                    }
+                } else if writeln!(out, "{}", line).is_err() {
+                    break;
+                } else {
+                    printed += 1;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => out.flush()?,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
         }
-        None => {
-            string.to_string() // Return the input string if no match is found.
+        if options
+            .timeout
+            .is_some_and(|limit| started.elapsed() >= limit)
+            || options
+                .idle_timeout
+                .is_some_and(|limit| last_input.elapsed() >= limit)
+        {
+            break;
         }
     }
+    for line in tail {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
-    // Ignore SIGPIPE so broken stdout does not panic.
-    let _ = unsafe { signal(Signal::SIGPIPE, SigHandler::SigIgn) };
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    let started = Instant::now();
+    if let cli::Command::Diff(left, right) = &options.command {
+        return run_diff(left, right, &options.ignore);
+    }
+    let startup_commands = match options
+        .commands
+        .iter()
+        .map(|command| commands::parse(command))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(commands) => commands,
+        Err(message) => {
+            eprintln!("--cmd: {}", message);
+            std::process::exit(2);
+        }
+    };
+    if startup_commands
+        .iter()
+        .any(|command| matches!(command, commands::Command::Save(_)))
+    {
+        eprintln!("--cmd: save only works at the : prompt");
+        std::process::exit(2);
+    }
+    if let cli::Command::Mirror(path) = &options.command {
+        if let Err(e) = mirror::watch(path) {
+            eprintln!("mirror: {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+        return Ok(());
+    }
+
+    let parse_spec = |flag: &str, spec: Option<&str>| match spec.map(field::FieldSpec::parse) {
+        Some(Ok(spec)) => Some(spec),
+        Some(Err(message)) => {
+            eprintln!("{}: {}", flag, message);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let field = parse_spec("--field", options.field.as_deref());
+    let distinct = parse_spec("--distinct", options.distinct.as_deref());
+    let spans = match options
+        .span_start
+        .as_deref()
+        .zip(options.span_end.as_deref())
+    {
+        Some((start, end)) => match span::SpanTracker::new(start, end) {
+            Ok(spans) => Some(spans),
+            Err(message) => {
+                eprintln!("--span-start/--span-end: {}", message);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    let joiner = match options.record_start.as_deref().map(record::Joiner::new) {
+        Some(Ok(joiner)) => Some(Arc::new(Mutex::new(joiner))),
+        Some(Err(message)) => {
+            eprintln!("--record-start: {}", message);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let priority = match options.priority.as_deref().map(regex::Regex::new) {
+        Some(Ok(regex)) => Some(regex),
+        Some(Err(e)) => {
+            eprintln!("--priority: {}", e);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let cut = match (&options.delimiter, &options.fields) {
+        (delimiter, Some(fields)) => {
+            match cut::Cut::new(delimiter.as_deref().unwrap_or("\t"), fields) {
+                Ok(cut) => Some(cut),
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(2);
+                }
+            }
+        }
+        (Some(_), None) => {
+            eprintln!("-d needs -f to choose the fields");
+            std::process::exit(2);
+        }
+        (None, None) => None,
+    };
+    let rewriter = match (&options.tz, &options.time_format) {
+        (None, None) => None,
+        (zone, format) => {
+            let zone = zone.as_deref().unwrap_or("local");
+            match timestamp::Rewriter::new(zone, format.as_deref()) {
+                Ok(rewriter) => Some(rewriter),
+                Err(message) => {
+                    eprintln!("--tz: {}", message);
+                    std::process::exit(2);
+                }
+            }
+        }
+    };
+    let normalizer = diff::Normalizer::new(&options.ignore).unwrap_or_else(|message| {
+        eprintln!("--ignore: {}", message);
+        std::process::exit(2);
+    });
+    let known = match &options.known {
+        Some(path) => match diff::Known::load(path, normalizer.clone()) {
+            Ok(known) => Some(known),
+            Err(message) => {
+                eprintln!("--known: {}", message);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let min_level = match options.level.as_deref() {
+        Some(name) => match level::Level::parse(name) {
+            Some(level) => Some(level),
+            None => {
+                eprintln!("--level: unknown level '{}'", name);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let session = match options.session.as_deref().map(session::Session::load) {
+        Some(Ok(session)) => session,
+        Some(Err(message)) => {
+            eprintln!("--session: {}", message);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let history = match history::default_path().filter(|_| options.save_history) {
+        Some(path) => match history::History::load(&path) {
+            Ok(history) => history,
+            Err(e) => {
+                eprintln!("--save-history: {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        },
+        None => history::History::default(),
+    };
+    // Levels given on the command line win over the saved one.
+    let min_level = min_level.or(session.as_ref().and_then(|session| session.level));
+    let min_level = Arc::new(Mutex::new(min_level));
+    // The one level shown, picked from the --level-counts widget.
+    let only_level: Arc<Mutex<Option<level::Level>>> = Arc::new(Mutex::new(None));
+    let syslog_filter =
+        match syslog::Filter::new(options.severity.as_deref(), options.facility.as_deref()) {
+            Ok(filter) => filter,
+            Err(message) => {
+                eprintln!("{}", message);
+                std::process::exit(2);
+            }
+        };
+    let syslog = options.syslog || options.severity.is_some() || options.facility.is_some();
+
+    // A recording played back in place of stdin.
+    let replay = match &options.command {
+        cli::Command::Replay(path) => match recording::Reader::open(path) {
+            Ok(reader) => Some(reader),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        },
+        _ => None,
+    };
+    let replaying = replay.is_some();
+    // A command rerun in place of reading stdin.
+    let watch = match &options.command {
+        cli::Command::Watch(command) => {
+            let watch = Arc::new(Mutex::new(Watch::default()));
+            let interval = options.interval.unwrap_or(watch::DEFAULT_INTERVAL);
+            watch::spawn(command.clone(), interval, watch.clone());
+            Some(watch)
+        }
+        _ => None,
+    };
+    let cursor = match options.cursor.as_deref().map(cursor::Cursor::load) {
+        Some(Ok(mut cursor)) => {
+            if let Err(e) = cursor.seek_stdin() {
+                eprintln!("--cursor needs stdin redirected from a file: {}", e);
+                std::process::exit(2);
+            }
+            Some(Arc::new(Mutex::new(cursor)))
+        }
+        Some(Err(message)) => {
+            eprintln!("--cursor: {}", message);
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let playback = Arc::new(Mutex::new(recording::Playback::new(options.speed)));
+
+    let theme = match options.theme.as_deref() {
+        Some(name) => match theme::Theme::from_name(name) {
+            Some(theme) => Some(theme),
+            None => {
+                eprintln!("--theme: unknown theme '{}'", name);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    let config_path = options.config.clone().or_else(config::default_path);
+    let config = match config_path.as_deref() {
+        Some(path) => Config::load(path, theme),
+        None => Config::parse("", theme),
+    };
+    let mut config = match config {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    if options.no_redact {
+        config.redact.clear();
+    }
+    let config = Arc::new(Mutex::new(config));
+
+    terminal::ignore_sigpipe();
+
+    let filter_string = Arc::new(Mutex::new(match &session {
+        Some(session) => session.filter.clone(),
+        None => "stream".to_string(),
+    }));
 
-    let filter_string = Arc::new(Mutex::new("stream".to_string()));
+    let caps = Capabilities::detect();
 
-    let (_, rows) = get_terminal_size()?;
+    // Terminal output bypassing stdout
+    let term_out = match terminal::open_output() {
+        Ok(term_out) if caps.scroll_region => term_out,
+        // Without a usable terminal there is nowhere to put the status area.
+        _ => {
+            return run_plain(
+                &options,
+                started,
+                Redactor::new(&config.lock().unwrap().redact),
+                cut,
+            )
+        }
+    };
+    let (columns, rows) = terminal::size()?;
+
+    // Everything drawn from here on is also saved by --cast.
+    let cast = match &options.cast {
+        Some(path) => Some(Arc::new(Mutex::new(asciicast::Cast::create(
+            path, columns, rows,
+        )?))),
+        None => None,
+    };
+    // And shown to any mirrors attached with --share.
+    let share = match &options.share {
+        Some(path) => Some(mirror::serve(path)?),
+        None => None,
+    };
+    let mut term_out = Tee::new(term_out, cast.clone()).mirrored(share.clone());
+
+    // Inside tmux/screen the pane's scroll region and saved cursor can be disturbed
+    // behind our back, so draw on the alternate screen and repaint the status area
+    // periodically instead of trusting it to stay put.
+    let multiplexer = terminal::multiplexer();
+    let alt_screen = caps
+        .alt_screen
+        .clone()
+        .filter(|_| options.alt_screen.unwrap_or(multiplexer.is_some()));
+    if let Some((enter, _)) = &alt_screen {
+        write!(term_out, "{}", enter)?;
+    }
+    let mut last_repaint = Instant::now();
 
     // save the current cursor position
-    print!("\x1B[s");
+    write!(term_out, "{}", caps.save_cursor)?;
     // clear the screen
-    print!("\x1B[2J");
+    write!(term_out, "\x1B[2J")?;
 
     // Set scroll region to exclude the status area
-    set_scroll_region(0, rows - 4)?;
+    if caps.scroll_region {
+        terminal::set_scroll_region(&mut term_out, 0, rows - 4)?;
+    }
+
+    let stdin_piped = !io::stdin().is_terminal();
+    let is_pipe = replaying || watch.is_some() || !options.sources.is_empty() || stdin_piped;
 
-    let mut status_bar = StatusArea::new();
+    // Without a pipe, lines typed at the terminal are fed through the same view.
+    let initial_mode = if is_pipe { Mode::Filter } else { Mode::Input };
+
+    let mut status_bar = StatusArea::new(
+        term_out.try_clone()?,
+        &config.lock().unwrap().status,
+        caps.clone(),
+        options.low_bandwidth,
+    );
     status_bar.update(0, "");
     status_bar.update(
         1,
-        format!(
-            "Filter [\x1B[37;101m{}\x1b[44m]",
-            filter_string.lock().unwrap()
-        )
-        .as_str(),
+        &filter_status(
+            &filter_string.lock().unwrap(),
+            false,
+            &config.lock().unwrap(),
+        ),
     );
+    if !is_pipe {
+        status_bar.update(0, &input_status("", true));
+    }
     status_bar.update(2, "");
+    if let Some(cursor) = cursor.as_deref() {
+        let offset = cursor.lock().unwrap().offset();
+        if offset > 0 {
+            status_bar.update(2, &format!("Resumed at byte {}", offset));
+        }
+    }
     status_bar.redraw();
 
-    print!("\x1B[u"); // restore cursor position
+    write!(term_out, "{}", caps.restore_cursor)?; // restore cursor position
+    term_out.flush()?;
 
-    let stdin = io::stdin();
-    let is_pipe = !isatty(stdin.as_raw_fd()).unwrap_or(false);
+    // Shared with the key listener and the config watcher
+    let status_bar = Arc::new(Mutex::new(status_bar));
 
-    // Replace the atomic flag with a quit channel.
-    let (quit_tx, quit_rx) = mpsc::channel::<()>();
+    // Terms matched and highlighted beside the filter, each with its own style: the
+    // session's, then any more given now.
+    let mut initial_terms = session
+        .as_ref()
+        .map_or(Vec::new(), |session| session.terms.clone());
+    for term in &options.terms {
+        if initial_terms.iter().all(|(kept, _)| kept != term) {
+            let style = next_term_style(&initial_terms);
+            initial_terms.push((term.clone(), style));
+        }
+    }
+    let patterns = {
+        let configured = &config.lock().unwrap().patterns;
+        let mut patterns = Patterns::new(initial_terms, configured);
+        if let Some(session) = &session {
+            patterns.switch_off(&session.off, configured);
+        }
+        Arc::new(Mutex::new(patterns))
+    };
 
-    // Terminal output to /dev/tty
-    let mut term_out = OpenOptions::new()
-        .write(true)
-        .open("/dev/tty")
-        .expect("Could not open /dev/tty for writing");
+    // Reload the config whenever it changes and apply it to the running session.
+    if let Some(path) = config_path {
+        let config = config.clone();
+        let status_bar = status_bar.clone();
+        let filter_string = filter_string.clone();
+        let patterns = patterns.clone();
+        let no_redact = options.no_redact;
+        config::watch(path.clone(), move || {
+            let mut status = status_bar.lock().unwrap();
+            match Config::load(&path, theme) {
+                Ok(new_config) => {
+                    let mut config = config.lock().unwrap();
+                    *config = new_config;
+                    if no_redact {
+                        config.redact.clear();
+                    }
+                    status.style = config.status.clone();
+                    patterns.lock().unwrap().configure(&config.patterns);
+                    let filter = filter_string.lock().unwrap();
+                    status.status_lines[1] = filter_status(&filter, false, &config);
+                    status.update(2, "Config reloaded");
+                }
+                Err(message) => status.update(2, &format!("Config error: {}", message)),
+            }
+        });
+    }
 
-    let _ = writeln!(term_out, "rows {} ", rows);
+    // Replace the atomic flag with a quit channel.
+    let (quit_tx, quit_rx) = mpsc::channel::<()>();
 
-    // Channel for pipe lines if pipe is attached.
-    let (tx_pipe, rx_pipe) = mpsc::channel::<String>();
+    // Lines errors and --priority lines go ahead of, and --max-rate never drops.
+    let is_priority = Arc::new(move |line: &str| {
+        level::detect(line).is_some_and(|level| level >= level::Level::Error)
+            || priority.as_ref().is_some_and(|regex| regex.is_match(line))
+    });
+    // Channel for input lines, fed by the pipe or by interactive input. `None` marks the
+    // end of the input.
+    let (tx_pipe, rx_pipe) = {
+        let is_priority = is_priority.clone();
+        lane::channel(move |line| is_priority(line))
+    };
+    // Lines sent down it and not yet taken, capped by --buffer.
+    let backlog = Arc::new(Mutex::new(backlog::Backlog::new(options.buffer)));
 
     // Before creating pipe threads, clone it for pipe printer
     let filter_for_pipe = filter_string.clone();
 
-    // Spawn pipe reader thread if input is piped.
-    if is_pipe {
-        let tx_pipe = tx_pipe.clone();
-        let quit_tx_pipe = quit_tx.clone();
-        thread::spawn(move || {
-            for line in io::stdin().lock().lines() {
-                if let Ok(line) = line {
-                    // Send line; ignore send errors on quit.
-                    let _ = tx_pipe.send(line);
-                }
+    // Input lines and filter changes saved by --record.
+    let recorder = match &options.record {
+        Some(path) => {
+            let mut recorder = recording::Recorder::create(path, started)?;
+            let filter = filter_string.lock().unwrap().clone();
+            recorder.record(
+                &recording::Event::Filter(filter),
+                None,
+                started,
+                Timestamp::now(),
+            )?;
+            Some(Arc::new(Mutex::new(recorder)))
+        }
+        None => None,
+    };
+    let record = {
+        let recorder = recorder.clone();
+        // Source 0 is stdin and N the Nth --file or --exec.
+        move |event: recording::Event, source: Option<usize>| {
+            if let Some(recorder) = &recorder {
+                let _ = recorder.lock().unwrap().record(
+                    &event,
+                    source,
+                    Instant::now(),
+                    Timestamp::now(),
+                );
             }
-            // When the pipe ends send quit signal.
-            let _ = quit_tx_pipe.send(());
-        });
+        }
+    };
+
+    // Time of the most recent input line, used by --idle-timeout.
+    let last_input = Arc::new(Mutex::new(Instant::now()));
+
+    let mut initial_stats = Stats::new(started);
+    initial_stats.spans = spans;
+    let stats = Arc::new(Mutex::new(initial_stats));
+    let stats_mode = options.command == cli::Command::Stats;
+    let saved_view = session
+        .as_ref()
+        .and_then(|session| View::from_name(&session.view));
+    let view = Arc::new(Mutex::new(match saved_view {
+        _ if watch.is_some() => View::Watch,
+        Some(view) => view,
+        None if stats_mode => View::Stats,
+        None => View::Lines,
+    }));
 
-        // Modified pipe printer thread with access to shared filter string
+    // Whether JSON lines are pretty-printed.
+    let pretty = Arc::new(Mutex::new(
+        options.pretty_json || session.as_ref().is_some_and(|session| session.pretty),
+    ));
+
+    let mut initial_toggles = Toggles {
+        matching: session
+            .as_ref()
+            .map_or(MatchOptions::default(), |session| session.matching),
+        hide: options.only_matching_lines || session.as_ref().is_some_and(|session| session.hide),
+        step: options.step,
+        fold: !options.no_fold,
+        ..Toggles::default()
+    };
+    // Options given now go on top of the session's.
+    for (toggle, on) in [
+        (Toggle::IgnoreCase, options.ignore_case),
+        (Toggle::SmartCase, options.smart_case),
+        (Toggle::WholeWord, options.whole_word),
+        (Toggle::Fuzzy, options.fuzzy),
+    ] {
+        if on {
+            initial_toggles.set(toggle, true, &mut false);
+        }
+    }
+    let toggles = Arc::new(Mutex::new(initial_toggles));
+
+    // Changed with `set max-rate` at the : prompt.
+    let max_rate = Arc::new(Mutex::new(options.max_rate));
+
+    // --cmd commands, in order, over whatever the options and session set up.
+    if !startup_commands.is_empty() {
+        let before = filter_string.lock().unwrap().clone();
+        let mut filter = before.clone();
+        for command in &startup_commands {
+            match command {
+                commands::Command::AddTerm(term) => add_term(&mut patterns.lock().unwrap(), term),
+                commands::Command::Theme(theme) => set_theme(&mut config.lock().unwrap(), *theme),
+                commands::Command::MaxRate(max) => *max_rate.lock().unwrap() = *max,
+                _ => {}
+            }
+            run_command(
+                command,
+                &mut filter,
+                &mut toggles.lock().unwrap(),
+                &mut pretty.lock().unwrap(),
+                &mut min_level.lock().unwrap(),
+            );
+        }
+        if filter != before {
+            record(recording::Event::Filter(filter.clone()), None);
+            let mut status = status_bar.lock().unwrap();
+            status.update(1, &filter_status(&filter, false, &config.lock().unwrap()));
+            *filter_string.lock().unwrap() = filter;
+        }
+    }
+
+    // Scrollback number of the line the latest folded burst repeats.
+    let last_burst = Arc::new(Mutex::new(None::<usize>));
+
+    // With `commit = enter`, the filter still hiding lines while an edit is typed.
+    let committed = Arc::new(Mutex::new(None::<String>));
+
+    // The --file and --exec inputs, each paused or muted from the sources overlay.
+    let sources = Arc::new(Mutex::new(source::Sources::new(
+        options.sources.iter().map(source::label).collect(),
+        &config.lock().unwrap().sources,
+    )));
+
+    // Find-and-replace applied to the lines shown, and the one being typed, for its preview.
+    let substitution = Arc::new(Mutex::new(None::<Substitution>));
+    let proposed = Arc::new(Mutex::new(None::<Substitution>));
+
+    // Lines written while --fade is on, repainted as they fade.
+    let fresh = Arc::new(Mutex::new(fresh::Fresh::default()));
+
+    // Most recent line containing the filter, for copying to the clipboard.
+    let last_match = Arc::new(Mutex::new(None::<String>));
+    // What the filter's capture group has matched, for the captures panel.
+    let captures = Arc::new(Mutex::new(captures::Captures::default()));
+    // Structured records shown, for the table view.
+    let records = Arc::new(Mutex::new(records::Records::new(options.columns.clone())));
+
+    // Recent lines, for browsing and saving to a file.
+    let scrollback = Arc::new(Mutex::new(Scrollback::new(SCROLLBACK_LINES)));
+    let browse = Arc::new(Mutex::new(Browse::default()));
+
+    // Spawn pipe reader thread if input is piped.
+    let progress = Arc::new(Mutex::new(Progress::new(
+        started,
+        progress::stdin_file_size().filter(|_| is_pipe),
+    )));
+    let mut last_progress = Instant::now();
+    let meter =
+        (options.meter || options.totals).then(|| Arc::new(Mutex::new(Meter::new(started))));
+    // Runs of the watched command shown in the status area so far.
+    let mut watch_runs = 0;
+
+    if let Some(addr) = &options.metrics {
+        #[cfg(feature = "metrics")]
+        metrics::serve(addr, stats.clone(), progress.clone())?;
+        #[cfg(not(feature = "metrics"))]
         {
-            let filter_string = filter_for_pipe.clone();
-
-            thread::spawn(move || {
-                let stdout = io::stdout();
-                let mut out = stdout.lock();
-                for line in rx_pipe {
-                    // Get the current filter string for highlighting
-                    let current_filter = filter_string.lock().unwrap().clone();
-                    let highlighted_line = highlight_word_in_string(&line, &current_filter);
-                    if writeln!(out, "{}", highlighted_line).is_err() {
-                        break;
-                    }
-                }
-            });
+            eprintln!("--metrics {}: built without the metrics feature", addr);
+            std::process::exit(2);
         }
     }
 
-    // Updated terminal key listener with filter editing capabilities
-    {
-        use nix::fcntl::{fcntl, FcntlArg, OFlag};
-        let quit_tx_term = quit_tx.clone();
-        let filter_string_for_input = filter_string.clone();
-        let mut term_in = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .append(true)
-            .open("/dev/tty")
-            .expect("Could not open /dev/tty for reading");
-        let fd = term_in.as_raw_fd();
-
-        // Set /dev/tty to nonblocking mode.
-        let flags =
-            OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL).expect("Failed to get flags"));
-        fcntl(fd, FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK))
-            .expect("Failed to set nonblocking mode");
-
-        let orig_termios = tcgetattr(fd).expect("Failed to get terminal attributes");
-        let mut raw = orig_termios.clone();
-        raw.local_flags.remove(LocalFlags::ICANON);
-        raw.local_flags.remove(LocalFlags::ECHO);
-        tcsetattr(fd, SetArg::TCSANOW, &raw).expect("Failed to set terminal to raw mode");
-
-        // Create a mutex-wrapped reference to status_bar for the thread
-        let status_bar = Arc::new(Mutex::new(status_bar));
-        let status_bar_for_thread = status_bar.clone();
+    // With --checksum stdin is hashed and, unless stdout is the terminal, copied there as is.
+    let digest = options
+        .checksum
+        .then(|| Arc::new(Mutex::new(checksum::Sha256::default())));
+    let passthrough = options.checksum && !io::stdout().is_terminal();
 
+    if (replaying || stdin_piped) && watch.is_none() {
+        // With other sources still going, the end of stdin is not the end of the input.
+        let ends = options.sources.is_empty();
+        let tx_pipe = tx_pipe.clone();
+        let backlog = backlog.clone();
+        let digest = digest.clone();
+        let last_input = last_input.clone();
+        let progress = progress.clone();
+        let joiner = joiner.clone();
+        let record = record.clone();
+        let filter_string = filter_string.clone();
+        let status_bar = status_bar.clone();
+        let config = config.clone();
+        let playback = playback.clone();
+        let scrollback = scrollback.clone();
+        let cursor = cursor.clone();
         thread::spawn(move || {
-            let mut buf = [0u8; 1];
-            loop {
-                match term_in.read(&mut buf) {
-                    Ok(1) => {
-                        match buf[0] {
-                            b'q' => {
-                                // Still quit when 'q' is pressed
-                                writeln!(term_out, "Quitting...").unwrap_or(());
-                                let _ = quit_tx_term.send(());
+            let config_for_redact = config.clone();
+            let lines: Box<dyn Iterator<Item = (String, usize)>> = match replay {
+                Some(reader) => {
+                    // Recorded filter changes are applied as playback reaches them.
+                    let mut position = Duration::ZERO;
+                    Box::new(reader.map_while(Result::ok).filter_map(move |frame| {
+                        recording::wait_until(&playback, &mut position, frame.at);
+                        match frame.event {
+                            recording::Event::Line(line) => {
+                                let size = line.len() + 1;
+                                Some((line, size))
+                            }
+                            recording::Event::Filter(filter) => {
+                                let mut status = status_bar.lock().unwrap();
+                                status.update(
+                                    1,
+                                    &filter_status(&filter, false, &config.lock().unwrap()),
+                                );
+                                *filter_string.lock().unwrap() = filter;
+                                None
+                            }
+                            recording::Event::Note(note) => {
+                                scrollback.lock().unwrap().annotate(&note);
+                                status_bar
+                                    .lock()
+                                    .unwrap()
+                                    .update(2, &format!("Note: {}", note));
+                                None
+                            }
+                        }
+                    }))
+                }
+                None => match &digest {
+                    Some(hash) => Box::new(lines_with_size(checksum::Passthrough::new(
+                        io::stdin().lock(),
+                        passthrough.then(io::stdout),
+                        hash.clone(),
+                    ))),
+                    None => Box::new(lines_with_size(io::stdin().lock())),
+                },
+            };
+            let mut redactor = Redactor::new(&config_for_redact.lock().unwrap().redact);
+            for (line, size) in lines {
+                let now = Instant::now();
+                *last_input.lock().unwrap() = now;
+                progress.lock().unwrap().record(size, now);
+                if let Some(cursor) = &cursor {
+                    cursor.lock().unwrap().read(size);
+                }
+                {
+                    let config = config_for_redact.lock().unwrap();
+                    if !redactor.is_for(&config.redact) {
+                        redactor = Redactor::new(&config.redact);
+                    }
+                }
+                let line = redactor.redact(&line).into_owned();
+                record(recording::Event::Line(line.clone()), Some(0));
+                // Continuation lines are held back until their record is complete. Records
+                // are sent with the joiner held, so they are numbered in the order read.
+                let mut joiner = joiner.as_ref().map(|joiner| joiner.lock().unwrap());
+                let record = match joiner.as_mut() {
+                    Some(joiner) => joiner.push(line, now),
+                    None => Some(line),
+                };
+                if let Some(record) = record {
+                    backlog.lock().unwrap().add();
+                    tx_pipe.send_stdin(record);
+                }
+                drop(joiner);
+                // Stop reading while the backlog is full, so the producer blocks on the pipe.
+                while backlog.lock().unwrap().is_full(Instant::now()) {
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+            if let Some(record) = joiner.and_then(|joiner| joiner.lock().unwrap().flush()) {
+                backlog.lock().unwrap().add();
+                tx_pipe.send_stdin(record);
+            }
+            if ends {
+                tx_pipe.send(None);
+            }
+        });
+    }
+
+    for (index, spec) in options.sources.iter().enumerate() {
+        let tx_pipe = tx_pipe.clone();
+        let backlog = backlog.clone();
+        let last_input = last_input.clone();
+        let record = record.clone();
+        let config = config.clone();
+        let sources_for_thread = sources.clone();
+        let redactor = Mutex::new(Redactor::new(&config.lock().unwrap().redact));
+        source::spawn(spec.clone(), index, sources.clone(), move |line| {
+            *last_input.lock().unwrap() = Instant::now();
+            let line = {
+                let config = config.lock().unwrap();
+                let mut redactor = redactor.lock().unwrap();
+                if !redactor.is_for(&config.redact) {
+                    *redactor = Redactor::new(&config.redact);
+                }
+                redactor.redact(line).into_owned()
+            };
+            let passed = sources_for_thread.lock().unwrap().arrive(index, &line);
+            if let Some(line) = passed {
+                record(recording::Event::Line(line.clone()), Some(index + 1));
+                backlog.lock().unwrap().add();
+                tx_pipe.send(Some(line));
+            }
+        });
+    }
+
+    // Modified pipe printer thread with access to shared filter string
+    {
+        let filter_string = filter_for_pipe.clone();
+        let committed = committed.clone();
+        let config = config.clone();
+        let last_match = last_match.clone();
+        let captured = captures.clone();
+        let tabled = records.clone();
+        let scrollback = scrollback.clone();
+        let cursor = cursor.clone();
+        let stats = stats.clone();
+        let view = view.clone();
+        let field = field.clone();
+        let distinct = distinct.clone();
+        let pretty = pretty.clone();
+        let toggles = toggles.clone();
+        let line_numbers = options.line_numbers;
+        let pin_first = options.header;
+        let (head_lines, tail_lines) = (options.head, options.tail);
+        let (before_context, after_context) = (options.before_context, options.after_context);
+        let (mut max_count, pause_at_max) = (options.max_count, options.pause_at_max);
+        let max_rate = max_rate.clone();
+        let stepping = toggles.clone();
+        let last_burst = last_burst.clone();
+        let substitution = substitution.clone();
+        let kept = patterns.clone();
+        let hyperlinks = options.hyperlinks.unwrap_or_else(|| {
+            (passthrough || io::stdout().is_terminal()) && hyperlink::supported()
+        });
+        let fade =
+            (options.fade && (passthrough || io::stdout().is_terminal())).then(|| fresh.clone());
+        let display = match passthrough {
+            true => Some(term_out.try_clone()?),
+            false => None,
+        };
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let mut columns = Some(logfmt::Columns::new(options.logfmt.clone()))
+            .filter(|_| !options.logfmt.is_empty());
+        let mut table = options
+            .csv
+            .map(|separator| csv::Table::new(separator, options.bands));
+        let status_bar = status_bar.clone();
+        let syslog_filter = syslog_filter.clone();
+        let rewriter = rewriter.clone();
+        let min_level = min_level.clone();
+        let only_level = only_level.clone();
+        let level_counts = options.level_counts;
+        let backlog = backlog.clone();
+        let meter = meter.clone();
+        let source_colors = sources.lock().unwrap().colors();
+        // Lines only end up on screen when stdout is the terminal.
+        let cast = cast.clone().filter(|_| io::stdout().is_terminal());
+        let share = share.clone().filter(|_| io::stdout().is_terminal());
+        let quit_tx = quit_tx.clone();
+
+        thread::spawn(move || {
+            // With --checksum stdout gets the input as it came, and lines go to the terminal.
+            let mut out: Box<dyn Write> = match display {
+                Some(display) => Box::new(display),
+                None => Box::new(Tee::new(io::stdout().lock(), cast).mirrored(share)),
+            };
+            // Start of a container log line split over several entries.
+            let mut partial = String::new();
+            // Rebuilt only when the filter or its options change.
+            let mut matcher = Matcher::new("", MatchOptions::default());
+            let mut column_matcher = matcher.clone();
+            let mut committed_matcher = matcher.clone();
+            // The filter and each term on their own, for counting their matches apart.
+            let mut counters = vec![matcher.clone()];
+            // Lines hidden by the filter since the last one shown, and whether any has been.
+            let mut hidden: u64 = 0;
+            let mut shown = false;
+            // Lines shown so far, for --head, and the last ones held back for --tail.
+            let mut printed = 0;
+            // Lines shown that match the filter, for --max-count.
+            let mut matches = 0;
+            let mut tail = VecDeque::new();
+            let mut rate_limit = None::<ratelimit::RateLimit>;
+            let mut folder = fold::Folder::default();
+            // Hidden lines kept to show before the next match, and how many after the last
+            // one are still to be shown, for -B and -A.
+            let mut leading = VecDeque::new();
+            let mut trailing = 0;
+            let mut seen = diff::Seen::new(normalizer);
+            // --head 0 shows nothing, so stop before the first line.
+            if head_lines == Some(0) {
+                let _ = quit_tx.send(());
+                return;
+            }
+            for input in rx_pipe {
+                backlog.lock().unwrap().take();
+                let Some(lane::Input {
+                    text: raw,
+                    number,
+                    stdin,
+                }) = input
+                else {
+                    if let Some(burst) = folder.burst() {
+                        let _ = writeln!(out, "{}", burst.summary_in_place());
+                    }
+                    let dropped = rate_limit.as_mut().map_or(0, |limit| limit.take_dropped());
+                    if dropped > 0 {
+                        tail.push_back(dropped_separator(dropped));
+                    }
+                    for line in tail.drain(..) {
+                        let _ = writeln!(out, "{}", line);
+                    }
+                    // Quit once everything piped in has been shown, but keep the final
+                    // counts on screen.
+                    if !stats_mode {
+                        let _ = quit_tx.send(());
+                    }
+                    break;
+                };
+                while toggles.lock().unwrap().paused {
+                    thread::sleep(Duration::from_millis(50));
+                }
+                let max = *max_rate.lock().unwrap();
+                if max != rate_limit.as_ref().map(ratelimit::RateLimit::max) {
+                    rate_limit = max.map(|max| ratelimit::RateLimit::new(max, Instant::now()));
+                }
+                let toggles = *toggles.lock().unwrap();
+                if let Some(meter) = &meter {
+                    meter.lock().unwrap().record(raw.len() + 1, Instant::now());
+                }
+                // Joined records span several input lines.
+                let input_lines = raw.split('\n').count();
+                // Only lines read from stdin moved the cursor on.
+                let _handling = cursor::Handling::new(cursor.as_deref().zip(stdin), input_lines);
+                // Container runtimes wrap each line; show what the program actually wrote.
+                let line = match container::parse(&raw) {
+                    Some(entry) if entry.partial => {
+                        partial.push_str(&entry.message);
+                        continue;
+                    }
+                    Some(entry) => std::mem::take(&mut partial) + &entry.message,
+                    None => raw.clone(),
+                };
+                if pin_first && number == 1 {
+                    status_bar.lock().unwrap().set_header(line);
+                    continue;
+                }
+                let scrolled = {
+                    let mut scrollback = scrollback.lock().unwrap();
+                    scrollback.push(&line);
+                    scrollback.end() - 1
+                };
+                // Lines are only noted while repeats are dimmed, so nobody else pays for
+                // hashing each one; turning it on starts from the lines that follow.
+                let repeated = toggles.dups && seen.repeated(&line);
+                // Get the current filter string for highlighting
+                let current_filter = filter_string.lock().unwrap().clone();
+                let current_terms = kept.lock().unwrap().active_terms();
+                if !matcher.is_for(&current_filter, toggles.matching)
+                    || !matcher.has_terms(&current_terms)
+                {
+                    matcher =
+                        Matcher::new(&current_filter, toggles.matching).with_terms(&current_terms);
+                    counters = crate::counters(&current_filter, &current_terms, toggles.matching);
+                }
+                let committed = committed.lock().unwrap().clone();
+                if let Some(committed) = &committed {
+                    if !committed_matcher.is_for(committed, toggles.matching)
+                        || !committed_matcher.has_terms(&current_terms)
+                    {
+                        committed_matcher =
+                            Matcher::new(committed, toggles.matching).with_terms(&current_terms);
+                    }
+                }
+                let matched = !matcher.is_empty() && matcher.is_match(&line);
+                if matched {
+                    *last_match.lock().unwrap() = Some(line.clone());
+                    let values = matcher.captures(&line);
+                    if !values.is_empty() {
+                        captured.lock().unwrap().record(&current_filter, values);
+                    }
+                }
+                // The first CSV record is pinned as the header rather than printed.
+                let cells = match table.as_mut() {
+                    Some(table) => {
+                        let cells = table.render(&line);
+                        if let Some(header) = table.header() {
+                            status_bar.lock().unwrap().set_header(header);
+                        }
+                        match cells {
+                            Some(cells) => Some(cells),
+                            None => continue,
+                        }
+                    }
+                    None => None,
+                };
+                let config = config.lock().unwrap();
+                let counted = counted(&line, &counters, &config.patterns);
+                let mut line_stats = stats.lock().unwrap();
+                line_stats.record(&line, &counted, Instant::now());
+                if let Some(value) = field.as_ref().and_then(|spec| spec.extract(&raw)) {
+                    line_stats.field.record(value);
+                }
+                if let Some(value) = distinct.as_ref().and_then(|spec| spec.text(&raw)) {
+                    line_stats.distinct.record(&value);
+                }
+                if let Some(level) = level_counts.then(|| level::detect(&line)).flatten() {
+                    line_stats.levels.record(level);
+                }
+                if let Some(spans) = line_stats.spans.as_mut() {
+                    spans.record(&line, Instant::now());
+                }
+                drop(line_stats);
+                if matcher.is_match(&line) || (!toggles.hide && !matcher.is_excluded(&line)) {
+                    tabled.lock().unwrap().record(&line);
+                }
+                if *view.lock().unwrap() != View::Lines || !toggles.follow {
+                    continue;
+                }
+                if let Some(min) = *min_level.lock().unwrap() {
+                    if level::detect(&line).is_some_and(|level| level < min) {
+                        continue;
+                    }
+                }
+                if let Some(only) = *only_level.lock().unwrap() {
+                    if level::detect(&line) != Some(only) {
+                        continue;
+                    }
+                }
+                let line = match &rewriter {
+                    Some(rewriter) => rewriter.rewrite(&line),
+                    None => line,
+                };
+                let line = match &*substitution.lock().unwrap() {
+                    Some(substitution) => substitution.apply(&line).into_owned(),
+                    None => line,
+                };
+                let message = syslog.then(|| syslog::parse(&line)).flatten();
+                if message
+                    .as_ref()
+                    .is_some_and(|message| !syslog_filter.allows(message))
+                {
+                    continue;
+                }
+
+                let mut tokens = None;
+                let mut filter = &matcher;
+                let mut only = None;
+                if *pretty.lock().unwrap() {
+                    tokens = json::pretty(&line);
+                }
+                if let Some(cells) = cells.filter(|_| tokens.is_none()) {
+                    let table = table.as_ref().unwrap();
+                    if let Some((column, text)) = table.column_filter(&current_filter) {
+                        if !column_matcher.is_for(text, toggles.matching) {
+                            column_matcher = Matcher::new(text, toggles.matching);
+                        }
+                        filter = &column_matcher;
+                        only = Some(column * 2);
+                    }
+                    tokens = Some(cells);
+                }
+                if let Some(message) = message.filter(|_| tokens.is_none()) {
+                    tokens = Some(message.tokens(config.theme));
+                }
+                if tokens.is_none() {
+                    tokens = access::tokens(&line);
+                }
+                if let Some(columns) = columns.as_mut().filter(|_| tokens.is_none()) {
+                    tokens = logfmt::parse(&line).and_then(|pairs| columns.render(&pairs));
+                }
+                // A column filter only looks at its column.
+                let text = match (only, &tokens) {
+                    (Some(only), Some(tokens)) => tokens.get(only).map_or("", |t| &t.0),
+                    _ => &line,
+                };
+                let hiding = match (&committed, only) {
+                    (Some(_), None) => &committed_matcher,
+                    _ => filter,
+                };
+                // Excluded lines are never shown, even with hiding off.
+                if hiding.is_excluded(text) {
+                    hidden += 1;
+                    continue;
+                }
+                if !toggles.hide || hiding.is_match(text) {
+                    trailing = after_context;
+                } else if trailing > 0 {
+                    trailing -= 1;
+                } else {
+                    if before_context > 0 {
+                        if leading.len() == before_context {
+                            leading.pop_front();
+                        }
+                        leading.push_back((number, line.clone()));
+                    }
+                    hidden += 1;
+                    continue;
+                }
+                if known.as_ref().is_some_and(|known| known.contains(&line)) {
+                    hidden += 1;
+                    continue;
+                }
+                // Repeats are folded in place, which lines held back for --tail cannot be.
+                if toggles.fold && tail_lines.is_none() {
+                    match folder.push(&line, scrolled, Instant::now()) {
+                        fold::Fold::Show => {}
+                        fold::Fold::EndBurst(burst) => {
+                            let _ = writeln!(out, "{}", burst.summary_in_place());
+                        }
+                        fold::Fold::Start(burst) => {
+                            *last_burst.lock().unwrap() = Some(burst.line);
+                            let summary = burst.summary();
+                            if let Some(fresh) = &fade {
+                                fresh.lock().unwrap().other(&summary);
+                            }
+                            let _ = writeln!(out, "{}", summary);
+                            continue;
+                        }
+                        fold::Fold::Redraw(burst) => {
+                            let _ = writeln!(out, "{}", burst.summary_in_place());
+                            continue;
+                        }
+                        fold::Fold::Hide => continue,
+                    }
+                }
+                let dropped = match rate_limit.as_mut() {
+                    Some(limit) => match limit.admit(Instant::now(), is_priority(&line)) {
+                        Some(dropped) => dropped,
+                        None => {
+                            stats.lock().unwrap().dropped += 1;
+                            continue;
+                        }
+                    },
+                    None => 0,
+                };
+                let context: Vec<(usize, String)> = leading.drain(..).collect();
+                hidden = hidden.saturating_sub(context.len() as u64);
+                let mut separator = String::new();
+                if hidden > 0 && shown {
+                    separator = format!(
+                        "\x1B[2m── {} lines hidden ──\x1B[0m\n",
+                        progress::format_count(hidden)
+                    );
+                }
+                if dropped > 0 {
+                    separator += &(dropped_separator(dropped) + "\n");
+                }
+                hidden = 0;
+                shown = true;
+                let patterns = kept.lock().unwrap();
+                for (number, text) in context {
+                    let text = highlight_line(&text, filter, &config, &patterns);
+                    separator += &match line_numbers {
+                        true => format!("\x1B[2m{:>6}\x1B[0m {}\n", number, text),
+                        false => text + "\n",
+                    };
+                }
+                let highlighted_line = match (tokens, &cut) {
+                    (Some(tokens), _) => render_tokens(tokens, filter, &config, &patterns, only),
+                    // Cut only for display; the whole line was matched.
+                    (None, Some(cut)) => {
+                        highlight_line(&cut.apply(&line), filter, &config, &patterns)
+                    }
+                    (None, None) => highlight_line(&line, filter, &config, &patterns),
+                };
+                drop(patterns);
+                drop(config);
+                let colored = source_colors
+                    .iter()
+                    .find(|(prefix, _)| highlighted_line.starts_with(prefix.as_str()));
+                let highlighted_line = match colored {
+                    Some((prefix, sgr)) => format!(
+                        "\x1B[{}m{}\x1B[0m{}",
+                        sgr,
+                        prefix,
+                        &highlighted_line[prefix.len()..]
+                    ),
+                    None => highlighted_line,
+                };
+                let highlighted_line = match repeated {
+                    true => format!(
+                        "\x1B[2m{}\x1B[0m",
+                        highlighted_line.replace("\x1B[0m", "\x1B[0;2m")
+                    ),
+                    false => highlighted_line,
+                };
+                let highlighted_line = match line_numbers {
+                    true => format!("\x1B[2m{:>6}\x1B[0m {}", number, highlighted_line),
+                    false => highlighted_line,
+                };
+                let highlighted_line = match hyperlinks {
+                    true => hyperlink::link(&highlighted_line, &cwd),
+                    false => highlighted_line,
+                };
+                // In step mode each line waits for a key press.
+                loop {
+                    let mut toggles = stepping.lock().unwrap();
+                    if !toggles.step || toggles.steps > 0 {
+                        toggles.steps = toggles.steps.saturating_sub(1);
+                        break;
+                    }
+                    drop(toggles);
+                    thread::sleep(Duration::from_millis(50));
+                }
+                if let Some(limit) = tail_lines {
+                    if tail.len() == limit {
+                        tail.pop_front();
+                    }
+                    if limit > 0 {
+                        tail.push_back(separator + &highlighted_line);
+                    }
+                    continue;
+                }
+                let written = match &fade {
+                    // Noted and written together so a repaint cannot count rows not yet there.
+                    Some(fresh) => {
+                        let mut fresh = fresh.lock().unwrap();
+                        if !separator.is_empty() {
+                            fresh.other(separator.trim_end_matches('\n'));
+                        }
+                        let line = fresh.line(&highlighted_line, Instant::now());
+                        writeln!(out, "{}{}", separator, line).and_then(|_| out.flush())
+                    }
+                    None => writeln!(out, "{}{}", separator, highlighted_line),
+                };
+                if written.is_err() {
+                    // Keep counting (and serving metrics) even if the output went away.
+                    stats.lock().unwrap().dropped += 1;
+                }
+                printed += 1;
+                if head_lines.is_some_and(|limit| printed >= limit) {
+                    let _ = quit_tx.send(());
+                    break;
+                }
+                matches += matched as usize;
+                if max_count.is_some_and(|limit| matches >= limit) {
+                    if !pause_at_max {
+                        let _ = quit_tx.send(());
+                        break;
+                    }
+                    // Paused once; resuming shows the rest.
+                    max_count = None;
+                    let notice = format!("{} matches shown; paused", matches);
+                    status_bar.lock().unwrap().update(2, &notice);
+                    stepping.lock().unwrap().paused = true;
+                }
+            }
+        });
+    }
+
+    // Updated terminal key listener with filter editing capabilities
+    let term_in = terminal::open_input().expect("Could not open terminal for reading");
+    let raw_mode = terminal::RawMode::enable(&term_in).expect("Failed to set terminal to raw mode");
+    terminal::set_bracketed_paste(&mut term_out, true)?;
+    let mut title = String::new();
+    // Whether --alert-idle has gone off for the current silence.
+    let mut idle_alerted = false;
+    // The end of the scrollback when --snapshot-idle last wrote it, so a silence with
+    // nothing new is not written again.
+    let mut snapshot_end = 0;
+    {
+        let quit_tx_term = quit_tx.clone();
+        let filter_string_for_input = filter_string.clone();
+        let tx_input = tx_pipe.clone();
+        let backlog = backlog.clone();
+        let last_input = last_input.clone();
+        let last_match = last_match.clone();
+        let captures = captures.clone();
+        let records = records.clone();
+        let scrollback = scrollback.clone();
+        let browse = browse.clone();
+        let view = view.clone();
+        let pretty = pretty.clone();
+        let toggles = toggles.clone();
+        let min_level = min_level.clone();
+        let only_level = only_level.clone();
+        let stats = stats.clone();
+        let level_counts = options.level_counts;
+        let playback = playback.clone();
+        let caps = caps.clone();
+        let mut keys_in = input::InputReader::new(terminal::KeyReader::new(term_in));
+        let mut term_out = term_out.try_clone()?;
+
+        let record = record.clone();
+        let status_bar_for_thread = status_bar.clone();
+        let config = config.clone();
+
+        let proposed = proposed.clone();
+        let sources = sources.clone();
+        let committed = committed.clone();
+        let patterns = patterns.clone();
+
+        thread::spawn(move || {
+            let mut mode = initial_mode;
+            let mut input = String::new();
+            let mut note = String::new();
+            let mut command_line = String::new();
+            let mut replace = String::new();
+            let mut editor = LineEditor::default();
+            // With `style = vi`, whether Esc has switched the filter line to command mode.
+            let mut vi_command = false;
+            let mut vi = editor::Vi::default();
+            // Filters committed in this session, or in earlier ones too, for Up and Down.
+            let mut history = history;
+            // Ctrl+R query, and the index of the filter it found.
+            let mut query = String::new();
+            let mut found = None;
+            // Count typed before a command in normal mode, such as the 20 of `20j`.
+            let mut count: Option<usize> = None;
+            // First key of a chord such as `gg`, and when it was pressed.
+            let mut pending: Option<(u8, Instant)> = None;
+            let mut undo = undo::Undo::default();
+            let mut last_setting = setting(
+                &filter_string_for_input.lock().unwrap(),
+                *toggles.lock().unwrap(),
+            );
+            loop {
+                // Whatever changed the setting since the last key, it can be undone.
+                let current = setting(
+                    &filter_string_for_input.lock().unwrap(),
+                    *toggles.lock().unwrap(),
+                );
+                if current != last_setting {
+                    let typed = current.1 == last_setting.1;
+                    let before = std::mem::replace(&mut last_setting, current);
+                    undo.record(before, typed, Instant::now());
+                }
+                let read = keys_in.read(Duration::from_millis(100));
+                // The filter as it was before the first key of an edit stays applied.
+                if matches!(read, Ok(Some(_)))
+                    && matches!(mode, Mode::Filter | Mode::Search)
+                    && config.lock().unwrap().commit_on_enter
+                {
+                    let filter = filter_string_for_input.lock().unwrap();
+                    committed
+                        .lock()
+                        .unwrap()
+                        .get_or_insert_with(|| filter.clone());
+                }
+                match read {
+                    // A typed character beyond ASCII is not a command, so it is ignored where
+                    // keys are commands.
+                    Ok(Some(input::Input::Char(_)))
+                        if mode == Mode::Normal || (mode == Mode::Filter && vi_command) => {}
+                    Ok(Some(literal @ (input::Input::Paste(_) | input::Input::Char(_)))) => {
+                        // Pasted text is always literal input, never commands.
+                        let mut status = status_bar_for_thread.lock().unwrap();
+                        let config = config.lock().unwrap();
+                        let text = match literal {
+                            input::Input::Paste(text) => input::single_line(&text),
+                            input::Input::Char(c) => c.to_string(),
+                            _ => continue,
+                        };
+                        match mode {
+                            Mode::Input => {
+                                input.push_str(&text);
+                                status.update(0, &input_status(&input, true));
+                            }
+                            Mode::Note => {
+                                note.push_str(&text);
+                                status.update(2, &note_status(&note));
+                            }
+                            Mode::Command => {
+                                command_line.push_str(&text);
+                                status.update(2, &command_status(&command_line));
+                            }
+                            Mode::Replace => {
+                                replace.push_str(&text);
+                                let parsed = Substitution::parse(&replace);
+                                status.update(
+                                    2,
+                                    &replace_status(
+                                        &replace,
+                                        parsed.as_ref().err().map(String::as_str),
+                                    ),
+                                );
+                                *proposed.lock().unwrap() = parsed.unwrap_or(None);
+                            }
+                            Mode::Search => {
+                                query.push_str(&text);
+                                let result = history.search(&query, None);
+                                found = result.map(|(index, _)| index);
+                                status.update(1, &search_status(&query, result.map(|(_, f)| f)));
+                            }
+                            Mode::Filter | Mode::Normal => {
+                                if mode == Mode::Normal {
+                                    mode = Mode::Filter;
+                                    status.update(2, "");
+                                }
+                                let mut filter = filter_string_for_input.lock().unwrap();
+                                if edit_filter(&mut editor, &mut filter, |e| e.insert(&text)) {
+                                    history.edited();
+                                    record(recording::Event::Filter(filter.clone()), None);
+                                }
+                                status.update(1, &editing_status(&editor, !is_pipe, &config));
+                            }
+                        }
+                    }
+                    Ok(Some(input::Input::Key(key))) => {
+                        let mut status = status_bar_for_thread.lock().unwrap();
+                        let mut config = config.lock().unwrap();
+                        let keys = config.keys;
+                        let repeat = count.take();
+                        let chord = pending
+                            .take()
+                            .filter(|(_, at)| at.elapsed() < CHORD_TIMEOUT)
+                            .map(|(first, _)| [first, key])
+                            .filter(|chord| CHORDS.contains(&chord));
+                        match (mode, key) {
+                            (_, key) if key == keys.switch && !is_pipe => {
+                                // Switch between editing the input line and the filter
+                                mode = match mode {
+                                    Mode::Filter
+                                    | Mode::Normal
+                                    | Mode::Note
+                                    | Mode::Command
+                                    | Mode::Replace
+                                    | Mode::Search => Mode::Input,
+                                    Mode::Input => Mode::Filter,
+                                };
+                                vi_command = false;
+                                let filter = filter_string_for_input.lock().unwrap();
+                                status.update(0, &input_status(&input, mode == Mode::Input));
+                                status.update(
+                                    1,
+                                    &filter_status(&filter, mode == Mode::Filter, &config),
+                                );
+                            }
+                            (_, key) if key == keys.copy => {
+                                let notice = match last_match.lock().unwrap().as_deref() {
+                                    Some(line) => match clipboard::copy(line, &mut term_out) {
+                                        Ok(method) => {
+                                            format!("Copied last match ({})", method.name())
+                                        }
+                                        Err(e) => format!("Copy failed: {}", e),
+                                    },
+                                    None => "Nothing matched yet".to_string(),
+                                };
+                                status.update(2, &notice);
+                            }
+                            (_, key) if key == keys.pretty => {
+                                let mut pretty = pretty.lock().unwrap();
+                                *pretty = !*pretty;
+                                let state = if *pretty { "on" } else { "off" };
+                                status.update(2, &format!("Pretty JSON {}", state));
+                            }
+                            (_, key) if key == keys.histogram || key == keys.topk => {
+                                let mut view = view.lock().unwrap();
+                                *view = match *view {
+                                    View::TopK if key == keys.topk => View::Lines,
+                                    _ if key == keys.topk => View::TopK,
+                                    current => current.next_histogram(),
+                                };
+                                if *view == View::Lines {
+                                    // Clear the histogram away before lines flow again
+                                    let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                    let height = rows.saturating_sub(status.height());
+                                    let _ = draw_panel(&mut term_out, &caps, &[], columns, height);
+                                }
+                            }
+                            (Mode::Input, 4) if input.is_empty() => {
+                                // Ctrl+D on an empty line ends interactive input
+                                let _ = quit_tx_term.send(());
                                 break;
                             }
-                            8 | 127 => {
-                                // Backspace or Delete
-                                // Remove the last character from filter_string
+                            (Mode::Input, b'\r' | b'\n') => {
+                                *last_input.lock().unwrap() = Instant::now();
+                                let line = Redactor::new(&config.redact)
+                                    .redact(&std::mem::take(&mut input))
+                                    .into_owned();
+                                record(recording::Event::Line(line.clone()), Some(0));
+                                backlog.lock().unwrap().add();
+                                tx_input.send(Some(line));
+                                status.update(0, &input_status(&input, true));
+                            }
+                            (Mode::Input, 8 | 127) => {
+                                input.pop();
+                                status.update(0, &input_status(&input, true));
+                            }
+                            (Mode::Input, 32..=126) => {
+                                input.push(key as char);
+                                status.update(0, &input_status(&input, true));
+                            }
+                            (Mode::Filter, key)
+                                if key == keys.normal
+                                    && config.editing == EditingStyle::Vi
+                                    && !vi_command =>
+                            {
+                                // The first Esc leaves vi insert mode, a second one leaves
+                                // the filter.
+                                vi_command = true;
                                 let mut filter = filter_string_for_input.lock().unwrap();
-                                if !filter.is_empty() {
-                                    filter.pop();
-                                    // Update status bar with new filter
-                                    let mut status = status_bar_for_thread.lock().unwrap();
+                                edit_filter(&mut editor, &mut filter, LineEditor::left);
+                                status.update(1, &editing_status(&editor, !is_pipe, &config));
+                                status.update(2, "-- COMMAND -- i inserts, Esc leaves the filter");
+                            }
+                            (Mode::Filter, key) if key == keys.normal => {
+                                mode = Mode::Normal;
+                                vi_command = false;
+                                let mut filter = filter_string_for_input.lock().unwrap();
+                                // An edit not applied with Enter is dropped.
+                                if let Some(applied) = committed.lock().unwrap().take() {
+                                    if *filter != applied {
+                                        *filter = applied;
+                                        record(recording::Event::Filter(filter.clone()), None);
+                                    }
+                                }
+                                history.commit(&filter);
+                                status.update(1, &filter_status(&filter, false, &config));
+                                status.update(
+                                    2,
+                                    "Normal mode: / edits the filter, +/- change the level, j/k/n/N browse, S saves lines",
+                                );
+                            }
+                            (Mode::Normal, key) if key == keys.edit => {
+                                mode = Mode::Filter;
+                                let filter = filter_string_for_input.lock().unwrap();
+                                status.update(1, &filter_status(&filter, true, &config));
+                                status.update(2, "");
+                            }
+                            (Mode::Normal, key) if key == keys.sources => {
+                                let mut view = view.lock().unwrap();
+                                if sources.lock().unwrap().list.is_empty() {
+                                    status.update(2, "No --file or --exec sources");
+                                    continue;
+                                }
+                                *view = match *view {
+                                    View::Sources => View::Lines,
+                                    _ => View::Sources,
+                                };
+                                if *view == View::Lines {
+                                    let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                    let height = rows.saturating_sub(status.height());
+                                    let _ = draw_panel(&mut term_out, &caps, &[], columns, height);
+                                }
+                            }
+                            (Mode::Normal, key)
+                                if *view.lock().unwrap() == View::Sources
+                                    && [keys.down, keys.up, keys.pause, keys.mute]
+                                        .contains(&key) =>
+                            {
+                                let mut sources = sources.lock().unwrap();
+                                match key {
+                                    key if key == keys.down || key == keys.up => {
+                                        sources.select(key == keys.down)
+                                    }
+                                    key if key == keys.mute => sources.toggle_mute(),
+                                    _ => {
+                                        let source = sources.selected + 1;
+                                        for line in sources.toggle_pause() {
+                                            record(
+                                                recording::Event::Line(line.clone()),
+                                                Some(source),
+                                            );
+                                            backlog.lock().unwrap().add();
+                                            tx_input.send(Some(line));
+                                        }
+                                    }
+                                }
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let panel = sources.panel();
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key) if key == keys.patterns => {
+                                let mut view = view.lock().unwrap();
+                                *view = match *view {
+                                    View::Patterns => View::Lines,
+                                    _ => View::Patterns,
+                                };
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let panel = match *view {
+                                    View::Patterns => {
+                                        patterns.lock().unwrap().panel(&config.patterns)
+                                    }
+                                    _ => Vec::new(),
+                                };
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key)
+                                if *view.lock().unwrap() == View::Patterns
+                                    && [keys.down, keys.up, keys.pause].contains(&key) =>
+                            {
+                                let mut patterns = patterns.lock().unwrap();
+                                if key == keys.pause {
+                                    if let Some((pattern, on)) = patterns.toggle(&config.patterns) {
+                                        let state = if on { "on" } else { "off" };
+                                        status
+                                            .update(2, &format!("{} switched {}", pattern, state));
+                                    }
+                                } else {
+                                    patterns.select(key == keys.down, &config.patterns);
+                                }
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let panel = patterns.panel(&config.patterns);
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key) if key == keys.captures => {
+                                let filter = filter_string_for_input.lock().unwrap();
+                                let mut view = view.lock().unwrap();
+                                *view = match *view {
+                                    View::Captures => View::Lines,
+                                    _ => View::Captures,
+                                };
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let panel = match *view {
+                                    View::Captures => {
+                                        captures.lock().unwrap().panel(&filter, height as usize)
+                                    }
+                                    _ => Vec::new(),
+                                };
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key) if key == keys.table => {
+                                let mut view = view.lock().unwrap();
+                                *view = match *view {
+                                    View::Table => View::Lines,
+                                    _ => View::Table,
+                                };
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let panel = match *view {
+                                    View::Table => records.lock().unwrap().panel(height as usize),
+                                    _ => Vec::new(),
+                                };
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key)
+                                if *view.lock().unwrap() == View::Table
+                                    && [keys.left, keys.right, keys.pause].contains(&key) =>
+                            {
+                                let mut records = records.lock().unwrap();
+                                match key {
+                                    key if key == keys.pause => records.cycle_sort(),
+                                    _ => records.select(key == keys.right),
+                                }
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let panel = records.panel(height as usize);
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key)
+                                if *view.lock().unwrap() == View::Captures
+                                    && [keys.down, keys.up, keys.yank].contains(&key) =>
+                            {
+                                let filter = filter_string_for_input.lock().unwrap();
+                                let mut captures = captures.lock().unwrap();
+                                if key == keys.yank {
+                                    let notice = match captures.selected(&filter) {
+                                        Some(value) => {
+                                            match clipboard::copy(value, &mut term_out) {
+                                                Ok(method) => {
+                                                    format!("Copied {} ({})", value, method.name())
+                                                }
+                                                Err(e) => format!("Copy failed: {}", e),
+                                            }
+                                        }
+                                        None => "Nothing captured yet".to_string(),
+                                    };
+                                    status.update(2, &notice);
+                                } else {
+                                    captures.select(key == keys.down);
+                                }
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let panel = captures.panel(&filter, height as usize);
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key)
+                                if key == keys.level_up || key == keys.level_down =>
+                            {
+                                let mut min_level = min_level.lock().unwrap();
+                                *min_level = match *min_level {
+                                    None if key == keys.level_up => Some(level::Level::Debug),
+                                    None => None,
+                                    Some(level::Level::Trace) if key == keys.level_down => None,
+                                    Some(level) if key == keys.level_up => Some(level.raise()),
+                                    Some(level) => Some(level.lower()),
+                                };
+                                let notice = match *min_level {
+                                    Some(level) => format!("Minimum level: {}", level.name()),
+                                    None => "All levels shown".to_string(),
+                                };
+                                status.update(2, &notice);
+                            }
+                            (Mode::Normal, key) if level_counts && key == keys.severity => {
+                                let mut only_level = only_level.lock().unwrap();
+                                *only_level = stats.lock().unwrap().levels.next(*only_level);
+                                let notice = match *only_level {
+                                    Some(level) => format!("Only {} lines shown", level.name()),
+                                    None => "All levels shown".to_string(),
+                                };
+                                status.update(2, &notice);
+                            }
+                            (Mode::Normal, key)
+                                if replaying
+                                    && [keys.faster, keys.slower, keys.pause].contains(&key) =>
+                            {
+                                let mut playback = playback.lock().unwrap();
+                                match key {
+                                    key if key == keys.faster => playback.faster(),
+                                    key if key == keys.slower => playback.slower(),
+                                    _ => playback.paused = !playback.paused,
+                                }
+                                status.update(2, &playback.status());
+                            }
+                            (Mode::Normal, key) if key == keys.save || key == keys.save_matches => {
+                                let filter = filter_string_for_input.lock().unwrap();
+                                let only = Some(filter.as_str()).filter(|filter| {
+                                    key == keys.save_matches && !filter.is_empty()
+                                });
+                                let notice = match scrollback.lock().unwrap().dump(only) {
+                                    Ok((path, count)) => {
+                                        format!("Saved {} lines to {}", count, path.display())
+                                    }
+                                    Err(e) => format!("Save failed: {}", e),
+                                };
+                                status.update(2, &notice);
+                            }
+                            (Mode::Normal, b'1'..=b'9') | (Mode::Normal, b'0')
+                                if repeat.is_some() || key != b'0' =>
+                            {
+                                let typed = typed_count(repeat, key);
+                                count = Some(typed);
+                                status.update(2, &typed.to_string());
+                            }
+                            (Mode::Normal, b'g' | b'z') if chord.is_none() => {
+                                pending = Some((key, Instant::now()));
+                                count = repeat;
+                                status.update(
+                                    2,
+                                    &format!(
+                                        "{}{}",
+                                        repeat.map_or(String::new(), |n| n.to_string()),
+                                        key as char
+                                    ),
+                                );
+                            }
+                            (Mode::Normal, key)
+                                if chord.is_some()
+                                    || [
+                                        keys.down,
+                                        keys.up,
+                                        keys.next_match,
+                                        keys.previous_match,
+                                        keys.goto,
+                                    ]
+                                    .contains(&key) =>
+                            {
+                                let filter = filter_string_for_input.lock().unwrap();
+                                let matcher =
+                                    Matcher::new(&filter, toggles.lock().unwrap().matching)
+                                        .with_terms(&patterns.lock().unwrap().active_terms());
+                                let scrollback = scrollback.lock().unwrap();
+                                let mut view = view.lock().unwrap();
+                                let mut browse = browse.lock().unwrap();
+                                if *view != View::Scrollback {
+                                    // Browsing starts from the most recent line.
+                                    *view = View::Scrollback;
+                                    browse.go(&scrollback, scrollback.end());
+                                }
+                                let times = repeat.unwrap_or(1);
+                                let current = browse.line;
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                // A count picks the line first, as in `5gg` or `5zz`.
+                                if let Some(number) = repeat.filter(|_| chord.is_some()) {
+                                    browse.go(&scrollback, number - 1);
+                                }
+                                match key {
+                                    _ if chord == Some(*b"gg") => {
+                                        if repeat.is_none() {
+                                            browse.go(&scrollback, scrollback.start());
+                                        }
+                                    }
+                                    _ if chord == Some(*b"zt") => browse.place(&scrollback, 0),
+                                    _ if chord == Some(*b"zz") => {
+                                        browse.place(&scrollback, height as usize / 2)
+                                    }
+                                    _ if chord == Some(*b"zb") => browse
+                                        .place(&scrollback, (height as usize).saturating_sub(1)),
+                                    key if key == keys.down => {
+                                        browse.go(&scrollback, current.saturating_add(times))
+                                    }
+                                    key if key == keys.up => {
+                                        browse.go(&scrollback, current.saturating_sub(times))
+                                    }
+                                    key if key == keys.goto => match repeat {
+                                        // Lines are numbered from 1 on screen.
+                                        Some(number) => browse.go(&scrollback, number - 1),
+                                        None => {
+                                            *view =
+                                                if stats_mode { View::Stats } else { View::Lines };
+                                            let _ = draw_panel(
+                                                &mut term_out,
+                                                &caps,
+                                                &[],
+                                                columns,
+                                                height,
+                                            );
+                                            status.update(2, "");
+                                            continue;
+                                        }
+                                    },
+                                    _ => {
+                                        let forward = key == keys.next_match;
+                                        let mut line = current;
+                                        for _ in 0..times {
+                                            match scrollback
+                                                .find(line, forward, |l| matcher.is_match(l))
+                                            {
+                                                Some(found) => line = found,
+                                                None => break,
+                                            }
+                                        }
+                                        browse.go(&scrollback, line);
+                                    }
+                                }
+                                let searched = key == keys.next_match || key == keys.previous_match;
+                                let notice = match browse.line {
+                                    line if searched && line == current => {
+                                        "No more matches".to_string()
+                                    }
+                                    line => format!("Line {}", line + 1),
+                                };
+                                status.update(2, &notice);
+                                let panel = scrollback_panel(
+                                    &scrollback,
+                                    &mut browse,
+                                    height as usize,
+                                    &matcher,
+                                    &config,
+                                    &patterns.lock().unwrap(),
+                                );
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key) if key == keys.expand => {
+                                let Some(line) = *last_burst.lock().unwrap() else {
+                                    status.update(2, "No repeats folded yet");
+                                    continue;
+                                };
+                                let filter = filter_string_for_input.lock().unwrap();
+                                let matcher =
+                                    Matcher::new(&filter, toggles.lock().unwrap().matching)
+                                        .with_terms(&patterns.lock().unwrap().active_terms());
+                                let scrollback = scrollback.lock().unwrap();
+                                let mut view = view.lock().unwrap();
+                                let mut browse = browse.lock().unwrap();
+                                *view = View::Scrollback;
+                                browse.go(&scrollback, line);
+                                browse.place(&scrollback, 0);
+                                status.update(2, &format!("Line {}", browse.line + 1));
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let panel = scrollback_panel(
+                                    &scrollback,
+                                    &mut browse,
+                                    height as usize,
+                                    &matcher,
+                                    &config,
+                                    &patterns.lock().unwrap(),
+                                );
+                                let _ = draw_panel(&mut term_out, &caps, &panel, columns, height);
+                            }
+                            (Mode::Normal, key)
+                                if (key == keys.step
+                                    || key == keys.step_page
+                                    || (key == b'\n' && keys.step == b'\r'))
+                                    && toggles.lock().unwrap().step =>
+                            {
+                                // Enter may arrive as a line feed.
+                                let lines = match key {
+                                    key if key == keys.step_page => {
+                                        let (_, rows) = terminal::size().unwrap_or((80, 24));
+                                        rows.saturating_sub(status.height()) as usize
+                                    }
+                                    _ => 1,
+                                };
+                                toggles.lock().unwrap().steps += lines * repeat.unwrap_or(1);
+                                status.update(2, "");
+                            }
+                            (Mode::Normal, key) if key == keys.yank => {
+                                let filter = filter_string_for_input.lock().unwrap();
+                                let matcher =
+                                    Matcher::new(&filter, toggles.lock().unwrap().matching)
+                                        .with_terms(&patterns.lock().unwrap().active_terms());
+                                let line = match *view.lock().unwrap() {
+                                    View::Scrollback => {
+                                        let number = browse.lock().unwrap().line;
+                                        scrollback.lock().unwrap().get(number).map(str::to_string)
+                                    }
+                                    _ => last_match.lock().unwrap().clone(),
+                                };
+                                let Some(line) = line else {
+                                    status.update(2, "Nothing matched yet");
+                                    continue;
+                                };
+                                // Each match on a line of its own, as for several request IDs.
+                                let matched: Vec<&str> = matcher
+                                    .ranges(&line)
+                                    .into_iter()
+                                    .map(|range| &line[range])
+                                    .collect();
+                                let notice = match matched.len() {
+                                    0 => "No match in the line to yank".to_string(),
+                                    count => {
+                                        match clipboard::copy(&matched.join("\n"), &mut term_out) {
+                                            Ok(method) if count == 1 => {
+                                                format!("Yanked {} ({})", matched[0], method.name())
+                                            }
+                                            Ok(method) => {
+                                                format!(
+                                                    "Yanked {} matches ({})",
+                                                    count,
+                                                    method.name()
+                                                )
+                                            }
+                                            Err(e) => format!("Copy failed: {}", e),
+                                        }
+                                    }
+                                };
+                                status.update(2, &notice);
+                            }
+                            (Mode::Normal, key) if key == keys.copy_matches => {
+                                let filter = filter_string_for_input.lock().unwrap();
+                                let matcher =
+                                    Matcher::new(&filter, toggles.lock().unwrap().matching)
+                                        .with_terms(&patterns.lock().unwrap().active_terms());
+                                let scrollback = scrollback.lock().unwrap();
+                                let lines: Vec<String> = scrollback
+                                    .distinct(|line| matcher.is_match(line))
+                                    .into_iter()
+                                    .map(width::strip)
+                                    .collect();
+                                let notice = match lines.len() {
+                                    0 => "Nothing matched yet".to_string(),
+                                    count => {
+                                        match clipboard::copy(&lines.join("\n"), &mut term_out) {
+                                            Ok(method) => format!(
+                                                "Copied {} matching lines ({})",
+                                                progress::format_count(count as u64),
+                                                method.name()
+                                            ),
+                                            Err(e) => format!("Copy failed: {}", e),
+                                        }
+                                    }
+                                };
+                                status.update(2, &notice);
+                            }
+                            (Mode::Normal, key) if key == keys.pin => {
+                                let scrollback = scrollback.lock().unwrap();
+                                let number = match *view.lock().unwrap() {
+                                    View::Scrollback => browse.lock().unwrap().line,
+                                    _ => scrollback.end().saturating_sub(1),
+                                };
+                                match scrollback.get(number) {
+                                    Some(line) if status.header.as_deref() == Some(line) => {
+                                        status.clear_header();
+                                        status.update(2, "Header unpinned");
+                                    }
+                                    Some(line) => {
+                                        status.set_header(line.to_string());
+                                        status.update(2, "Header pinned");
+                                    }
+                                    None => status.update(2, "No line to pin"),
+                                }
+                            }
+                            (Mode::Normal, key) if key == keys.add_term => {
+                                let mut filter = filter_string_for_input.lock().unwrap();
+                                let mut patterns = patterns.lock().unwrap();
+                                if filter.is_empty() {
+                                    status.update(2, "No filter to keep as a term");
+                                    continue;
+                                }
+                                if !patterns.terms.iter().any(|(term, _)| *term == *filter) {
+                                    let style = next_term_style(&patterns.terms);
+                                    patterns.terms.push((filter.clone(), style));
+                                }
+                                // The filter starts again empty, for the next term.
+                                filter.clear();
+                                if let Some(committed) = committed.lock().unwrap().as_mut() {
+                                    committed.clear();
+                                }
+                                record(recording::Event::Filter(String::new()), None);
+                                status.update(1, &filter_status(&filter, false, &config));
+                                status.update(2, &terms_status(&patterns.terms));
+                            }
+                            (Mode::Normal, key) if key == keys.remove_term => {
+                                let mut patterns = patterns.lock().unwrap();
+                                match patterns.drop_term() {
+                                    Some(term) if patterns.terms.is_empty() => {
+                                        status.update(2, &format!("Dropped {}", term))
+                                    }
+                                    Some(_) => status.update(2, &terms_status(&patterns.terms)),
+                                    None => status.update(2, "No terms kept"),
+                                }
+                            }
+                            (Mode::Normal, key) if key == keys.preset => {
+                                if config.presets.is_empty() {
                                     status.update(
-                                        1,
-                                        &format!("Filter [\x1B[37;101m{}\x1b[44m]", *filter),
+                                        2,
+                                        "No presets; add [preset NAME] sections to the config",
                                     );
+                                    continue;
                                 }
+                                let mut filter = filter_string_for_input.lock().unwrap();
+                                let number = config.preset_number(&filter, repeat);
+                                let Some(preset) = config.presets.get(number.wrapping_sub(1))
+                                else {
+                                    status.update(2, &format!("No preset {}", number));
+                                    continue;
+                                };
+                                if *filter != preset.filter {
+                                    *filter = preset.filter.clone();
+                                    record(recording::Event::Filter(filter.clone()), None);
+                                }
+                                toggles.lock().unwrap().apply(preset);
+                                status.update(1, &filter_status(&filter, false, &config));
+                                status.update(2, &format!("Preset {}: {}", number, preset.name));
+                            }
+                            (Mode::Normal, key) if key == keys.command => {
+                                mode = Mode::Command;
+                                status.update(2, &command_status(&command_line));
+                            }
+                            (Mode::Command, b'\r' | b'\n') => {
+                                mode = Mode::Normal;
+                                let line = std::mem::take(&mut command_line);
+                                let command = match commands::parse(&line) {
+                                    Ok(command) => command,
+                                    Err(message) => {
+                                        status.update(2, &message);
+                                        continue;
+                                    }
+                                };
+                                let notice = match command {
+                                    commands::Command::Save(path) => {
+                                        let scrollback = scrollback.lock().unwrap();
+                                        let saved = match path {
+                                            Some(path) => scrollback
+                                                .save(&path, None)
+                                                .map(|count| (path, count)),
+                                            None => scrollback.dump(None),
+                                        };
+                                        match saved {
+                                            Ok((path, count)) => format!(
+                                                "Saved {} lines to {}",
+                                                count,
+                                                path.display()
+                                            ),
+                                            Err(e) => format!("Save failed: {}", e),
+                                        }
+                                    }
+                                    commands::Command::AddTerm(term) => {
+                                        let mut patterns = patterns.lock().unwrap();
+                                        add_term(&mut patterns, &term);
+                                        terms_status(&patterns.terms)
+                                    }
+                                    commands::Command::Theme(theme) => {
+                                        set_theme(&mut config, theme);
+                                        format!("Theme {}", line.trim_start_matches("theme").trim())
+                                    }
+                                    commands::Command::MaxRate(max) => {
+                                        *max_rate.lock().unwrap() = max;
+                                        match max {
+                                            Some(max) => format!("At most {} lines a second", max),
+                                            None => "No line rate limit".to_string(),
+                                        }
+                                    }
+                                    command => {
+                                        let mut filter = filter_string_for_input.lock().unwrap();
+                                        let before = filter.clone();
+                                        run_command(
+                                            &command,
+                                            &mut filter,
+                                            &mut toggles.lock().unwrap(),
+                                            &mut pretty.lock().unwrap(),
+                                            &mut min_level.lock().unwrap(),
+                                        );
+                                        if *filter != before {
+                                            record(recording::Event::Filter(filter.clone()), None);
+                                        }
+                                        status.update(1, &filter_status(&filter, false, &config));
+                                        format!("Ran: {}", line.trim())
+                                    }
+                                };
+                                status.update(2, &notice);
+                            }
+                            (Mode::Command, key) if key == keys.normal => {
+                                mode = Mode::Normal;
+                                command_line.clear();
+                                status.update(2, "");
+                            }
+                            (Mode::Command, 8 | 127) => {
+                                command_line.pop();
+                                status.update(2, &command_status(&command_line));
+                            }
+                            (Mode::Command, 32..=126) => {
+                                command_line.push(key as char);
+                                status.update(2, &command_status(&command_line));
+                            }
+                            (Mode::Normal, key) if key == keys.annotate => {
+                                mode = Mode::Note;
+                                status.update(2, &note_status(&note));
+                            }
+                            (Mode::Note, b'\r' | b'\n') => {
+                                mode = Mode::Normal;
+                                let note = std::mem::take(&mut note);
+                                let notice = match scrollback.lock().unwrap().annotate(&note) {
+                                    Some(line) => {
+                                        record(recording::Event::Note(note.clone()), None);
+                                        format!("Noted: {}", line)
+                                    }
+                                    None => "No line to attach the note to".to_string(),
+                                };
+                                status.update(2, &notice);
+                            }
+                            (Mode::Note, key) if key == keys.normal => {
+                                mode = Mode::Normal;
+                                note.clear();
+                                status.update(2, "Note discarded");
+                            }
+                            (Mode::Note, 8 | 127) => {
+                                note.pop();
+                                status.update(2, &note_status(&note));
+                            }
+                            (Mode::Note, 32..=126) => {
+                                note.push(key as char);
+                                status.update(2, &note_status(&note));
+                            }
+                            (Mode::Normal, key) if key == keys.replace => {
+                                mode = Mode::Replace;
+                                let current = substitution.lock().unwrap().clone();
+                                replace = current
+                                    .as_ref()
+                                    .map(|current| current.source.clone())
+                                    .unwrap_or_default();
+                                *proposed.lock().unwrap() = current;
+                                *view.lock().unwrap() = View::Replace;
+                                status.update(2, &replace_status(&replace, None));
                             }
-                            32..=126 => {
-                                // Printable ASCII
-                                // Add the character to filter_string
+                            (Mode::Replace, b'\r' | b'\n') => {
+                                let applied = match Substitution::parse(&replace) {
+                                    Ok(applied) => applied,
+                                    Err(e) => {
+                                        status.update(2, &replace_status(&replace, Some(&e)));
+                                        continue;
+                                    }
+                                };
+                                mode = Mode::Normal;
+                                let notice = match &applied {
+                                    Some(applied) => format!("Replacing {}", applied.source),
+                                    None => "Replacing nothing".to_string(),
+                                };
+                                *substitution.lock().unwrap() = applied;
+                                *proposed.lock().unwrap() = None;
+                                *view.lock().unwrap() = View::Lines;
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let _ = draw_panel(&mut term_out, &caps, &[], columns, height);
+                                status.update(2, &notice);
+                            }
+                            (Mode::Replace, key) if key == keys.normal => {
+                                mode = Mode::Normal;
+                                replace.clear();
+                                *proposed.lock().unwrap() = None;
+                                *view.lock().unwrap() = View::Lines;
+                                let (columns, rows) = terminal::size().unwrap_or((80, 24));
+                                let height = rows.saturating_sub(status.height());
+                                let _ = draw_panel(&mut term_out, &caps, &[], columns, height);
+                                status.update(2, "Replacement unchanged");
+                            }
+                            (Mode::Replace, 8 | 127 | 32..=126) => {
+                                if key == 8 || key == 127 {
+                                    replace.pop();
+                                } else {
+                                    replace.push(key as char);
+                                }
+                                let parsed = Substitution::parse(&replace);
+                                let error = parsed.as_ref().err().map(String::as_str);
+                                status.update(2, &replace_status(&replace, error));
+                                *proposed.lock().unwrap() = parsed.unwrap_or(None);
+                            }
+                            (Mode::Filter | Mode::Normal, key) if key == keys.quit => {
+                                // Still quit when 'q' is pressed
+                                writeln!(term_out, "Quitting...").unwrap_or(());
+                                let _ = quit_tx_term.send(());
+                                break;
+                            }
+                            (Mode::Filter | Mode::Search, key) if key == keys.search => {
+                                // Again while searching finds the next older match.
+                                let before = if mode == Mode::Search { found } else { None };
+                                if mode == Mode::Filter {
+                                    mode = Mode::Search;
+                                    query.clear();
+                                    found = None;
+                                }
+                                let result = history
+                                    .search(&query, before)
+                                    .or_else(|| history.search(&query, found.map(|f| f + 1)));
+                                found = result.map(|(index, _)| index);
+                                status.update(1, &search_status(&query, result.map(|(_, f)| f)));
+                            }
+                            (Mode::Search, b'\r' | b'\n') => {
+                                mode = Mode::Filter;
                                 let mut filter = filter_string_for_input.lock().unwrap();
-                                filter.push(buf[0] as char);
-                                // Update status bar with new filter
-                                let mut status = status_bar_for_thread.lock().unwrap();
-                                status.update(
-                                    1,
-                                    &format!("Filter [\x1B[37;101m{}\x1b[44m]", *filter),
-                                );
+                                if let Some(recalled) = found.and_then(|index| history.get(index)) {
+                                    if edit_filter(&mut editor, &mut filter, |e| e.set(recalled)) {
+                                        record(recording::Event::Filter(filter.clone()), None);
+                                    }
+                                }
+                                history.edited();
+                                status.update(1, &filter_status(&filter, !is_pipe, &config));
+                            }
+                            (Mode::Search, key) if key == keys.normal => {
+                                mode = Mode::Filter;
+                                let filter = filter_string_for_input.lock().unwrap();
+                                status.update(1, &filter_status(&filter, !is_pipe, &config));
+                            }
+                            (Mode::Search, 8 | 127 | 32..=126) => {
+                                if key == 8 || key == 127 {
+                                    query.pop();
+                                } else {
+                                    query.push(key as char);
+                                }
+                                let result = history.search(&query, None);
+                                found = result.map(|(index, _)| index);
+                                status.update(1, &search_status(&query, result.map(|(_, f)| f)));
+                            }
+                            (Mode::Filter, 32..=126) if vi_command => {
+                                let mut filter = filter_string_for_input.lock().unwrap();
+                                let mut action = editor::ViAction::Done;
+                                let edited = edit_filter(&mut editor, &mut filter, |e| {
+                                    action = vi.key(e, key);
+                                });
+                                if edited {
+                                    history.edited();
+                                }
+                                let recalled = match action {
+                                    editor::ViAction::Insert => {
+                                        vi_command = false;
+                                        status.update(2, "");
+                                        None
+                                    }
+                                    editor::ViAction::Older => history.previous(&filter.clone()),
+                                    editor::ViAction::Newer => history.next(),
+                                    editor::ViAction::Done | editor::ViAction::Unbound => None,
+                                };
+                                let recalled = recalled.is_some_and(|recalled| {
+                                    edit_filter(&mut editor, &mut filter, |e| {
+                                        e.set(recalled);
+                                        e.home();
+                                    })
+                                });
+                                if edited || recalled {
+                                    record(recording::Event::Filter(filter.clone()), None);
+                                }
+                                status.update(1, &editing_status(&editor, !is_pipe, &config));
+                            }
+                            (Mode::Filter, b'\r' | b'\n') => {
+                                history.commit(&filter_string_for_input.lock().unwrap());
+                                if committed.lock().unwrap().take().is_some() {
+                                    status.update(2, "Filter applied");
+                                }
+                            }
+                            (Mode::Normal, key) | (Mode::Filter, key)
+                                if (mode == Mode::Normal
+                                    && (key == keys.undo || key == keys.redo))
+                                    || (mode == Mode::Filter && key == 31) =>
+                            {
+                                let redo = mode == Mode::Normal && key == keys.redo;
+                                let mut filter = filter_string_for_input.lock().unwrap();
+                                let mut toggles = toggles.lock().unwrap();
+                                let current = setting(&filter, *toggles);
+                                let restored = match redo {
+                                    true => undo.redo(current),
+                                    false => undo.undo(current),
+                                };
+                                let Some((text, restored)) = restored else {
+                                    let what = if redo { "redo" } else { "undo" };
+                                    status.update(2, &format!("Nothing to {}", what));
+                                    continue;
+                                };
+                                if *filter != text {
+                                    *filter = text;
+                                    record(recording::Event::Filter(filter.clone()), None);
+                                }
+                                *toggles = Toggles {
+                                    paused: toggles.paused,
+                                    steps: toggles.steps,
+                                    ..restored
+                                };
+                                last_setting = setting(&filter, *toggles);
+                                let active = mode == Mode::Filter;
+                                status.update(1, &filter_status(&filter, active, &config));
+                                status.update(2, if redo { "Redone" } else { "Undone" });
+                            }
+                            (Mode::Filter, 1 | 2 | 5 | 6 | 11 | 25 | 8 | 127 | 32..=126) => {
+                                // Emacs keys move and kill, Backspace/Delete remove the
+                                // character before the cursor, printable ASCII is inserted.
+                                let mut filter = filter_string_for_input.lock().unwrap();
+                                let edited = edit_filter(&mut editor, &mut filter, |e| match key {
+                                    1 => e.home(),
+                                    2 => e.left(),
+                                    5 => e.end(),
+                                    6 => e.right(),
+                                    11 => e.kill_to_end(),
+                                    25 => e.yank(),
+                                    8 | 127 => e.backspace(),
+                                    _ => e.insert((key as char).encode_utf8(&mut [0; 4])),
+                                });
+                                if edited {
+                                    history.edited();
+                                    record(recording::Event::Filter(filter.clone()), None);
+                                }
+                                status.update(1, &editing_status(&editor, !is_pipe, &config));
                             }
                             _ => {} // Ignore other keys
                         }
                     }
-                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                        // No data available yet
-                        // thread::sleep(Duration::from_millis(10));
+                    Ok(Some(input::Input::Special(special))) => {
+                        let mut status = status_bar_for_thread.lock().unwrap();
+                        let config = config.lock().unwrap();
+                        // Function keys work in every mode, so toggles don't interrupt
+                        // editing.
+                        if let input::Special::F(number) = special {
+                            let toggle = config
+                                .function_keys
+                                .get(number as usize - 1)
+                                .copied()
+                                .flatten();
+                            if let Some(toggle) = toggle {
+                                let mut pretty = pretty.lock().unwrap();
+                                let on = toggles.lock().unwrap().flip(toggle, &mut pretty);
+                                let state = if on { "on" } else { "off" };
+                                status.update(2, &format!("{} {}", toggle.label(), state));
+                            }
+                            continue;
+                        }
+                        if mode != Mode::Filter {
+                            continue; // Not bound to anything yet
+                        }
+                        let mut filter = filter_string_for_input.lock().unwrap();
+                        let edited = match special {
+                            // Up and Down recall earlier filters.
+                            input::Special::Up | input::Special::Down => {
+                                let current = filter.clone();
+                                let recalled = match special {
+                                    input::Special::Up => history.previous(&current),
+                                    _ => history.next(),
+                                };
+                                match recalled {
+                                    Some(recalled) => {
+                                        edit_filter(&mut editor, &mut filter, |e| e.set(recalled))
+                                    }
+                                    None => false,
+                                }
+                            }
+                            input::Special::Left => {
+                                edit_filter(&mut editor, &mut filter, LineEditor::left)
+                            }
+                            input::Special::Right => {
+                                edit_filter(&mut editor, &mut filter, LineEditor::right)
+                            }
+                            input::Special::Home => {
+                                edit_filter(&mut editor, &mut filter, LineEditor::home)
+                            }
+                            input::Special::End => {
+                                edit_filter(&mut editor, &mut filter, LineEditor::end)
+                            }
+                            input::Special::Delete => {
+                                let edited =
+                                    edit_filter(&mut editor, &mut filter, LineEditor::delete);
+                                if edited {
+                                    history.edited();
+                                }
+                                edited
+                            }
+                            _ => false,
+                        };
+                        if edited {
+                            record(recording::Event::Filter(filter.clone()), None);
+                        }
+                        status.update(1, &editing_status(&editor, !is_pipe, &config));
+                    }
+                    Ok(Some(input::Input::Alt(key))) => {
+                        // Alt+B and Alt+F move by words while editing the filter.
+                        if mode == Mode::Filter && (key == b'b' || key == b'f') {
+                            let mut status = status_bar_for_thread.lock().unwrap();
+                            let config = config.lock().unwrap();
+                            let mut filter = filter_string_for_input.lock().unwrap();
+                            edit_filter(&mut editor, &mut filter, |e| match key {
+                                b'b' => e.word_left(),
+                                _ => e.word_right(),
+                            });
+                            status.update(1, &editing_status(&editor, !is_pipe, &config));
+                        }
+                    }
+                    Ok(None) => {
+                        // An unfinished chord is dropped after a while.
+                        if pending.is_some_and(|(_, at)| at.elapsed() >= CHORD_TIMEOUT) {
+                            pending = None;
+                            count = None;
+                            status_bar_for_thread.lock().unwrap().update(2, "");
+                        }
                     }
                     Err(_) => break, // Error reading, exit thread
-                    Ok(0) => break,  // End of file
-                    _ => {}          // Unexpected read size
                 }
             }
-
-            // Restore terminal attributes once before exiting
-            let _ = tcsetattr(fd, SetArg::TCSANOW, &orig_termios);
         });
     }
-    // Instead of polling on an atomic flag, block until a quit signal is received.
-    let _ = quit_rx.recv();
-    let _ = reset_scroll_region();
+    // Wait for a quit signal, waking up periodically to enforce the timeouts.
+    while let Err(mpsc::RecvTimeoutError::Timeout) =
+        quit_rx.recv_timeout(Duration::from_millis(100))
+    {
+        if options
+            .timeout
+            .is_some_and(|limit| started.elapsed() >= limit)
+        {
+            break;
+        }
+        if let Some(limit) = options.idle_timeout {
+            if last_input.lock().unwrap().elapsed() >= limit {
+                break;
+            }
+        }
+        if let Some(limit) = options.alert_idle {
+            let idle = last_input.lock().unwrap().elapsed();
+            if idle >= limit && !idle_alerted {
+                idle_alerted = true;
+                let message = format!("No input for {}", stats::format_duration(idle));
+                let _ = terminal::alert(&mut term_out, &message);
+                let notice = format!("\x1B[7m {} \x1B[27m", message);
+                status_bar.lock().unwrap().update(2, &notice);
+            } else if idle < limit && idle_alerted {
+                idle_alerted = false;
+                status_bar.lock().unwrap().update(2, "Input resumed");
+            }
+        }
+        if let Some(limit) = options.snapshot_idle {
+            let idle = last_input.lock().unwrap().elapsed();
+            let scrollback = scrollback.lock().unwrap();
+            if idle >= limit && scrollback.end() != snapshot_end {
+                snapshot_end = scrollback.end();
+                let notice = match scrollback.snapshot(Path::new(SNAPSHOT_FILE)) {
+                    Ok(count) => {
+                        format!("Snapshot of {} lines written to {}", count, SNAPSHOT_FILE)
+                    }
+                    Err(e) => format!("Snapshot failed: {}", e),
+                };
+                drop(scrollback);
+                status_bar.lock().unwrap().update(2, &notice);
+            }
+        }
+        if let Some(recorder) = &recorder {
+            recorder.lock().unwrap().flush()?;
+        }
+        if let Some(cast) = &cast {
+            cast.lock().unwrap().flush()?;
+        }
+        if let Some(cursor) = &cursor {
+            cursor.lock().unwrap().save()?;
+        }
+        if let Some(joiner) = &joiner {
+            // Sent with the joiner held, as the reader sends records, to keep their order.
+            let mut joiner = joiner.lock().unwrap();
+            if let Some(record) = joiner.flush_idle(Instant::now()) {
+                backlog.lock().unwrap().add();
+                tx_pipe.send_stdin(record);
+            }
+        }
+        let current_view = *view.lock().unwrap();
+        if options.fade {
+            // The status area saves the cursor too, so it is held while lines are repainted.
+            let status = status_bar.lock().unwrap();
+            let mut fresh = fresh.lock().unwrap();
+            if current_view == View::Lines {
+                let (columns, rows) = terminal::size()?;
+                let height = rows.saturating_sub(status.height()).saturating_sub(1);
+                let repaint = fresh.repaint(height as usize, columns as usize, Instant::now());
+                if !repaint.is_empty() {
+                    write!(term_out, "{}", repaint)?;
+                    term_out.flush()?;
+                }
+            } else {
+                fresh.forget();
+            }
+        }
+        if current_view != View::Lines {
+            let (columns, rows) = terminal::size()?;
+            let height = rows.saturating_sub(status_bar.lock().unwrap().height());
+            let panel = match current_view {
+                // Highlighted like the output, so the config is needed; the printer locks it
+                // before the stats, so the stats are not held here.
+                View::Scrollback
+                | View::Replace
+                | View::Sources
+                | View::Patterns
+                | View::Captures
+                | View::Watch => {
+                    let config = config.lock().unwrap();
+                    let filter = filter_string.lock().unwrap();
+                    let toggles = *toggles.lock().unwrap();
+                    let patterns = patterns.lock().unwrap();
+                    let matcher = Matcher::new(&filter, toggles.matching)
+                        .with_terms(&patterns.active_terms());
+                    match &watch {
+                        Some(watch) if current_view == View::Watch => watch_panel(
+                            &watch.lock().unwrap(),
+                            &matcher,
+                            toggles.hide,
+                            &config,
+                            &patterns,
+                        ),
+                        _ if current_view == View::Sources => sources.lock().unwrap().panel(),
+                        _ if current_view == View::Patterns => patterns.panel(&config.patterns),
+                        _ if current_view == View::Captures => {
+                            captures.lock().unwrap().panel(&filter, height as usize)
+                        }
+                        _ if current_view == View::Replace => replace_panel(
+                            &scrollback.lock().unwrap(),
+                            proposed.lock().unwrap().as_ref(),
+                            height as usize,
+                        ),
+                        _ => scrollback_panel(
+                            &scrollback.lock().unwrap(),
+                            &mut browse.lock().unwrap(),
+                            height as usize,
+                            &matcher,
+                            &config,
+                            &patterns,
+                        ),
+                    }
+                }
+                _ => {
+                    let mut stats = stats.lock().unwrap();
+                    match current_view {
+                        View::Histogram(resolution) => stats.histogram.render(
+                            resolution,
+                            Instant::now(),
+                            columns as usize,
+                            height as usize,
+                        ),
+                        View::TopK => stats.topk.render(height as usize),
+                        View::Table => records.lock().unwrap().panel(height as usize),
+                        _ => stats.table(Instant::now()),
+                    }
+                }
+            };
+            draw_panel(&mut term_out, &caps, &panel, columns, height)?;
+        }
+        if let (Some(watch), cli::Command::Watch(command)) = (&watch, &options.command) {
+            let runs = watch.lock().unwrap().runs;
+            if runs != watch_runs {
+                watch_runs = runs;
+                let interval = options.interval.unwrap_or(watch::DEFAULT_INTERVAL);
+                let every = stats::format_duration(interval);
+                let line = format!("Every {}: {}  (run {})", every, command, runs);
+                status_bar.lock().unwrap().update(0, &line);
+            }
+        }
+        let mut status = status_bar.lock().unwrap();
+        if last_progress.elapsed() >= Duration::from_millis(500) {
+            // Live widgets share a line: the top one in pipe mode, the notice line otherwise.
+            let mut widgets = Vec::new();
+            if options.progress && is_pipe {
+                widgets.push(progress.lock().unwrap().status(Instant::now()));
+            }
+            if let Some(spec) = &options.field {
+                widgets.push(format!(
+                    "{}: {}",
+                    spec,
+                    stats.lock().unwrap().field.summary()
+                ));
+            }
+            if let Some(spec) = &options.distinct {
+                widgets.push(format!(
+                    "distinct {}: {}",
+                    spec,
+                    stats.lock().unwrap().distinct.summary()
+                ));
+            }
+            if let Some(spans) = &stats.lock().unwrap().spans {
+                widgets.push(spans.summary());
+            }
+            if let Some(meter) = meter.as_ref().filter(|_| options.meter) {
+                widgets.push(meter.lock().unwrap().status(Instant::now()));
+            }
+            if let Some(meter) = meter.as_ref().filter(|_| options.totals) {
+                widgets.push(meter.lock().unwrap().totals(Instant::now()));
+            }
+            if options.buffer.is_some() {
+                widgets.push(backlog.lock().unwrap().status(Instant::now()));
+            }
+            if options.level_counts {
+                let only = *only_level.lock().unwrap();
+                widgets.push(stats.lock().unwrap().levels.widget(only));
+            }
+            if !widgets.is_empty() {
+                status.update(if is_pipe { 0 } else { 2 }, &widgets.join("  |  "));
+            }
+            if options.title {
+                let filter = filter_string.lock().unwrap();
+                let current = window_title(&filter, &stats.lock().unwrap());
+                if current != title {
+                    let _ = terminal::set_title(&mut term_out, &current, title.is_empty());
+                    title = current;
+                }
+            }
+            last_progress = Instant::now();
+        }
+        // A mirror attaching starts blank, so it needs the status area and scroll region.
+        let joined = share
+            .as_ref()
+            .is_some_and(|share| share.lock().unwrap().take_joined());
+        if joined || (multiplexer.is_some() && last_repaint.elapsed() >= Duration::from_secs(1)) {
+            status.redraw();
+            last_repaint = Instant::now();
+        } else if options.low_bandwidth {
+            status.flush();
+        }
+    }
+    if caps.scroll_region {
+        let _ = terminal::reset_scroll_region(&mut term_out);
+    }
+    if let Some((_, exit)) = &alt_screen {
+        let _ = write!(term_out, "{}", exit);
+    }
+    let _ = terminal::set_bracketed_paste(&mut term_out, false);
+    if !title.is_empty() {
+        let _ = terminal::restore_title(&mut term_out);
+    }
+    let mut summary = Vec::new();
+    if options.progress && is_pipe {
+        summary.push(progress.lock().unwrap().status(Instant::now()));
+    }
+    if let Some(meter) = meter.as_ref().filter(|_| options.totals) {
+        let totals = meter.lock().unwrap().totals(Instant::now());
+        summary.push(format!("pipe_tools: {}", totals));
+    }
+    if !summary.is_empty() {
+        // Resetting the scroll region sent the cursor home; go below the status area.
+        if alt_screen.is_none() {
+            let (_, rows) = terminal::size().unwrap_or((80, 24));
+            let _ = writeln!(term_out, "\x1B[{};1H", rows);
+        }
+        for line in summary {
+            let _ = writeln!(term_out, "{}", line);
+        }
+    }
+    drop(raw_mode);
+    if let Some(recorder) = &recorder {
+        recorder.lock().unwrap().flush()?;
+    }
+    if let Some(cast) = &cast {
+        cast.lock().unwrap().flush()?;
+    }
+    if let Some(cursor) = &cursor {
+        cursor.lock().unwrap().save()?;
+    }
+    if let Some(path) = &options.share {
+        let _ = std::fs::remove_file(path);
+    }
+    if let Some(path) = &options.session {
+        let toggles = toggles.lock().unwrap();
+        let patterns = patterns.lock().unwrap();
+        let session = session::Session {
+            filter: filter_string.lock().unwrap().clone(),
+            matching: toggles.matching,
+            hide: toggles.hide,
+            terms: patterns.terms.clone(),
+            off: patterns.switched_off(),
+            level: *min_level.lock().unwrap(),
+            pretty: *pretty.lock().unwrap(),
+            view: view.lock().unwrap().name().to_string(),
+        };
+        session.save(path)?;
+    }
+    if let Some(path) = &options.stats_json {
+        let summary = stats.lock().unwrap().summary(Instant::now());
+        write_stats_json(path, &summary)?;
+    }
+    if let Some(hash) = &digest {
+        print_checksum(&hash.lock().unwrap());
+    }
     Ok(())
 }
 #[cfg(test)]
@@ -291,14 +3534,85 @@ mod tests {
     fn test_highlight_word_found() {
         let input = "this is a stream of data";
         let expected = format!("this is a {} of data", "\x1B[37;101mstream\x1B[0m");
-        let result = highlight_word_in_string(input, "stream");
+        let result =
+            Matcher::new("stream", MatchOptions::default()).highlight(input, "37;101", Vec::new());
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_window_title() {
+        let mut stats = Stats::new(Instant::now());
+        let options = MatchOptions {
+            regex: true,
+            ignore_case: true,
+            ..MatchOptions::default()
+        };
+        let counters = counters("err(or)?", &[], options);
+        for line in ["ERROR a", "ok", "err b"] {
+            stats.record(line, &counted(line, &counters, &[]), Instant::now());
+        }
+        assert_eq!(
+            window_title("err(or)?", &stats),
+            "pipe_tools: 2 err(or)? matches"
+        );
+        assert_eq!(window_title("", &stats), "pipe_tools: 3 lines");
+    }
+
+    #[test]
+    fn test_count_with_presets() {
+        let config =
+            Config::parse("[preset a]\nfilter = a\n\n[preset b]\nfilter = b\n", None).unwrap();
+        // Digits still make counts for moves such as 20j; one before # picks a preset.
+        let count = typed_count(Some(typed_count(None, b'2')), b'0');
+        assert_eq!(count, 20);
+        assert_eq!(typed_count(Some(MAX_COUNT), b'9'), MAX_COUNT);
+        assert_ne!(config.keys.preset, b'2');
+        assert_eq!(config.preset_number("a", Some(2)), 2);
+        assert_eq!(config.preset_number("a", None), 2);
+        assert_eq!(config.preset_number("b", None), 1);
+    }
+
+    #[test]
+    fn test_counted_with_options() {
+        let terms = [("timeout".to_string(), "33".to_string())];
+        let smart_case = MatchOptions {
+            smart_case: true,
+            ..MatchOptions::default()
+        };
+        let configured = [("WARN".to_string(), "33".to_string())];
+        let counters = counters("error", &terms, smart_case);
+        assert_eq!(
+            counted("Error: Timeout, warn", &counters, &configured),
+            [("error", true), ("timeout", true), ("WARN", false)]
+        );
+    }
+
+    #[test]
+    fn test_copy_plain_head() {
+        let copied = |head: Option<usize>| {
+            let (tx, rx) = mpsc::channel();
+            for line in ["a", "b", "c"] {
+                tx.send(line.to_string()).unwrap();
+            }
+            drop(tx);
+            let options = cli::Options {
+                head,
+                ..cli::Options::default()
+            };
+            let mut out = Vec::new();
+            copy_plain(&rx, &mut out, &options, Instant::now()).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(copied(None), "a\nb\nc\n");
+        assert_eq!(copied(Some(2)), "a\nb\n");
+        assert_eq!(copied(Some(0)), "");
+    }
+
     #[test]
     fn test_highlight_word_not_found() {
         let input = "no match here";
-        let result = highlight_word_in_string(input, "stream");
+        let result =
+            Matcher::new("stream", MatchOptions::default()).highlight(input, "37;101", Vec::new());
         assert_eq!(result, "no match here");
     }
 
@@ -316,10 +3630,8 @@ mod tests {
 
         // Simulated pipe reader thread.
         thread::spawn(move || {
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    let _ = tx_pipe.send(line);
-                }
+            for line in reader.lines().map_while(Result::ok) {
+                let _ = tx_pipe.send(line);
             }
         });
 
@@ -327,7 +3639,8 @@ mod tests {
         let filter_word = "stream";
         thread::spawn(move || {
             for line in rx_pipe {
-                let highlighted_line = highlight_word_in_string(&line, filter_word);
+                let highlighted_line = Matcher::new(filter_word, MatchOptions::default())
+                    .highlight(&line, "37;101", Vec::new());
                 let _ = tx_out.send(highlighted_line);
             }
         });
@@ -344,7 +3657,7 @@ mod tests {
         let expected = vec![
             "line1".to_string(),
             "line2".to_string(),
-            format!("{}{}{}", "s", "\x1B[37;101mstream\x1B[0m", ""),
+            "\x1B[37;101mstream\x1B[0m".to_string(),
             "q".to_string(),
         ];
         assert_eq!(outputs, expected);
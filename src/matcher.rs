@@ -0,0 +1,417 @@
+//! Matching the filter against lines, as literal text, as a regex or fuzzily, along with any terms
+//! pinned beside it. A filter or term starting with `!` excludes the lines it matches
+//! instead; `\!` starts one with a literal `!`. Either may be a boolean expression of
+//! terms, as parsed by `expr`. Terms of plain text are all found in one pass, by `words`.
+
+use crate::expr::{self, Expr};
+use crate::words::Words;
+use regex::RegexBuilder;
+use std::ops::Range;
+
+/// How the filter is interpreted; each is toggled from the keyboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MatchOptions {
+    /// The filter is a regular expression rather than literal text.
+    pub regex: bool,
+    /// The filter's characters match in order with anything between them, as in fzf, so
+    /// `cnterr` matches `connection_error`.
+    pub fuzzy: bool,
+    pub ignore_case: bool,
+    /// Ignore case unless the filter, or a term of an expression, has a capital letter
+    /// in it.
+    pub smart_case: bool,
+    /// Lines that do not contain the filter are the matching ones.
+    pub invert: bool,
+    /// The filter and terms only match as whole words, not inside longer ones.
+    pub whole_word: bool,
+}
+
+/// The filter compiled with its options.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    filter: String,
+    options: MatchOptions,
+    /// `None` for an empty filter, which matches every line.
+    regex: Option<Expr>,
+    /// For a `!` filter, what lines must not have.
+    exclude: Option<Expr>,
+    /// Terms pinned from earlier filters with their SGR styles; a line matches if it has
+    /// the filter or any of them.
+    terms: Vec<(String, String)>,
+    /// Terms that are regexes, expressions or exclusions, with their styles.
+    term_matchers: Vec<(Matcher, String)>,
+    /// Terms of plain text, case-sensitive ones apart from the others.
+    term_words: Vec<Words>,
+}
+
+/// A regex for the characters of `term` in order, each in a group and matched as soon as
+/// it can be after the one before; spaces are left out.
+fn fuzzy_pattern(term: &str) -> String {
+    term.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| format!("({})", regex::escape(c.encode_utf8(&mut [0; 4]))))
+        .collect::<Vec<_>>()
+        .join(".*?")
+}
+
+/// Styles given to pinned terms in turn.
+pub const TERM_STYLES: [&str; 6] = ["30;103", "30;102", "30;106", "30;105", "30;104", "30;43"];
+
+impl Matcher {
+    /// An invalid regex, as while one is still being typed, is matched as literal text.
+    pub fn new(filter: &str, options: MatchOptions) -> Matcher {
+        let build = |pattern: &str, term: &str| {
+            let pattern = match options.whole_word {
+                true => format!(r"\b{{start-half}}(?:{})\b{{end-half}}", pattern),
+                false => pattern.to_string(),
+            };
+            RegexBuilder::new(&pattern)
+                .case_insensitive(
+                    options.ignore_case
+                        || (options.smart_case && !term.chars().any(char::is_uppercase)),
+                )
+                .build()
+        };
+        let compile = |term: &str| match (options.fuzzy, options.regex) {
+            (true, _) => build(&fuzzy_pattern(term), term).ok().map(Expr::Fuzzy),
+            (false, true) => build(term, term)
+                .or_else(|_| build(&regex::escape(term), term))
+                .ok()
+                .map(Expr::Term),
+            (false, false) => build(&regex::escape(term), term).ok().map(Expr::Term),
+        };
+        let (pattern, excluding) = match filter.strip_prefix('!') {
+            Some(rest) => (rest, true),
+            None => (
+                filter
+                    .strip_prefix('\\')
+                    .filter(|rest| rest.starts_with('!'))
+                    .unwrap_or(filter),
+                false,
+            ),
+        };
+        let compiled = match pattern {
+            "" => None,
+            _ => expr::parse(pattern, options.regex, compile).or_else(|| compile(pattern)),
+        };
+        let (regex, exclude) = match excluding {
+            true => (None, compiled),
+            false => (compiled, None),
+        };
+        Matcher {
+            filter: filter.to_string(),
+            options,
+            regex,
+            exclude,
+            terms: Vec::new(),
+            term_matchers: Vec::new(),
+            term_words: Vec::new(),
+        }
+    }
+
+    /// This matcher also matching each of `terms`, highlighted in its style.
+    pub fn with_terms(mut self, terms: &[(String, String)]) -> Matcher {
+        let options = MatchOptions {
+            invert: false,
+            ..self.options
+        };
+        let mut words = [Vec::new(), Vec::new()];
+        for (term, style) in terms {
+            let ignore_case = options.ignore_case
+                || (options.smart_case && !term.chars().any(char::is_uppercase));
+            // Only ASCII letters are folded in one pass, so other terms ignoring case are
+            // left to the regex engine.
+            let plain = !options.regex
+                && !options.fuzzy
+                && !options.whole_word
+                && !term.starts_with(['!', '\\'])
+                && (term.is_ascii() || !ignore_case)
+                && expr::parse(term, false, |_| None).is_none();
+            match plain {
+                true => words[ignore_case as usize].push((term.clone(), style.clone())),
+                false => self
+                    .term_matchers
+                    .push((Matcher::new(term, options), style.clone())),
+            }
+        }
+        self.term_words = words
+            .iter()
+            .enumerate()
+            .map(|(ignore_case, words)| Words::new(words, ignore_case == 1))
+            .filter(|words| !words.is_empty())
+            .collect();
+        self.terms = terms.to_vec();
+        self
+    }
+
+    /// Whether this was built with `terms`.
+    pub fn has_terms(&self, terms: &[(String, String)]) -> bool {
+        self.terms == terms
+    }
+
+    /// Whether there is neither a filter nor any term for lines to have, so every line
+    /// not excluded matches.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regex.is_none()
+            && self
+                .term_matchers
+                .iter()
+                .all(|(term, _)| term.regex.is_none())
+            && self.term_words.is_empty()
+    }
+
+    fn finds(&self, line: &str) -> bool {
+        self.regex.as_ref().is_some_and(|expr| expr.is_match(line))
+    }
+
+    /// Whether a `!` filter or term rules `line` out, whatever else it matches.
+    pub fn is_excluded(&self, line: &str) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|expr| expr.is_match(line))
+            || self
+                .term_matchers
+                .iter()
+                .any(|(term, _)| term.is_excluded(line))
+    }
+
+    /// Whether this was built from `filter` and `options`, so it need not be rebuilt.
+    pub fn is_for(&self, filter: &str, options: MatchOptions) -> bool {
+        self.filter == filter && self.options == options
+    }
+
+    pub fn is_match(&self, line: &str) -> bool {
+        if self.is_excluded(line) {
+            return false;
+        }
+        if self.is_empty() {
+            return true;
+        }
+        let found = self.finds(line)
+            || self.term_words.iter().any(|words| words.is_match(line))
+            || self.term_matchers.iter().any(|(term, _)| term.finds(line));
+        found != self.options.invert
+    }
+
+    /// What groups in the filter captured in `line`, as for `Expr::captures`.
+    pub fn captures<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match &self.regex {
+            Some(expr) if !self.options.invert => expr.captures(line),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Byte ranges of the filter in `text` to highlight; none when inverted, since the
+    /// lines shown are the ones without it.
+    pub fn ranges(&self, text: &str) -> Vec<Range<usize>> {
+        self.styled_ranges(text, "", Vec::new())
+            .into_iter()
+            .map(|(range, _)| range)
+            .collect()
+    }
+
+    /// Ranges of the filter with `style`, of each term with its own and then of `also`, in
+    /// order and without overlaps; the filter wins where they overlap, then the terms.
+    fn styled_ranges<'a>(
+        &'a self,
+        text: &str,
+        style: &'a str,
+        also: Vec<(Range<usize>, &'a str)>,
+    ) -> Vec<(Range<usize>, &'a str)> {
+        let mut ranges: Vec<_> = match self.options.invert {
+            true => Vec::new(),
+            false => self
+                .regex
+                .iter()
+                .flat_map(|expr| expr.ranges(text).into_iter().map(|range| (range, style)))
+                .collect(),
+        };
+        if !self.options.invert {
+            for (term, style) in &self.term_matchers {
+                ranges.extend(
+                    term.ranges(text)
+                        .into_iter()
+                        .map(|range| (range, style.as_str())),
+                );
+            }
+            for words in &self.term_words {
+                ranges.extend(words.ranges(text));
+            }
+        }
+        ranges.extend(also);
+        ranges.retain(|(range, _)| !range.is_empty());
+        // Stable, so the filter's ranges come before a term's starting at the same place.
+        ranges.sort_by_key(|(range, _)| range.start);
+        let mut end = 0;
+        ranges.retain(|(range, _)| {
+            let keep = range.start >= end;
+            if keep {
+                end = range.end;
+            }
+            keep
+        });
+        ranges
+    }
+
+    /// `text` with every match of the filter wrapped in the SGR `style`, of each term in
+    /// its own and of the ranges of `also`, such as configured patterns, in theirs where
+    /// neither the filter nor a term is.
+    pub fn highlight(&self, text: &str, style: &str, also: Vec<(Range<usize>, &str)>) -> String {
+        let mut highlighted = String::new();
+        let mut end = 0;
+        for (range, style) in self.styled_ranges(text, style, also) {
+            highlighted.push_str(&text[end..range.start]);
+            highlighted.push_str(&format!("\x1B[{}m{}\x1B[0m", style, &text[range.clone()]));
+            end = range.end;
+        }
+        highlighted.push_str(&text[end..]);
+        highlighted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(regex: bool, ignore_case: bool, invert: bool) -> MatchOptions {
+        MatchOptions {
+            regex,
+            ignore_case,
+            invert,
+            ..MatchOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_literal_and_regex() {
+        let literal = Matcher::new("a.c", MatchOptions::default());
+        assert!(literal.is_match("xa.cx"));
+        assert!(!literal.is_match("abc"));
+        let regex = Matcher::new("a.c", options(true, false, false));
+        assert!(regex.is_match("abc"));
+        assert_eq!(regex.ranges("abc adc"), vec![0..3, 4..7]);
+        // Half-typed regexes still match literally.
+        assert!(Matcher::new("f(", options(true, false, false)).is_match("f(x)"));
+        assert!(Matcher::new("", options(true, true, true)).is_match("anything"));
+    }
+
+    #[test]
+    fn test_case_and_invert() {
+        let matcher = Matcher::new("error", options(false, true, false));
+        assert!(matcher.is_match("ERROR: disk"));
+        assert_eq!(
+            matcher.highlight("an Error", "31", Vec::new()),
+            "an \x1B[31mError\x1B[0m"
+        );
+        let inverted = Matcher::new("error", options(false, false, true));
+        assert!(!inverted.is_match("an error"));
+        assert!(inverted.is_match("all good"));
+        assert_eq!(inverted.highlight("an error", "31", Vec::new()), "an error");
+        assert!(inverted.is_for("error", options(false, false, true)));
+        let smart = MatchOptions {
+            smart_case: true,
+            ..MatchOptions::default()
+        };
+        assert!(Matcher::new("error", smart).is_match("ERROR: disk"));
+        assert!(!Matcher::new("Error", smart).is_match("ERROR: disk"));
+        assert!(Matcher::new("Error", smart).is_match("an Error"));
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let whole = MatchOptions {
+            whole_word: true,
+            ..MatchOptions::default()
+        };
+        let matcher = Matcher::new("err", whole);
+        assert!(matcher.is_match("err: disk"));
+        assert!(!matcher.is_match("transferred 3 files"));
+        assert_eq!(matcher.ranges("an err, err2 and (err)"), vec![3..6, 18..21]);
+        assert!(Matcher::new("-v", whole).is_match("run -v now"));
+        let regex = MatchOptions {
+            regex: true,
+            ..whole
+        };
+        assert!(Matcher::new("err|warn", regex).is_match("a warn"));
+        assert!(!Matcher::new("err|warn", regex).is_match("warning"));
+        let terms = vec![("fail".to_string(), "33".to_string())];
+        let terms_only = Matcher::new("", whole).with_terms(&terms);
+        assert!(!terms_only.is_match("failed"));
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let fuzzy = MatchOptions {
+            fuzzy: true,
+            ..MatchOptions::default()
+        };
+        let matcher = Matcher::new("cnterr", fuzzy);
+        assert!(matcher.is_match("connection_error"));
+        assert!(!matcher.is_match("error connecting"));
+        assert_eq!(
+            matcher.highlight("a connection_error", "31", Vec::new()),
+            "a \x1B[31mc\x1B[0mo\x1B[31mn\x1B[0mnec\x1B[31mt\x1B[0mion_\x1B[31me\x1B[0m\
+             \x1B[31mr\x1B[0m\x1B[31mr\x1B[0mor"
+        );
+        assert!(Matcher::new("a.b OR zz", fuzzy).is_match("a-x.yb"));
+    }
+
+    #[test]
+    fn test_terms() {
+        let terms = vec![
+            ("timeout".to_string(), "33".to_string()),
+            ("retry".to_string(), "32".to_string()),
+        ];
+        let matcher = Matcher::new("error", MatchOptions::default()).with_terms(&terms);
+        assert!(matcher.is_match("retry 3"));
+        assert!(!matcher.is_match("ok"));
+        assert_eq!(
+            matcher.highlight("error: timeout", "31", Vec::new()),
+            "\x1B[31merror\x1B[0m: \x1B[33mtimeout\x1B[0m"
+        );
+        let terms_only = Matcher::new("", MatchOptions::default()).with_terms(&terms);
+        assert!(!terms_only.is_empty());
+        assert!(!terms_only.is_match("error"));
+        assert!(terms_only.has_terms(&terms));
+        let mixed = vec![
+            ("Retry".to_string(), "32".to_string()),
+            ("time.?out".to_string(), "33".to_string()),
+            ("warn OR slow".to_string(), "34".to_string()),
+        ];
+        let smart = MatchOptions {
+            smart_case: true,
+            ..MatchOptions::default()
+        };
+        let matcher = Matcher::new("", smart).with_terms(&mixed);
+        assert!(matcher.is_match("SLOW disk"));
+        assert!(!matcher.is_match("retry 3"));
+        assert_eq!(
+            matcher.highlight("Retry time.?out", "31", Vec::new()),
+            "\x1B[32mRetry\x1B[0m \x1B[33mtime.?out\x1B[0m"
+        );
+    }
+
+    #[test]
+    fn test_exclude() {
+        let excluding = Matcher::new("!health", MatchOptions::default());
+        assert!(excluding.is_empty());
+        assert!(excluding.is_excluded("GET /health 200"));
+        assert!(!excluding.is_match("GET /health 200"));
+        assert!(excluding.is_match("GET /users 200"));
+        assert_eq!(
+            excluding.highlight("GET /users", "31", Vec::new()),
+            "GET /users"
+        );
+        let terms = vec![("!debug".to_string(), "33".to_string())];
+        let matcher = Matcher::new("GET", MatchOptions::default()).with_terms(&terms);
+        assert!(!matcher.is_match("debug GET /"));
+        assert!(matcher.is_match("GET /"));
+        let literal = Matcher::new("\\!important", MatchOptions::default());
+        assert!(literal.is_match("!important: disk"));
+        assert!(!literal.is_match("disk"));
+    }
+}
@@ -0,0 +1,132 @@
+//! Prometheus text-format metrics served over HTTP, for long-running relay use.
+
+use crate::progress::Progress;
+use crate::stats::Stats;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// A client this slow to send its request is given up on, so it can't hold up the others.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve `/metrics` on `addr` from a background thread.
+pub fn serve(
+    addr: &str,
+    stats: Arc<Mutex<Stats>>,
+    progress: Arc<Mutex<Progress>>,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    thread::spawn(move || {
+        // A failed accept (a client that hung up first, or running out of file
+        // descriptors) only loses that connection.
+        for stream in listener.incoming().filter_map(Result::ok) {
+            if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+                continue;
+            }
+            let body = render(&stats.lock().unwrap(), &progress.lock().unwrap());
+            let _ = respond(stream, &body);
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, body: &str) -> io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+
+    if path == "/metrics" {
+        write!(
+            stream,
+            "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        write!(
+            stream,
+            "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n"
+        )
+    }
+}
+
+/// The metrics in the Prometheus text exposition format.
+pub fn render(stats: &Stats, progress: &Progress) -> String {
+    let mut out = String::new();
+    let mut counter = |name: &str, help: &str, samples: &[(String, u64)]| {
+        out.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} counter\n",
+            name, help, name
+        ));
+        for (labels, value) in samples {
+            out.push_str(&format!("{}{} {}\n", name, labels, value));
+        }
+    };
+
+    counter(
+        "pipe_tools_input_lines_total",
+        "Lines read from the input.",
+        &[(String::new(), progress.lines)],
+    );
+    counter(
+        "pipe_tools_input_bytes_total",
+        "Bytes read from the input.",
+        &[(String::new(), progress.bytes)],
+    );
+    counter(
+        "pipe_tools_lines_processed_total",
+        "Lines passed through the filter.",
+        &[(String::new(), stats.lines)],
+    );
+    counter(
+        "pipe_tools_lines_dropped_total",
+        "Lines dropped by --max-rate or that could not be written to the output.",
+        &[(String::new(), stats.dropped)],
+    );
+    let matches: Vec<(String, u64)> = stats
+        .patterns
+        .iter()
+        .map(|p| {
+            (
+                format!("{{pattern=\"{}\"}}", escape_label(&p.pattern)),
+                p.count,
+            )
+        })
+        .collect();
+    counter(
+        "pipe_tools_pattern_matches_total",
+        "Lines matching each pattern.",
+        &matches,
+    );
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_render_metrics() {
+        let now = Instant::now();
+        let mut stats = Stats::new(now);
+        stats.record("say \"hi\"", &[("\"hi\"", true)], now);
+        let mut progress = Progress::new(now, None);
+        progress.record(9, now);
+
+        let text = render(&stats, &progress);
+        assert!(text.contains("# TYPE pipe_tools_input_lines_total counter\n"));
+        assert!(text.contains("pipe_tools_input_bytes_total 9\n"));
+        assert!(text.contains("pipe_tools_lines_dropped_total 0\n"));
+        assert!(text.contains("pipe_tools_pattern_matches_total{pattern=\"\\\"hi\\\"\"} 1\n"));
+    }
+}
@@ -0,0 +1,144 @@
+//! `--share` and `mirror`: a second terminal attached over a Unix socket sees everything
+//! drawn, read-only, such as a teammate pairing on an incident.
+
+use std::io::Write;
+
+/// Sent to a mirror as it attaches, so it starts from a clean screen.
+#[cfg(any(unix, test))]
+const CLEAR: &[u8] = b"\x1B[2J\x1B[H";
+
+#[derive(Default)]
+pub struct Mirror {
+    clients: Vec<Box<dyn Write + Send>>,
+    /// Set when a mirror attaches, until the status area has been drawn again for it.
+    joined: bool,
+}
+
+impl Mirror {
+    /// Only Unix sockets take mirrors.
+    #[cfg(any(unix, test))]
+    pub fn attach(&mut self, mut client: Box<dyn Write + Send>) {
+        if client.write_all(CLEAR).is_ok() {
+            self.clients.push(client);
+            self.joined = true;
+        }
+    }
+
+    /// Pass bytes written to the terminal on, dropping mirrors that went away or fell
+    /// too far behind.
+    pub fn output(&mut self, data: &[u8]) {
+        self.clients
+            .retain_mut(|client| client.write_all(data).is_ok());
+    }
+
+    pub fn take_joined(&mut self) -> bool {
+        std::mem::take(&mut self.joined)
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::Mirror;
+    use std::fs;
+    use std::io::{self, Write};
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A mirror this slow to take output is dropped rather than holding up the session.
+    const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+
+    /// Listen on `path`, replacing a socket left behind by an earlier run.
+    pub fn serve(path: &Path) -> io::Result<Arc<Mutex<Mirror>>> {
+        if fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        let mirror = Arc::new(Mutex::new(Mirror::default()));
+        let shared = mirror.clone();
+        thread::spawn(move || {
+            // A failed accept only loses that mirror; later ones can still attach.
+            for stream in listener.incoming().filter_map(Result::ok) {
+                if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok() {
+                    shared.lock().unwrap().attach(Box::new(stream));
+                }
+            }
+        });
+        Ok(mirror)
+    }
+
+    /// Copy what the session at `path` draws to stdout until it ends.
+    pub fn watch(path: &Path) -> io::Result<()> {
+        let mut stream = UnixStream::connect(path)?;
+        let mut out = io::stdout().lock();
+        io::copy(&mut stream, &mut out)?;
+        // Leave the terminal as the session's own exit would.
+        writeln!(out, "\x1B[r\x1B[0m")?;
+        out.flush()
+    }
+}
+
+#[cfg(unix)]
+pub use unix::*;
+
+#[cfg(windows)]
+mod windows {
+    use super::Mirror;
+    use std::io;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    pub fn serve(_path: &Path) -> io::Result<Arc<Mutex<Mirror>>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "sharing needs Unix sockets",
+        ))
+    }
+
+    pub fn watch(_path: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "mirroring needs Unix sockets",
+        ))
+    }
+}
+
+#[cfg(windows)]
+pub use windows::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// A client writer whose bytes the test can read back.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mirrors_output() {
+        let mut mirror = Mirror::default();
+        mirror.output(b"before");
+        let client = Shared::default();
+        mirror.attach(Box::new(client.clone()));
+        assert!(mirror.take_joined());
+        assert!(!mirror.take_joined());
+        mirror.output(b"line\n");
+        assert_eq!(*client.0.lock().unwrap(), b"\x1B[2J\x1B[Hline\n");
+    }
+}
@@ -0,0 +1,166 @@
+//! Terms kept beside the filter, and which of them and of the config's patterns are
+//! switched off from the patterns overlay without being removed.
+
+use crate::words::Words;
+use std::collections::HashSet;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Default)]
+pub struct Patterns {
+    /// Terms kept from earlier filters or given with --term and --exclude, with their SGR
+    /// styles, oldest first.
+    pub terms: Vec<(String, String)>,
+    /// Terms and configured patterns switched off, by their text.
+    off: HashSet<String>,
+    /// The line selected in the overlay: terms first, then configured patterns.
+    pub selected: usize,
+    /// The configured patterns switched on, for highlighting.
+    words: Words,
+}
+
+impl Patterns {
+    pub fn new(terms: Vec<(String, String)>, configured: &[(String, String)]) -> Patterns {
+        let mut patterns = Patterns {
+            terms,
+            ..Patterns::default()
+        };
+        patterns.configure(configured);
+        patterns
+    }
+
+    /// Take the configured patterns anew, as when the config is reloaded.
+    pub fn configure(&mut self, configured: &[(String, String)]) {
+        let on: Vec<_> = configured
+            .iter()
+            .filter(|(pattern, _)| self.is_on(pattern))
+            .cloned()
+            .collect();
+        self.words = Words::new(&on, false);
+    }
+
+    /// Where the configured patterns switched on are in `text`, with their styles.
+    pub fn ranges(&self, text: &str) -> Vec<(Range<usize>, &str)> {
+        self.words.ranges(text)
+    }
+
+    pub fn is_on(&self, pattern: &str) -> bool {
+        !self.off.contains(pattern)
+    }
+
+    /// The terms and configured patterns switched off, in order.
+    pub fn switched_off(&self) -> Vec<String> {
+        let mut off: Vec<String> = self.off.iter().cloned().collect();
+        off.sort();
+        off
+    }
+
+    /// Switch `patterns` off, as when a session is restored.
+    pub fn switch_off(&mut self, patterns: &[String], configured: &[(String, String)]) {
+        self.off.extend(patterns.iter().cloned());
+        self.configure(configured);
+    }
+
+    /// The terms switched on, for matching.
+    pub fn active_terms(&self) -> Vec<(String, String)> {
+        self.terms
+            .iter()
+            .filter(|(term, _)| self.is_on(term))
+            .cloned()
+            .collect()
+    }
+
+    /// Drop the last term kept, returning it.
+    pub fn drop_term(&mut self) -> Option<String> {
+        let (term, _) = self.terms.pop()?;
+        self.off.remove(&term);
+        Some(term)
+    }
+
+    pub fn select(&mut self, down: bool, configured: &[(String, String)]) {
+        let last = (self.terms.len() + configured.len()).saturating_sub(1);
+        self.selected = match down {
+            true => (self.selected + 1).min(last),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    /// Switch the selected term or configured pattern off, or back on, returning it and
+    /// whether it is now on.
+    pub fn toggle(&mut self, configured: &[(String, String)]) -> Option<(String, bool)> {
+        let (pattern, _) = self.terms.iter().chain(configured).nth(self.selected)?;
+        let was_on = !self.off.remove(pattern);
+        let pattern = pattern.clone();
+        if was_on {
+            self.off.insert(pattern.clone());
+        }
+        self.configure(configured);
+        Some((pattern, !was_on))
+    }
+
+    /// The overlay, each pattern with a checkbox and in its style, and the selected one
+    /// marked.
+    pub fn panel(&self, configured: &[(String, String)]) -> Vec<String> {
+        let mut panel = vec![
+            "\x1B[1mPatterns\x1B[0m  (j/k select, space switches on and off, P closes)".to_string(),
+        ];
+        let terms = self.terms.iter().map(|pattern| (pattern, "term"));
+        let configured = configured.iter().map(|pattern| (pattern, "config"));
+        for (index, ((pattern, style), kind)) in terms.chain(configured).enumerate() {
+            let check = if self.is_on(pattern) { "x" } else { " " };
+            let kind = match pattern.starts_with('!') && kind == "term" {
+                true => "exclude",
+                false => kind,
+            };
+            let marker = if index == self.selected { ">" } else { " " };
+            panel.push(format!(
+                "{} [{}] \x1B[{}m{}\x1B[0m  \x1B[2m{}\x1B[0m",
+                marker, check, style, pattern, kind
+            ));
+        }
+        if panel.len() == 1 {
+            panel.push(
+                "\x1B[2mNo terms kept (t keeps the filter) or patterns in the config\x1B[0m"
+                    .to_string(),
+            );
+        }
+        panel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_off_and_on() {
+        let term = |text: &str| (text.to_string(), "33".to_string());
+        let configured = vec![term("WARN")];
+        let mut patterns = Patterns::new(vec![term("error"), term("!health")], &configured);
+        assert_eq!(patterns.ranges("a WARN"), vec![(2..6, "33")]);
+        patterns.select(true, &configured);
+        assert_eq!(
+            patterns.toggle(&configured),
+            Some(("!health".to_string(), false))
+        );
+        assert_eq!(patterns.active_terms(), vec![term("error")]);
+        patterns.select(true, &configured);
+        patterns.select(true, &configured);
+        assert_eq!(
+            patterns.toggle(&configured),
+            Some(("WARN".to_string(), false))
+        );
+        assert!(!patterns.is_on("WARN"));
+        assert!(patterns.ranges("a WARN").is_empty());
+        assert_eq!(
+            patterns.toggle(&configured),
+            Some(("WARN".to_string(), true))
+        );
+        assert_eq!(patterns.drop_term().as_deref(), Some("!health"));
+        assert!(patterns.is_on("!health"));
+
+        let mut restored = Patterns::new(vec![term("error")], &configured);
+        restored.switch_off(&["WARN".to_string()], &configured);
+        assert!(restored.ranges("a WARN").is_empty());
+        assert_eq!(restored.switched_off(), ["WARN"]);
+    }
+}
@@ -0,0 +1,188 @@
+//! Structured log formats mapped onto one set of common fields.
+//!
+//! Whatever the format calls them, a record's timestamp, level, message, host and service
+//! are available as `.time`, `.level`, `.message`, `.host` and `.service`. The original
+//! fields are kept as well.
+
+use jiff::Timestamp;
+use serde_json::{Map, Value};
+
+/// A known structured log format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Profile {
+    /// Graylog Extended Log Format.
+    Gelf,
+    /// Logstash's JSON event format.
+    Logstash,
+    /// OpenTelemetry log records in their JSON encoding.
+    Otel,
+}
+
+impl Profile {
+    pub fn detect(map: &Map<String, Value>) -> Option<Profile> {
+        if map.contains_key("short_message") && map.contains_key("version") {
+            Some(Profile::Gelf)
+        } else if map.contains_key("@timestamp") {
+            Some(Profile::Logstash)
+        } else if map.contains_key("timeUnixNano") || map.contains_key("severityNumber") {
+            Some(Profile::Otel)
+        } else {
+            None
+        }
+    }
+}
+
+/// Add the common fields to `value` if it is a record in a known format.
+pub fn normalize(value: Value) -> Value {
+    let Value::Object(mut map) = value else {
+        return value;
+    };
+    let Some(profile) = Profile::detect(&map) else {
+        return Value::Object(map);
+    };
+    let mut common = Map::new();
+    match profile {
+        Profile::Gelf => {
+            common.insert("message".into(), map["short_message"].clone());
+            if let Some(seconds) = map.get("timestamp").and_then(Value::as_f64) {
+                common.extend(time_field(seconds * 1e9));
+            }
+            // GELF levels are syslog severities; the name replaces the number.
+            if let Some(level) = map.get("level").and_then(Value::as_u64) {
+                let name = [
+                    "fatal", "fatal", "fatal", "error", "warn", "info", "info", "debug",
+                ];
+                map.insert("level".into(), name[level.min(7) as usize].into());
+            }
+            // Additional fields are prefixed with an underscore.
+            for (key, value) in &map {
+                if let Some(name) = key.strip_prefix('_') {
+                    common.insert(name.to_string(), value.clone());
+                }
+            }
+        }
+        Profile::Logstash => {
+            common.insert("time".into(), map["@timestamp"].clone());
+            if let Some(name) = map.get("host").and_then(|host| host.get("name")) {
+                common.insert("host".into(), name.clone());
+            }
+            if let Some(logger) = map.get("logger_name") {
+                common.insert("service".into(), logger.clone());
+            }
+        }
+        Profile::Otel => {
+            let nanos = map
+                .get("timeUnixNano")
+                .or_else(|| map.get("observedTimeUnixNano"))
+                .and_then(|nanos| match nanos {
+                    Value::String(text) => text.parse::<f64>().ok(),
+                    other => other.as_f64(),
+                });
+            common.extend(nanos.and_then(time_field));
+            let level = match map.get("severityText").and_then(Value::as_str) {
+                Some(text) => Some(text.to_ascii_lowercase()),
+                None => map
+                    .get("severityNumber")
+                    .and_then(Value::as_u64)
+                    .map(|number| {
+                        let names = ["trace", "debug", "info", "warn", "error", "fatal"];
+                        names[(number.clamp(1, 24) as usize - 1) / 4].to_string()
+                    }),
+            };
+            common.extend(level.map(|level| ("level".to_string(), level.into())));
+            if let Some(body) = map.get("body") {
+                common.insert("message".into(), any_value(body));
+            }
+            for attribute in map
+                .get("attributes")
+                .into_iter()
+                .chain(map.get("resource").and_then(|r| r.get("attributes")))
+                .filter_map(Value::as_array)
+                .flatten()
+            {
+                if let Some(key) = attribute.get("key").and_then(Value::as_str) {
+                    let value = attribute.get("value").map(any_value).unwrap_or(Value::Null);
+                    let key = if key == "service.name" {
+                        "service"
+                    } else {
+                        key
+                    };
+                    common.insert(key.to_string(), value);
+                }
+            }
+        }
+    }
+    // The format's own fields win over the common names.
+    for (key, value) in common {
+        map.entry(key).or_insert(value);
+    }
+    Value::Object(map)
+}
+
+/// `time` as RFC 3339, from nanoseconds since the Unix epoch.
+fn time_field(nanos: f64) -> Option<(String, Value)> {
+    let time = Timestamp::from_nanosecond(nanos as i128).ok()?;
+    Some(("time".to_string(), time.to_string().into()))
+}
+
+/// The plain value inside an OpenTelemetry `AnyValue` such as `{"stringValue": "x"}`.
+fn any_value(value: &Value) -> Value {
+    let Some(map) = value.as_object() else {
+        return value.clone();
+    };
+    match map.iter().next() {
+        Some((key, inner)) if map.len() == 1 && key.ends_with("Value") => match key.as_str() {
+            "intValue" => inner
+                .as_str()
+                .and_then(|text| text.parse::<i64>().ok())
+                .map_or_else(|| inner.clone(), Value::from),
+            _ => inner.clone(),
+        },
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(json: &str) -> Value {
+        normalize(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_gelf() {
+        let record = normalized(
+            r#"{"version":"1.1","host":"web1","short_message":"boom","timestamp":1700000000.5,"level":3,"_user_id":42}"#,
+        );
+        assert_eq!(record["message"], "boom");
+        assert_eq!(record["level"], "error");
+        assert_eq!(record["time"], "2023-11-14T22:13:20.5Z");
+        assert_eq!(record["user_id"], 42);
+        assert_eq!(record["host"], "web1");
+    }
+
+    #[test]
+    fn test_logstash_and_otel() {
+        let record = normalized(
+            r#"{"@timestamp":"2024-01-01T00:00:00Z","message":"hi","level":"WARN","host":{"name":"h"}}"#,
+        );
+        assert_eq!(record["time"], "2024-01-01T00:00:00Z");
+        assert_eq!(record["level"], "WARN");
+        assert_eq!(record["host"]["name"], "h");
+
+        let record = normalized(
+            r#"{"timeUnixNano":"1700000000000000000","severityNumber":17,"body":{"stringValue":"failed"},"attributes":[{"key":"retries","value":{"intValue":"3"}}]}"#,
+        );
+        assert_eq!(record["level"], "error");
+        assert_eq!(record["message"], "failed");
+        assert_eq!(record["retries"], 3);
+        assert_eq!(record["time"], "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn test_other_json_untouched() {
+        let record = normalized(r#"{"msg":"x"}"#);
+        assert_eq!(record, serde_json::json!({"msg": "x"}));
+    }
+}
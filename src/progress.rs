@@ -0,0 +1,268 @@
+//! pv-style throughput accounting for the input stream.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// How far back the current rate looks.
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Bytes and lines read so far, with enough history to compute a current rate.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    started: Instant,
+    pub bytes: u64,
+    pub lines: u64,
+    /// Size of the input when it is a regular file, for the ETA.
+    pub total: Option<u64>,
+    /// (time, cumulative bytes) samples within the rate window.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl Progress {
+    pub fn new(started: Instant, total: Option<u64>) -> Self {
+        Progress {
+            started,
+            bytes: 0,
+            lines: 0,
+            total,
+            samples: VecDeque::from([(started, 0)]),
+        }
+    }
+
+    /// Account for one line of `bytes` bytes (including its newline).
+    pub fn record(&mut self, bytes: usize, now: Instant) {
+        self.bytes += bytes as u64;
+        self.lines += 1;
+        // One sample per 100ms is plenty to compute a rate from.
+        if self
+            .samples
+            .back()
+            .is_none_or(|(time, _)| now.duration_since(*time) >= Duration::from_millis(100))
+        {
+            self.samples.push_back((now, self.bytes));
+        }
+        while self.samples.len() > 1 && now.duration_since(self.samples[0].0) > RATE_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Bytes per second over the recent window.
+    pub fn rate(&self, now: Instant) -> f64 {
+        let (since, bytes) = self.samples.front().copied().unwrap_or((self.started, 0));
+        let elapsed = now.duration_since(since).as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        (self.bytes - bytes) as f64 / elapsed
+    }
+
+    /// Estimated time until the whole input has been read, when its size is known.
+    pub fn eta(&self, now: Instant) -> Option<Duration> {
+        let remaining = self.total?.saturating_sub(self.bytes);
+        let rate = self.rate(now);
+        (rate > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / rate))
+    }
+
+    /// One status line: `12.3 MiB  4.5 MiB/s  120,000 lines  40%  ETA 0:32`.
+    pub fn status(&self, now: Instant) -> String {
+        let mut status = format!(
+            "{}  {}/s  {} lines  {}",
+            format_bytes(self.bytes),
+            format_bytes(self.rate(now) as u64),
+            format_count(self.lines),
+            format_clock(now.duration_since(self.started)),
+        );
+        if let Some(total) = self.total.filter(|total| *total > 0) {
+            let percent = (self.bytes.min(total) * 100) / total;
+            status.push_str(&format!("  {}%", percent));
+            if let Some(eta) = self.eta(now) {
+                status.push_str(&format!("  ETA {}", format_clock(eta)));
+            }
+        }
+        status
+    }
+}
+
+/// Lines and bytes through the printer, from every source, in total and a second over the
+/// recent window.
+#[derive(Debug, Clone)]
+pub struct Meter {
+    started: Instant,
+    pub lines: u64,
+    pub bytes: u64,
+    /// (time, cumulative lines, cumulative bytes) samples within the rate window.
+    samples: VecDeque<(Instant, u64, u64)>,
+}
+
+impl Meter {
+    pub fn new(started: Instant) -> Self {
+        Meter {
+            started,
+            lines: 0,
+            bytes: 0,
+            samples: VecDeque::from([(started, 0, 0)]),
+        }
+    }
+
+    /// Account for one line of `bytes` bytes (including its newline).
+    pub fn record(&mut self, bytes: usize, now: Instant) {
+        self.lines += 1;
+        self.bytes += bytes as u64;
+        if self
+            .samples
+            .back()
+            .is_none_or(|(time, ..)| now.duration_since(*time) >= Duration::from_millis(100))
+        {
+            self.samples.push_back((now, self.lines, self.bytes));
+        }
+        while self.samples.len() > 1 && now.duration_since(self.samples[0].0) > RATE_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Lines and bytes per second over the window, falling to zero once input stops.
+    pub fn rates(&self, now: Instant) -> (f64, f64) {
+        let (since, lines, bytes) = self
+            .samples
+            .iter()
+            .find(|(time, ..)| now.duration_since(*time) <= RATE_WINDOW)
+            .copied()
+            .unwrap_or((now, self.lines, self.bytes));
+        let elapsed = now.duration_since(since).as_secs_f64();
+        if elapsed <= 0.0 {
+            return (0.0, 0.0);
+        }
+        (
+            (self.lines - lines) as f64 / elapsed,
+            (self.bytes - bytes) as f64 / elapsed,
+        )
+    }
+
+    /// One status widget: `1,250 lines/s  4.5 MiB/s`.
+    pub fn status(&self, now: Instant) -> String {
+        let (lines, bytes) = self.rates(now);
+        format!(
+            "{} lines/s  {}/s",
+            format_count(lines.round() as u64),
+            format_bytes(bytes as u64)
+        )
+    }
+
+    /// Totals since the start: `12.3 MiB  120,000 lines  0:42`.
+    pub fn totals(&self, now: Instant) -> String {
+        format!(
+            "{}  {} lines  {}",
+            format_bytes(self.bytes),
+            format_count(self.lines),
+            format_clock(now.duration_since(self.started))
+        )
+    }
+}
+
+/// Size of stdin if it is redirected from a regular file.
+pub fn stdin_file_size() -> Option<u64> {
+    let file = stdin_file().ok()?;
+    let metadata = file.metadata().ok()?;
+    metadata.is_file().then_some(metadata.len())
+}
+
+#[cfg(unix)]
+pub fn stdin_file() -> io::Result<File> {
+    use std::os::fd::AsFd;
+    Ok(File::from(io::stdin().as_fd().try_clone_to_owned()?))
+}
+
+#[cfg(windows)]
+pub fn stdin_file() -> io::Result<File> {
+    use std::os::windows::io::AsHandle;
+    Ok(File::from(io::stdin().as_handle().try_clone_to_owned()?))
+}
+
+/// Binary-prefixed byte count such as `512 B` or `12.3 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Count with thousands separators, such as `1,234,567`.
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Clock-style duration such as `0:07` or `1:02:03`.
+pub fn format_clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_rate_and_eta() {
+        let start = Instant::now();
+        let mut progress = Progress::new(start, Some(4000));
+        progress.record(1000, start + Duration::from_secs(1));
+        progress.record(1000, start + Duration::from_secs(2));
+
+        let now = start + Duration::from_secs(2);
+        assert_eq!(progress.lines, 2);
+        assert_eq!(progress.rate(now), 1000.0);
+        assert_eq!(progress.eta(now), Some(Duration::from_secs(2)));
+        assert_eq!(
+            progress.status(now),
+            "2.0 KiB  1000 B/s  2 lines  0:02  50%  ETA 0:02"
+        );
+    }
+
+    #[test]
+    fn test_meter_rates() {
+        let start = Instant::now();
+        let mut meter = Meter::new(start);
+        for tenth in 1..=20 {
+            meter.record(100, start + Duration::from_millis(tenth * 100));
+        }
+        let now = start + Duration::from_secs(2);
+        assert_eq!(meter.status(now), "10 lines/s  1000 B/s");
+        assert_eq!(meter.rates(now + RATE_WINDOW * 2), (0.0, 0.0));
+        assert_eq!(meter.totals(now), "2.0 KiB  20 lines  0:02");
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 * 1024 * 1024 / 2), "1.5 MiB");
+        assert_eq!(format_count(1234567), "1,234,567");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_clock(Duration::from_secs(3723)), "1:02:03");
+    }
+}
@@ -0,0 +1,75 @@
+//! Capping how many lines a second are shown, for producers that go berserk.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    max: usize,
+    /// Start of the current one-second window and the lines shown in it.
+    window: Instant,
+    shown: usize,
+    /// Lines held back since the last one shown.
+    dropped: u64,
+}
+
+impl RateLimit {
+    pub fn new(max: usize, now: Instant) -> Self {
+        RateLimit {
+            max,
+            window: now,
+            shown: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Whether a line arriving at `now` may be shown; if so, along with the number of
+    /// lines dropped before it. A `priority` line is always shown and leaves the other
+    /// lines their full share.
+    pub fn admit(&mut self, now: Instant, priority: bool) -> Option<u64> {
+        if now.duration_since(self.window) >= Duration::from_secs(1) {
+            self.window = now;
+            self.shown = 0;
+        }
+        if priority {
+            Some(std::mem::take(&mut self.dropped))
+        } else if self.shown < self.max {
+            self.shown += 1;
+            Some(std::mem::take(&mut self.dropped))
+        } else {
+            self.dropped += 1;
+            None
+        }
+    }
+
+    /// Lines dropped since the last one shown, such as at the end of the input.
+    pub fn take_dropped(&mut self) -> u64 {
+        std::mem::take(&mut self.dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excess_is_dropped_and_counted() {
+        let start = Instant::now();
+        let mut limit = RateLimit::new(2, start);
+        assert_eq!(limit.admit(start, false), Some(0));
+        assert_eq!(limit.admit(start, false), Some(0));
+        assert_eq!(limit.admit(start, false), None);
+        assert_eq!(limit.admit(start + Duration::from_millis(900), false), None);
+        assert_eq!(limit.admit(start + Duration::from_secs(1), false), Some(2));
+        assert_eq!(limit.admit(start + Duration::from_secs(1), true), Some(0));
+        assert_eq!(limit.admit(start + Duration::from_secs(1), false), Some(0));
+        assert_eq!(limit.admit(start + Duration::from_secs(1), false), None);
+        assert_eq!(limit.admit(start + Duration::from_secs(1), true), Some(1));
+        assert_eq!(limit.admit(start + Duration::from_secs(1), false), None);
+        assert_eq!(limit.take_dropped(), 1);
+        assert_eq!(limit.take_dropped(), 0);
+    }
+}
@@ -0,0 +1,112 @@
+//! Joining continuation lines, such as stack traces, onto the record they belong to.
+
+use regex::Regex;
+use std::time::{Duration, Instant};
+
+/// A record is sent on once this long passes without a continuation line.
+pub const IDLE_FLUSH: Duration = Duration::from_millis(200);
+/// Records are cut off at this many lines, in case the start pattern never matches.
+const MAX_LINES: usize = 1000;
+
+/// Preset for `--record-start timestamp`: an ISO date, a `[date` or a syslog-style date.
+const TIMESTAMP: &str =
+    r"^(\[?\d{4}-\d{2}-\d{2}|\d{2}:\d{2}:\d{2}|[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2})";
+
+/// Collects lines until the next one that starts a new record.
+#[derive(Debug)]
+pub struct Joiner {
+    start: Regex,
+    pending: Option<String>,
+    lines: usize,
+    last_line: Instant,
+}
+
+impl Joiner {
+    /// `start` matches the first line of each record; `timestamp` picks a built-in pattern.
+    pub fn new(start: &str) -> Result<Joiner, String> {
+        let pattern = if start == "timestamp" {
+            TIMESTAMP
+        } else {
+            start
+        };
+        Ok(Joiner {
+            start: Regex::new(pattern).map_err(|e| format!("invalid regex: {}", e))?,
+            pending: None,
+            lines: 0,
+            last_line: Instant::now(),
+        })
+    }
+
+    /// Add a line, returning the previous record if this line starts a new one.
+    pub fn push(&mut self, line: String, now: Instant) -> Option<String> {
+        self.last_line = now;
+        match &mut self.pending {
+            Some(record) if !self.start.is_match(&line) && self.lines < MAX_LINES => {
+                record.push('\n');
+                record.push_str(&line);
+                self.lines += 1;
+                None
+            }
+            _ => {
+                self.lines = 1;
+                self.pending.replace(line)
+            }
+        }
+    }
+
+    /// The pending record, if nothing has been added to it for `IDLE_FLUSH`.
+    pub fn flush_idle(&mut self, now: Instant) -> Option<String> {
+        if now.saturating_duration_since(self.last_line) < IDLE_FLUSH {
+            return None;
+        }
+        self.flush()
+    }
+
+    pub fn flush(&mut self) -> Option<String> {
+        self.lines = 0;
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_stack_trace() {
+        let mut joiner = Joiner::new("timestamp").unwrap();
+        let now = Instant::now();
+        let mut records = Vec::new();
+        for line in [
+            "2024-01-02 10:00:00 ERROR boom",
+            "java.lang.IllegalStateException: bad",
+            "\tat com.example.Main.run(Main.java:10)",
+            "2024-01-02 10:00:01 INFO fine",
+        ] {
+            records.extend(joiner.push(line.to_string(), now));
+        }
+        assert_eq!(
+            records,
+            vec!["2024-01-02 10:00:00 ERROR boom\njava.lang.IllegalStateException: bad\n\tat com.example.Main.run(Main.java:10)"]
+        );
+        assert_eq!(joiner.flush_idle(now), None);
+        assert_eq!(
+            joiner.flush_idle(now + IDLE_FLUSH).as_deref(),
+            Some("2024-01-02 10:00:01 INFO fine")
+        );
+        assert_eq!(joiner.flush(), None);
+    }
+
+    #[test]
+    fn test_leading_continuation_lines() {
+        let mut joiner = Joiner::new("^START").unwrap();
+        let now = Instant::now();
+        assert_eq!(joiner.push("orphan".to_string(), now), None);
+        assert_eq!(joiner.push("more".to_string(), now), None);
+        assert_eq!(
+            joiner.push("START".to_string(), now).as_deref(),
+            Some("orphan\nmore")
+        );
+        assert!(Joiner::new("(").is_err());
+    }
+}
@@ -0,0 +1,338 @@
+//! Session recordings: every input line and filter change with the time it happened.
+//!
+//! A recording starts with a `pipe_tools recording 2` line, followed by frames of the form
+//!
+//! ```text
+//! <kind> <milliseconds since start> <length in bytes> <source> <wall clock>\n<payload>\n
+//! ```
+//!
+//! where kind is `L` for an input line, `F` for a new filter string and `N` for a note
+//! attached to the most recent line. The length prefix lets payloads hold any bytes,
+//! including newlines. Source is 0 for stdin, N for the Nth `--file` or `--exec`, or `-`
+//! for what did not come from an input; the wall clock is in milliseconds since the Unix
+//! epoch. Recordings of version 1 have frames without the last two fields.
+
+use jiff::Timestamp;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const MAGIC: &str = "pipe_tools recording 2";
+/// The first line of recordings written before frames had a source and wall clock.
+const MAGIC_V1: &str = "pipe_tools recording 1";
+
+/// Something that happened during a session.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Line(String),
+    Filter(String),
+    Note(String),
+}
+
+impl Event {
+    fn kind(&self) -> char {
+        match self {
+            Event::Line(_) => 'L',
+            Event::Filter(_) => 'F',
+            Event::Note(_) => 'N',
+        }
+    }
+
+    fn payload(&self) -> &str {
+        match self {
+            Event::Line(text) | Event::Filter(text) | Event::Note(text) => text,
+        }
+    }
+}
+
+/// An event read back from a recording, with where and when it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Time since the recording started.
+    pub at: Duration,
+    /// `None` in version 1 recordings.
+    pub wall: Option<Timestamp>,
+    /// The input the event came from, as numbered in the frame.
+    pub source: Option<usize>,
+    pub event: Event,
+}
+
+/// Writes events to a recording as they happen.
+pub struct Recorder<W: Write = BufWriter<File>> {
+    out: W,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, started: Instant) -> io::Result<Recorder> {
+        Recorder::new(BufWriter::new(File::create(path)?), started)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut out: W, started: Instant) -> io::Result<Recorder<W>> {
+        writeln!(out, "{}", MAGIC)?;
+        Ok(Recorder { out, started })
+    }
+
+    /// Write `event` from input `source`, which happened at `now` and `wall`.
+    pub fn record(
+        &mut self,
+        event: &Event,
+        source: Option<usize>,
+        now: Instant,
+        wall: Timestamp,
+    ) -> io::Result<()> {
+        let millis = now.saturating_duration_since(self.started).as_millis();
+        let source = source.map_or_else(|| "-".to_string(), |source| source.to_string());
+        let payload = event.payload();
+        write!(
+            self.out,
+            "{} {} {} {} {}\n{}\n",
+            event.kind(),
+            millis,
+            payload.len(),
+            source,
+            wall.as_millisecond(),
+            payload
+        )
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Reads the events of a recording back, with the time each happened at.
+pub struct Reader<R: BufRead = BufReader<File>> {
+    input: R,
+    /// Whether frames have a source and wall clock.
+    stamped: bool,
+}
+
+impl Reader {
+    pub fn open(path: &Path) -> io::Result<Reader> {
+        Reader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(mut input: R) -> io::Result<Reader<R>> {
+        let mut magic = String::new();
+        input.read_line(&mut magic)?;
+        let stamped = match magic.trim_end() {
+            MAGIC => true,
+            MAGIC_V1 => false,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "not a pipe_tools recording",
+                ))
+            }
+        };
+        Ok(Reader { input, stamped })
+    }
+
+    fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+        let mut header = String::new();
+        if self.input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let mut fields = header.split_whitespace();
+        let kind = fields.next().ok_or_else(|| invalid("empty frame header"))?;
+        let mut field = || fields.next().ok_or_else(|| invalid("bad frame header"));
+        let number = |field: &str| -> io::Result<i64> {
+            field.parse().map_err(|_| invalid("bad frame header"))
+        };
+        let (millis, length) = (number(field()?)?, number(field()?)?);
+        let (source, wall) = match self.stamped {
+            true => {
+                let source = match field()? {
+                    "-" => None,
+                    source => Some(number(source)? as usize),
+                };
+                let wall = Timestamp::from_millisecond(number(field()?)?)
+                    .map_err(|_| invalid("bad frame header"))?;
+                (source, Some(wall))
+            }
+            false => (None, None),
+        };
+        if millis < 0 || length < 0 {
+            return Err(invalid("bad frame header"));
+        }
+
+        let mut payload = vec![0; length as usize + 1];
+        self.input.read_exact(&mut payload)?;
+        if payload.pop() != Some(b'\n') {
+            return Err(invalid("frame not terminated by a newline"));
+        }
+        let text = String::from_utf8_lossy(&payload).into_owned();
+        let event = match kind {
+            "L" => Event::Line(text),
+            "F" => Event::Filter(text),
+            "N" => Event::Note(text),
+            _ => return Err(invalid("unknown frame kind")),
+        };
+        Ok(Some(Frame {
+            at: Duration::from_millis(millis as u64),
+            wall,
+            source,
+            event,
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+/// How fast a recording is played back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playback {
+    pub speed: f64,
+    pub paused: bool,
+}
+
+impl Playback {
+    const MIN_SPEED: f64 = 1.0 / 16.0;
+    const MAX_SPEED: f64 = 256.0;
+
+    pub fn new(speed: f64) -> Self {
+        Playback {
+            speed,
+            paused: false,
+        }
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(Self::MAX_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(Self::MIN_SPEED);
+    }
+
+    /// Status area text such as `Replay 4x` or `Replay paused`.
+    pub fn status(&self) -> String {
+        if self.paused {
+            "Replay paused".to_string()
+        } else {
+            format!("Replay {}x", self.speed)
+        }
+    }
+}
+
+/// Sleep until recording time `position` reaches `at`, following speed changes and pauses.
+pub fn wait_until(playback: &Mutex<Playback>, position: &mut Duration, at: Duration) {
+    const STEP: Duration = Duration::from_millis(50);
+    while *position < at {
+        let Playback { speed, paused } = *playback.lock().unwrap();
+        if paused {
+            thread::sleep(STEP);
+            continue;
+        }
+        let nap = (at - *position).div_f64(speed).min(STEP);
+        thread::sleep(nap);
+        *position += nap.mul_f64(speed).max(Duration::from_nanos(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_record_frames() {
+        let start = Instant::now();
+        let wall = Timestamp::from_millisecond(1_700_000_000_000).unwrap();
+        let mut recorder = Recorder::new(Vec::new(), start).unwrap();
+        recorder
+            .record(
+                &Event::Line("two\nlines".to_string()),
+                Some(2),
+                start + Duration::from_millis(5),
+                wall,
+            )
+            .unwrap();
+        recorder
+            .record(
+                &Event::Filter("ERR".to_string()),
+                None,
+                start + Duration::from_secs(1),
+                wall,
+            )
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(recorder.out.clone()).unwrap(),
+            "pipe_tools recording 2\nL 5 9 2 1700000000000\ntwo\nlines\n\
+             F 1000 3 - 1700000000000\nERR\n"
+        );
+
+        let frames: Vec<_> = Reader::new(recorder.out.as_slice())
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            frames,
+            vec![
+                Frame {
+                    at: Duration::from_millis(5),
+                    wall: Some(wall),
+                    source: Some(2),
+                    event: Event::Line("two\nlines".to_string())
+                },
+                Frame {
+                    at: Duration::from_secs(1),
+                    wall: Some(wall),
+                    source: None,
+                    event: Event::Filter("ERR".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_version_1() {
+        let frame = Reader::new(&b"pipe_tools recording 1\nL 5 3\nabc\n"[..])
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (frame.at, frame.wall, frame.source),
+            (Duration::from_millis(5), None, None)
+        );
+        assert_eq!(frame.event, Event::Line("abc".to_string()));
+    }
+
+    #[test]
+    fn test_playback_speed() {
+        let mut playback = Playback::new(1.0);
+        playback.faster();
+        assert_eq!(playback.status(), "Replay 2x");
+        for _ in 0..10 {
+            playback.slower();
+        }
+        assert_eq!(playback.speed, 1.0 / 16.0);
+        playback.paused = true;
+        assert_eq!(playback.status(), "Replay paused");
+    }
+
+    #[test]
+    fn test_reader_rejects_garbage() {
+        assert!(Reader::new(&b"hello\n"[..]).is_err());
+        let mut reader = Reader::new(&b"pipe_tools recording 1\nL 0 10\nshort\n"[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+        let mut reader = Reader::new(&b"pipe_tools recording 2\nL 0 5\nshort\n"[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+}
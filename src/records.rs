@@ -0,0 +1,220 @@
+//! The table view: JSON and logfmt records as rows of chosen fields, sorted by any
+//! column.
+
+use crate::field;
+use crate::logfmt;
+use crate::width;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+/// Rows kept; the oldest are dropped beyond this.
+const KEPT: usize = 10_000;
+/// Columns taken from the first record when none are chosen.
+const AUTO_COLUMNS: usize = 6;
+
+#[derive(Debug, Clone, Default)]
+pub struct Records {
+    /// Fields shown, as named with --columns or taken from the first record; a dotted
+    /// name such as `http.status` reaches into nested JSON.
+    pub columns: Vec<String>,
+    rows: VecDeque<Vec<String>>,
+    /// The column the sort key acts on.
+    pub selected: usize,
+    /// The column rows are sorted by and whether descending; by arrival when `None`.
+    sort: Option<(usize, bool)>,
+}
+
+impl Records {
+    pub fn new(columns: Vec<String>) -> Records {
+        Records {
+            columns,
+            ..Records::default()
+        }
+    }
+
+    /// Add `line` as a row if it is a JSON or logfmt record.
+    pub fn record(&mut self, line: &str) {
+        let row = match field::record(line) {
+            Some(value @ Value::Object(_)) => {
+                if self.columns.is_empty() {
+                    let keys = value.as_object().unwrap().keys();
+                    self.columns = keys.take(AUTO_COLUMNS).cloned().collect();
+                }
+                self.columns
+                    .iter()
+                    .map(|column| {
+                        let path: Vec<String> = column.split('.').map(str::to_string).collect();
+                        field::lookup(&value, &path).unwrap_or_default()
+                    })
+                    .collect()
+            }
+            Some(_) => return,
+            None => {
+                let Some(pairs) = logfmt::parse(line) else {
+                    return;
+                };
+                if self.columns.is_empty() {
+                    self.columns = pairs
+                        .iter()
+                        .take(AUTO_COLUMNS)
+                        .map(|(k, _)| k.clone())
+                        .collect();
+                }
+                self.columns
+                    .iter()
+                    .map(|column| {
+                        pairs
+                            .iter()
+                            .find(|(key, _)| key == column)
+                            .map(|(_, value)| value.clone())
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            }
+        };
+        if self.rows.len() == KEPT {
+            self.rows.pop_front();
+        }
+        self.rows.push_back(row);
+    }
+
+    pub fn select(&mut self, right: bool) {
+        self.selected = match right {
+            true => (self.selected + 1).min(self.columns.len().saturating_sub(1)),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    /// Sort by the selected column ascending, then descending, then by arrival again.
+    pub fn cycle_sort(&mut self) {
+        self.sort = match self.sort {
+            Some((column, false)) if column == self.selected => Some((column, true)),
+            Some((column, true)) if column == self.selected => None,
+            _ => Some((self.selected, false)),
+        };
+    }
+
+    /// Rows in the order shown: sorted, or the latest last.
+    fn ordered(&self, count: usize) -> Vec<&Vec<String>> {
+        let Some((column, descending)) = self.sort else {
+            return self
+                .rows
+                .iter()
+                .skip(self.rows.len().saturating_sub(count))
+                .collect();
+        };
+        let mut rows: Vec<_> = self.rows.iter().collect();
+        rows.sort_by(|a, b| {
+            let order = compare(&a[column], &b[column]);
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        rows.truncate(count);
+        rows
+    }
+
+    /// The panel: a title, the column headers with the selected one in reverse video and
+    /// the sorted one marked, then as many rows as fit in `height` lines.
+    pub fn panel(&self, height: usize) -> Vec<String> {
+        let mut panel = vec![format!(
+            "\x1B[1mRecords\x1B[0m {}  (h/l select a column, space sorts by it, v closes)",
+            self.rows.len()
+        )];
+        if self.columns.is_empty() {
+            panel.push(
+                "\x1B[2mNo JSON or logfmt records yet; --columns chooses the fields\x1B[0m"
+                    .to_string(),
+            );
+            return panel;
+        }
+        let rows = self.ordered(height.saturating_sub(2));
+        let headers: Vec<String> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| match self.sort {
+                Some((sorted, false)) if sorted == index => format!("{} ▲", column),
+                Some((sorted, true)) if sorted == index => format!("{} ▼", column),
+                _ => column.clone(),
+            })
+            .collect();
+        let widths: Vec<usize> = headers
+            .iter()
+            .enumerate()
+            .map(|(index, header)| {
+                rows.iter()
+                    .map(|row| width::width(&row[index]))
+                    .chain([width::width(header)])
+                    .max()
+                    .unwrap_or(0)
+                    .min(width::MAX_COLUMN)
+            })
+            .collect();
+        let cells = |row: &[String]| -> Vec<String> {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, &width)| width::pad(&width::truncate(cell, width), width))
+                .collect()
+        };
+        let header: Vec<String> = cells(&headers)
+            .into_iter()
+            .enumerate()
+            .map(|(index, cell)| match index == self.selected {
+                true => format!("\x1B[1;7m{}\x1B[0m", cell),
+                false => format!("\x1B[1m{}\x1B[0m", cell),
+            })
+            .collect();
+        panel.push(header.join("  "));
+        panel.extend(rows.into_iter().map(|row| cells(row).join("  ")));
+        panel
+    }
+}
+
+/// Numbers in numeric order, before anything else in text order.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_and_sorting() {
+        let mut records = Records::new(Vec::new());
+        records.record(r#"{"level":"info","took":12,"http":{"status":200}}"#);
+        records.record("not a record");
+        records.record("level=warn took=9");
+        records.record(r#"{"level":"error","took":100}"#);
+        assert_eq!(records.columns, ["level", "took", "http"]);
+        let rows = |records: &Records| -> Vec<Vec<String>> {
+            records.ordered(10).into_iter().cloned().collect()
+        };
+        assert_eq!(rows(&records)[1], ["warn", "9", ""]);
+        records.select(true);
+        records.cycle_sort();
+        let took: Vec<String> = rows(&records)
+            .into_iter()
+            .map(|row| row[1].clone())
+            .collect();
+        assert_eq!(took, ["9", "12", "100"]);
+        records.cycle_sort();
+        assert_eq!(rows(&records)[0][0], "error");
+        records.cycle_sort();
+        assert_eq!(rows(&records)[0][0], "info");
+
+        let mut chosen = Records::new(vec!["http.status".to_string()]);
+        chosen.record(r#"{"http":{"status":503}}"#);
+        assert_eq!(rows(&chosen), [["503"]]);
+        assert_eq!(chosen.panel(5).len(), 3);
+    }
+}
@@ -0,0 +1,263 @@
+//! The most recent lines, kept in memory so they can be browsed or saved after the fact,
+//! along with any notes attached to them.
+
+use jiff::Zoned;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Earlier snapshots kept beside the latest.
+const SNAPSHOTS_KEPT: usize = 5;
+
+/// A bounded buffer of the lines that went past.
+#[derive(Debug, Clone)]
+pub struct Scrollback {
+    /// Each line with its notes.
+    lines: VecDeque<(String, Vec<String>)>,
+    capacity: usize,
+    /// Lines pushed out of the front, so lines keep their number as the buffer moves.
+    dropped: usize,
+}
+
+impl Scrollback {
+    pub fn new(capacity: usize) -> Self {
+        Scrollback {
+            lines: VecDeque::new(),
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, line: &str) {
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back((line.to_string(), Vec::new()));
+    }
+
+    /// Number of the oldest line still held, counting every line pushed from 0.
+    pub fn start(&self) -> usize {
+        self.dropped
+    }
+
+    /// One past the number of the most recent line.
+    pub fn end(&self) -> usize {
+        self.dropped + self.lines.len()
+    }
+
+    pub fn get(&self, number: usize) -> Option<&str> {
+        let (line, _) = self.lines.get(number.checked_sub(self.dropped)?)?;
+        Some(line)
+    }
+
+    /// The nearest line after `from` (or before it, going back) for which `matches` holds.
+    pub fn find(
+        &self,
+        from: usize,
+        forward: bool,
+        matches: impl Fn(&str) -> bool,
+    ) -> Option<usize> {
+        let found = |number: &usize| self.get(*number).is_some_and(&matches);
+        if forward {
+            (from + 1..self.end()).find(found)
+        } else {
+            (self.start()..from.min(self.end())).rev().find(found)
+        }
+    }
+
+    /// Attach `note` to the most recent line, returning that line.
+    pub fn annotate(&mut self, note: &str) -> Option<&str> {
+        let (line, notes) = self.lines.back_mut()?;
+        notes.push(note.to_string());
+        Some(line)
+    }
+
+    /// Lines in the buffer with their notes, oldest first, limited to those containing
+    /// `filter` if given.
+    pub fn lines<'a>(
+        &'a self,
+        filter: Option<&'a str>,
+    ) -> impl Iterator<Item = (&'a str, &'a [String])> {
+        self.lines
+            .iter()
+            .map(|(line, notes)| (line.as_str(), notes.as_slice()))
+            .filter(move |(line, _)| filter.is_none_or(|filter| line.contains(filter)))
+    }
+
+    /// Lines in the buffer that `keep` accepts, oldest first and each only once.
+    pub fn distinct(&self, keep: impl Fn(&str) -> bool) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.lines
+            .iter()
+            .map(|(line, _)| line.as_str())
+            .filter(|line| keep(line) && seen.insert(*line))
+            .collect()
+    }
+
+    /// Write the buffer to a new timestamped file in the current directory, returning
+    /// its name and the number of lines written. Notes follow their line as `# note: ...`.
+    pub fn dump(&self, filter: Option<&str>) -> io::Result<(PathBuf, usize)> {
+        let path = PathBuf::from(
+            Zoned::now()
+                .strftime("pipe_tools-%Y%m%d-%H%M%S.log")
+                .to_string(),
+        );
+        let count = self.save(&path, filter)?;
+        Ok((path, count))
+    }
+
+    /// Write the buffer, or only the lines matching `filter`, to the new file `path`;
+    /// returns the number of lines.
+    pub fn save(&self, path: &Path, filter: Option<&str>) -> io::Result<usize> {
+        self.write(File::create_new(path)?, filter)
+    }
+
+    /// Write the buffer to `path`, first moving earlier snapshots along from `path` to
+    /// `path` numbered 1 and so on, the oldest dropped; returns the number of lines.
+    pub fn snapshot(&self, path: &Path) -> io::Result<usize> {
+        let numbered = |number: usize| match number {
+            0 => path.to_path_buf(),
+            _ => path.with_extension(format!(
+                "{}.{}",
+                number,
+                path.extension().unwrap_or_default().to_string_lossy()
+            )),
+        };
+        for number in (0..SNAPSHOTS_KEPT).rev() {
+            match fs::rename(numbered(number), numbered(number + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        self.write(File::create(path)?, None)
+    }
+
+    fn write(&self, file: File, filter: Option<&str>) -> io::Result<usize> {
+        let mut out = BufWriter::new(file);
+        let mut count = 0;
+        for (line, notes) in self.lines(filter) {
+            writeln!(out, "{}", line)?;
+            for note in notes {
+                writeln!(out, "# note: {}", note)?;
+            }
+            count += 1;
+        }
+        out.flush()?;
+        Ok(count)
+    }
+}
+
+/// Position while browsing the scrollback, as line numbers: the selected line and the
+/// one at the top of the screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Browse {
+    pub line: usize,
+    pub top: usize,
+}
+
+impl Browse {
+    /// Select `line`, kept within the buffer.
+    pub fn go(&mut self, scrollback: &Scrollback, line: usize) {
+        self.line = line.clamp(scrollback.start(), scrollback.end().saturating_sub(1));
+    }
+
+    /// Scroll so the selected line is `row` rows from the top of the screen.
+    pub fn place(&mut self, scrollback: &Scrollback, row: usize) {
+        self.top = self.line.saturating_sub(row).max(scrollback.start());
+    }
+
+    /// Scroll just enough for the selected line to be on a screen `height` rows tall.
+    pub fn scroll(&mut self, scrollback: &Scrollback, height: usize) {
+        self.go(scrollback, self.line);
+        self.top = self
+            .top
+            .clamp(
+                self.line.saturating_sub(height.saturating_sub(1)),
+                self.line,
+            )
+            .max(scrollback.start());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_and_filtered() {
+        let mut scrollback = Scrollback::new(3);
+        assert_eq!(scrollback.annotate("nothing yet"), None);
+        for line in ["a ERROR", "b", "c ERROR", "d"] {
+            scrollback.push(line);
+        }
+        let lines =
+            |filter| -> Vec<&str> { scrollback.lines(filter).map(|(line, _)| line).collect() };
+        assert_eq!(lines(None), ["b", "c ERROR", "d"]);
+        assert_eq!(lines(Some("ERROR")), ["c ERROR"]);
+        scrollback.push("c ERROR");
+        assert_eq!(scrollback.distinct(|line| line.contains('c')), ["c ERROR"]);
+    }
+
+    #[test]
+    fn test_browse_and_find() {
+        let mut scrollback = Scrollback::new(4);
+        for line in ["a", "b ERROR", "c", "d ERROR", "e", "f"] {
+            scrollback.push(line);
+        }
+        assert_eq!((scrollback.start(), scrollback.end()), (2, 6));
+        assert_eq!(scrollback.get(3), Some("d ERROR"));
+        assert_eq!(scrollback.get(1), None);
+        let error = |line: &str| line.contains("ERROR");
+        assert_eq!(scrollback.find(5, false, error), Some(3));
+        assert_eq!(scrollback.find(3, false, error), None);
+        assert_eq!(scrollback.find(2, true, error), Some(3));
+
+        let mut browse = Browse::default();
+        browse.go(&scrollback, 100);
+        browse.scroll(&scrollback, 2);
+        assert_eq!(browse, Browse { line: 5, top: 4 });
+        browse.go(&scrollback, 0);
+        browse.scroll(&scrollback, 2);
+        assert_eq!(browse, Browse { line: 2, top: 2 });
+        browse.go(&scrollback, 5);
+        browse.place(&scrollback, 1);
+        browse.scroll(&scrollback, 3);
+        assert_eq!(browse, Browse { line: 5, top: 4 });
+    }
+
+    #[test]
+    fn test_annotate_latest_line() {
+        let mut scrollback = Scrollback::new(10);
+        scrollback.push("a");
+        scrollback.push("b");
+        assert_eq!(scrollback.annotate("started failing here"), Some("b"));
+        let notes: Vec<&[String]> = scrollback.lines(None).map(|(_, notes)| notes).collect();
+        assert!(notes[0].is_empty());
+        assert_eq!(notes[1], ["started failing here"]);
+    }
+
+    #[test]
+    fn test_snapshots_rotate() {
+        let dir = std::env::temp_dir().join(format!("pipe_tools-snapshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("snapshot.log");
+        let mut scrollback = Scrollback::new(10);
+        for line in ["a", "b", "c", "d", "e", "f", "g"] {
+            scrollback.push(line);
+            assert_eq!(scrollback.snapshot(&path).unwrap(), scrollback.end());
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\nc\nd\ne\nf\ng\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("snapshot.1.log")).unwrap(),
+            "a\nb\nc\nd\ne\nf\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("snapshot.5.log")).unwrap(),
+            "a\nb\n"
+        );
+        assert!(!dir.join("snapshot.6.log").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
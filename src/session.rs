@@ -0,0 +1,132 @@
+//! Filter, pattern and view settings saved on exit and restored by `--session`.
+
+use crate::level::Level;
+use crate::matcher::MatchOptions;
+use serde_json::{json, Value};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// What is needed to pick an investigation back up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub filter: String,
+    /// How the filter was matched.
+    pub matching: MatchOptions,
+    /// Whether lines not matching were hidden.
+    pub hide: bool,
+    /// Terms kept beside the filter, with their styles.
+    pub terms: Vec<(String, String)>,
+    /// Terms and configured patterns switched off from the patterns overlay.
+    pub off: Vec<String>,
+    pub level: Option<Level>,
+    pub pretty: bool,
+    /// Name of the view that was showing, such as `lines` or `topk`.
+    pub view: String,
+}
+
+impl Session {
+    /// Read a session file; a file that does not exist yet is not an error.
+    pub fn load(path: &Path) -> Result<Option<Session>, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        let error = |message: &str| format!("{}: {}", path.display(), message);
+        let value: Value = serde_json::from_str(&text).map_err(|e| error(&e.to_string()))?;
+        let level = match value["level"].as_str() {
+            Some(name) => Some(Level::parse(name).ok_or_else(|| error("unknown level"))?),
+            None => None,
+        };
+        let flag = |name: &str| value["matching"][name].as_bool().unwrap_or(false);
+        let matching = MatchOptions {
+            regex: flag("regex"),
+            fuzzy: flag("fuzzy"),
+            ignore_case: flag("ignore_case"),
+            smart_case: flag("smart_case"),
+            invert: flag("invert"),
+            whole_word: flag("whole_word"),
+        };
+        let text = |value: &Value| value.as_str().map(str::to_string);
+        let terms = value["terms"].as_array().map_or(&[][..], Vec::as_slice);
+        let terms = terms
+            .iter()
+            .map(|term| Some((text(&term["text"])?, text(&term["style"])?)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| error("terms need a text and a style"))?;
+        let off = value["off"].as_array().map_or(&[][..], Vec::as_slice);
+        Ok(Some(Session {
+            filter: value["filter"].as_str().unwrap_or_default().to_string(),
+            matching,
+            hide: value["hide"].as_bool().unwrap_or(false),
+            terms,
+            off: off.iter().filter_map(text).collect(),
+            level,
+            pretty: value["pretty"].as_bool().unwrap_or(false),
+            view: value["view"].as_str().unwrap_or("lines").to_string(),
+        }))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let matching = &self.matching;
+        let terms: Vec<Value> = self
+            .terms
+            .iter()
+            .map(|(text, style)| json!({ "text": text, "style": style }))
+            .collect();
+        let value = json!({
+            "filter": self.filter,
+            "matching": {
+                "regex": matching.regex,
+                "fuzzy": matching.fuzzy,
+                "ignore_case": matching.ignore_case,
+                "smart_case": matching.smart_case,
+                "invert": matching.invert,
+                "whole_word": matching.whole_word,
+            },
+            "hide": self.hide,
+            "terms": terms,
+            "off": self.off,
+            "level": self.level.map(Level::name),
+            "pretty": self.pretty,
+            "view": self.view,
+        });
+        fs::write(path, serde_json::to_string_pretty(&value)? + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let path = std::env::temp_dir().join(format!("pipe_tools-session-{}", std::process::id()));
+        assert_eq!(Session::load(&path), Ok(None));
+        let session = Session {
+            filter: "req=\"42\"".to_string(),
+            matching: MatchOptions {
+                regex: true,
+                ignore_case: true,
+                ..MatchOptions::default()
+            },
+            hide: true,
+            terms: vec![("timeout".to_string(), "30;103".to_string())],
+            off: vec!["ERROR".to_string()],
+            level: Some(Level::Warn),
+            pretty: true,
+            view: "topk".to_string(),
+        };
+        session.save(&path).unwrap();
+        assert_eq!(Session::load(&path), Ok(Some(session)));
+        fs::write(&path, r#"{"filter": "old"}"#).unwrap();
+        let old = Session::load(&path).unwrap().unwrap();
+        assert_eq!((old.filter.as_str(), old.terms.len()), ("old", 0));
+        fs::write(&path, r#"{"terms": [{"text": "x"}]}"#).unwrap();
+        assert!(Session::load(&path).is_err());
+        fs::write(&path, "{").unwrap();
+        assert!(Session::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,302 @@
+//! Inputs read alongside stdin: files followed as they grow and commands run with the
+//! shell. Each has a label shown before its lines and can be paused or muted on its own.
+
+use crate::cli;
+use crate::config::SourceStyle;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Lines kept for a paused source; older ones are dropped beyond this.
+const HELD_LINES: usize = 10_000;
+
+/// How often a followed file is checked for more lines once it has been read to the end.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Live,
+    /// Lines are held back until the source is resumed.
+    Paused,
+    /// Lines are dropped.
+    Muted,
+}
+
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub label: String,
+    /// What goes before each of its lines, such as `[api]`.
+    pub prefix: String,
+    /// SGR parameters the prefix is shown in.
+    pub color: Option<String>,
+    pub state: State,
+    held: VecDeque<String>,
+    /// Lines that arrived, and those dropped while muted or paused too long.
+    pub lines: u64,
+    pub dropped: u64,
+    /// Why the source stopped, once it has.
+    pub ended: Option<String>,
+}
+
+/// The label of a source when none is given: the file name, or the command's first word.
+pub fn label(source: &cli::Source) -> String {
+    match source {
+        cli::Source::File(path) => path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+        cli::Source::Exec(command) => command.split_whitespace().next().map_or_else(
+            || command.clone(),
+            |word| word.rsplit('/').next().unwrap().to_string(),
+        ),
+    }
+}
+
+/// Every source and the one selected in the overlay.
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+    pub list: Vec<Source>,
+    pub selected: usize,
+}
+
+impl Sources {
+    /// Sources with the usual `labels`, shown as `styles` with the same names say.
+    pub fn new(labels: Vec<String>, styles: &[SourceStyle]) -> Sources {
+        Sources {
+            list: labels
+                .into_iter()
+                .map(|label| {
+                    let style = styles.iter().find(|style| style.name == label);
+                    let style = style.cloned().unwrap_or_default();
+                    let label = style.label.unwrap_or(label);
+                    let prefix = style.prefix.as_deref().unwrap_or("[{label}]");
+                    Source {
+                        prefix: prefix.replace("{label}", &label),
+                        label,
+                        color: style.color,
+                        state: match style.muted {
+                            true => State::Muted,
+                            false => State::Live,
+                        },
+                        held: VecDeque::new(),
+                        lines: 0,
+                        dropped: 0,
+                        ended: None,
+                    }
+                })
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    /// Prefixes shown in color, with their SGR parameters.
+    pub fn colors(&self) -> Vec<(String, String)> {
+        self.list
+            .iter()
+            .filter_map(|source| Some((source.prefix.clone(), source.color.clone()?)))
+            .collect()
+    }
+
+    /// `line` from source `index` with its label, if it is to be passed on now.
+    pub fn arrive(&mut self, index: usize, line: &str) -> Option<String> {
+        let source = &mut self.list[index];
+        source.lines += 1;
+        let labelled = format!("{} {}", source.prefix, line);
+        match source.state {
+            State::Live => Some(labelled),
+            State::Paused => {
+                if source.held.len() == HELD_LINES {
+                    source.held.pop_front();
+                    source.dropped += 1;
+                }
+                source.held.push_back(labelled);
+                None
+            }
+            State::Muted => {
+                source.dropped += 1;
+                None
+            }
+        }
+    }
+
+    /// Pause the selected source, or resume it, returning the lines it held.
+    pub fn toggle_pause(&mut self) -> Vec<String> {
+        let Some(source) = self.list.get_mut(self.selected) else {
+            return Vec::new();
+        };
+        match source.state {
+            State::Paused => {
+                source.state = State::Live;
+                source.held.drain(..).collect()
+            }
+            _ => {
+                source.state = State::Paused;
+                Vec::new()
+            }
+        }
+    }
+
+    /// Mute the selected source, or unmute it. Lines held while paused are dropped.
+    pub fn toggle_mute(&mut self) {
+        let Some(source) = self.list.get_mut(self.selected) else {
+            return;
+        };
+        source.state = match source.state {
+            State::Muted => State::Live,
+            _ => {
+                source.dropped += source.held.len() as u64;
+                source.held.clear();
+                State::Muted
+            }
+        };
+    }
+
+    pub fn select(&mut self, down: bool) {
+        self.selected = match down {
+            true => (self.selected + 1).min(self.list.len().saturating_sub(1)),
+            false => self.selected.saturating_sub(1),
+        };
+    }
+
+    /// The overlay listing the sources, with the selected one in reverse video.
+    pub fn panel(&self) -> Vec<String> {
+        let mut panel = vec![
+            "\x1B[1mSources\x1B[0m  (j/k select, space pauses, m mutes, o closes)".to_string(),
+        ];
+        for (index, source) in self.list.iter().enumerate() {
+            let state = match source.state {
+                State::Live => "live".to_string(),
+                State::Paused => format!("paused, {} held", source.held.len()),
+                State::Muted => "muted".to_string(),
+            };
+            let mut line = format!(
+                "[{}]  {}  {} lines, {} dropped",
+                source.label,
+                state,
+                crate::progress::format_count(source.lines),
+                crate::progress::format_count(source.dropped)
+            );
+            if let Some(ended) = &source.ended {
+                line.push_str(&format!("  ({})", ended));
+            }
+            panel.push(match index == self.selected {
+                true => format!("\x1B[7m{}\x1B[0m", line),
+                false => line,
+            });
+        }
+        panel
+    }
+}
+
+/// Read source `index` in the background, handing each line to `pass`, and note in
+/// `sources` why it ended.
+pub fn spawn(
+    source: cli::Source,
+    index: usize,
+    sources: Arc<Mutex<Sources>>,
+    pass: impl Fn(&str) + Send + 'static,
+) {
+    thread::spawn(move || {
+        let ended = match source {
+            cli::Source::File(path) => match File::open(&path) {
+                Ok(file) => {
+                    follow(BufReader::new(file), &pass);
+                    "read error".to_string()
+                }
+                Err(e) => e.to_string(),
+            },
+            cli::Source::Exec(command) => {
+                let child = Command::new("sh")
+                    .arg("-c")
+                    .arg(format!("{} 2>&1", command))
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .spawn();
+                match child {
+                    Ok(mut child) => {
+                        let stdout = child.stdout.take().unwrap();
+                        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                            pass(line.trim_end_matches('\r'));
+                        }
+                        match child.wait() {
+                            Ok(status) => status.to_string(),
+                            Err(e) => e.to_string(),
+                        }
+                    }
+                    Err(e) => e.to_string(),
+                }
+            }
+        };
+        sources.lock().unwrap().list[index].ended = Some(ended);
+    });
+}
+
+/// Pass on each line of `input`, waiting for more at the end as `tail -f` does, until a
+/// read fails.
+fn follow(mut input: impl BufRead, pass: impl Fn(&str)) {
+    let mut partial = Vec::new();
+    loop {
+        match input.read_until(b'\n', &mut partial) {
+            Ok(0) => thread::sleep(FOLLOW_INTERVAL),
+            // A line still being written is finished on a later read.
+            Ok(_) if partial.last() != Some(&b'\n') => {}
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&partial);
+                pass(line.trim_end_matches('\n').trim_end_matches('\r'));
+                partial.clear();
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_and_mute() {
+        let mut sources = Sources::new(vec!["api".to_string(), "db".to_string()], &[]);
+        assert_eq!(sources.arrive(0, "up").as_deref(), Some("[api] up"));
+        sources.toggle_pause();
+        assert_eq!(sources.arrive(0, "held"), None);
+        assert_eq!(sources.arrive(1, "other").as_deref(), Some("[db] other"));
+        assert_eq!(sources.toggle_pause(), ["[api] held"]);
+        sources.select(true);
+        sources.toggle_mute();
+        assert_eq!(sources.arrive(1, "noise"), None);
+        assert_eq!((sources.list[1].lines, sources.list[1].dropped), (2, 1));
+        assert_eq!(
+            label(&cli::Source::Exec(
+                "/usr/bin/kubectl logs -f api".to_string()
+            )),
+            "kubectl"
+        );
+    }
+
+    #[test]
+    fn test_styles() {
+        let styles = [SourceStyle {
+            name: "app.log".to_string(),
+            label: Some("app".to_string()),
+            color: Some("36".to_string()),
+            prefix: Some("{label} |".to_string()),
+            muted: false,
+        }];
+        let mut sources = Sources::new(vec!["app.log".to_string(), "db".to_string()], &styles);
+        assert_eq!(sources.arrive(0, "up").as_deref(), Some("app | up"));
+        assert_eq!(sources.list[0].label, "app");
+        assert_eq!(sources.colors(), [("app |".to_string(), "36".to_string())]);
+        let muted = [SourceStyle {
+            name: "db".to_string(),
+            muted: true,
+            ..SourceStyle::default()
+        }];
+        let mut sources = Sources::new(vec!["db".to_string()], &muted);
+        assert_eq!(sources.arrive(0, "noise"), None);
+    }
+}
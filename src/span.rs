@@ -0,0 +1,108 @@
+//! Time between a start and an end line sharing an ID, such as a request's lifecycle.
+
+use crate::field::FieldStats;
+use regex::Regex;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Starts still waiting for their end line; the oldest are forgotten beyond this.
+const MAX_OPEN: usize = 100_000;
+
+/// Pairs start and end lines by the ID each pattern captures.
+#[derive(Debug, Clone)]
+pub struct SpanTracker {
+    start: Regex,
+    end: Regex,
+    open: HashMap<String, Instant>,
+    /// Elapsed milliseconds of every completed pair.
+    pub durations: FieldStats,
+    /// The most recently completed pair.
+    pub last: Option<(String, Duration)>,
+}
+
+impl SpanTracker {
+    /// Both patterns must capture the correlating ID in their first group.
+    pub fn new(start: &str, end: &str) -> Result<SpanTracker, String> {
+        let compile = |pattern: &str| {
+            let regex = Regex::new(pattern).map_err(|e| format!("invalid regex: {}", e))?;
+            if regex.captures_len() < 2 {
+                return Err(format!("'{}' needs a capture group for the ID", pattern));
+            }
+            Ok(regex)
+        };
+        Ok(SpanTracker {
+            start: compile(start)?,
+            end: compile(end)?,
+            open: HashMap::new(),
+            durations: FieldStats::default(),
+            last: None,
+        })
+    }
+
+    /// Look for a start or end in `line`, returning the pair it completes, if any.
+    pub fn record(&mut self, line: &str, now: Instant) -> Option<(String, Duration)> {
+        let id = |regex: &Regex| {
+            let captures = regex.captures(line)?;
+            Some(captures.get(1)?.as_str().to_string())
+        };
+        if let Some(id) = id(&self.end) {
+            if let Some(started) = self.open.remove(&id) {
+                let elapsed = now.saturating_duration_since(started);
+                self.durations.record(elapsed.as_secs_f64() * 1000.0);
+                self.last = Some((id, elapsed));
+                return self.last.clone();
+            }
+        }
+        if let Some(id) = id(&self.start) {
+            if self.open.len() >= MAX_OPEN && !self.open.contains_key(&id) {
+                if let Some(oldest) = self.open.iter().min_by_key(|(_, t)| **t) {
+                    let oldest = oldest.0.clone();
+                    self.open.remove(&oldest);
+                }
+            }
+            self.open.insert(id, now);
+        }
+        None
+    }
+
+    /// Status area summary of the completed pairs, in milliseconds.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("span ms {}", self.durations.summary());
+        if let Some((id, elapsed)) = &self.last {
+            summary.push_str(&format!(" last {}={}ms", id, elapsed.as_millis()));
+        }
+        summary.push_str(&format!(" open={}", self.open.len()));
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_span_pairs() {
+        let mut spans = SpanTracker::new(r"begin id=(\w+)", r"done id=(\w+)").unwrap();
+        let start = Instant::now();
+        assert_eq!(spans.record("begin id=a", start), None);
+        assert_eq!(spans.record("begin id=b", start), None);
+        assert_eq!(spans.record("done id=c", start), None);
+        let later = start + Duration::from_millis(250);
+        assert_eq!(
+            spans.record("done id=b", later),
+            Some(("b".to_string(), Duration::from_millis(250)))
+        );
+        assert_eq!(spans.record("done id=b", later), None);
+        assert_eq!(spans.durations.count, 1);
+        assert_eq!(
+            spans.summary(),
+            "span ms n=1 min=250 max=250 avg=250 p95=250 last b=250ms open=1"
+        );
+    }
+
+    #[test]
+    fn test_span_needs_capture_group() {
+        assert!(SpanTracker::new("begin", r"done (\d+)").is_err());
+        assert!(SpanTracker::new(r"begin (\d+)", "(").is_err());
+    }
+}
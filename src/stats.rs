@@ -0,0 +1,237 @@
+//! Running counts of what has flowed through the pipe.
+
+use crate::distinct::Distinct;
+use crate::field::FieldStats;
+use crate::histogram::{Histogram, Resolution};
+use crate::level::Counts;
+use crate::span::SpanTracker;
+use crate::topk::TopK;
+use crate::width;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+/// Matches seen for one pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternStats {
+    pub pattern: String,
+    pub count: u64,
+    pub first_seen: Option<Instant>,
+    pub last_seen: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stats {
+    pub started: Instant,
+    pub lines: u64,
+    /// Lines dropped by --max-rate or that could not be written to the output.
+    pub dropped: u64,
+    /// Patterns in the order they were first counted.
+    pub patterns: Vec<PatternStats>,
+    /// Lines matching any pattern, over time.
+    pub histogram: Histogram,
+    /// Most frequent lines.
+    pub topk: TopK,
+    /// Aggregates of the `--field` value, when one is configured.
+    pub field: FieldStats,
+    /// Distinct values of the `--distinct` field.
+    pub distinct: Distinct,
+    /// Start-to-end latencies, when `--span-start`/`--span-end` are given.
+    pub spans: Option<SpanTracker>,
+    /// Lines of each log level, with `--level-counts`.
+    pub levels: Counts,
+}
+
+impl Stats {
+    pub fn new(started: Instant) -> Self {
+        Stats {
+            started,
+            lines: 0,
+            dropped: 0,
+            patterns: Vec::new(),
+            histogram: Histogram::new(started),
+            topk: TopK::new(10_000),
+            field: FieldStats::default(),
+            distinct: Distinct::default(),
+            spans: None,
+            levels: Counts::default(),
+        }
+    }
+
+    /// Count `line` for each of `patterns` it matched, as the filter decided it, adding
+    /// patterns not seen before.
+    pub fn record<S: AsRef<str>>(&mut self, line: &str, patterns: &[(S, bool)], now: Instant) {
+        self.lines += 1;
+        self.topk.record(line);
+        let mut matched = false;
+        for (pattern, found) in patterns {
+            let pattern = pattern.as_ref();
+            if pattern.is_empty() {
+                continue;
+            }
+            let index = match self.patterns.iter().position(|p| p.pattern == pattern) {
+                Some(index) => index,
+                None => {
+                    self.patterns.push(PatternStats {
+                        pattern: pattern.to_string(),
+                        count: 0,
+                        first_seen: None,
+                        last_seen: None,
+                    });
+                    self.patterns.len() - 1
+                }
+            };
+            if *found {
+                matched = true;
+                let entry = &mut self.patterns[index];
+                entry.count += 1;
+                entry.first_seen.get_or_insert(now);
+                entry.last_seen = Some(now);
+            }
+        }
+        if matched {
+            self.histogram.record(now);
+        }
+    }
+
+    /// Render the per-pattern table shown by the `stats` command.
+    pub fn table(&self, now: Instant) -> Vec<String> {
+        let pattern_width = self
+            .patterns
+            .iter()
+            .map(|p| width::width(&p.pattern))
+            .chain(["pattern".len()])
+            .max()
+            .unwrap_or(0);
+        let mut rows = vec![
+            format!(
+                "{:<pattern_width$}  {:>10}  {:>10}  {:>10}",
+                "pattern", "count", "first", "last"
+            ),
+            format!(
+                "{:<pattern_width$}  {:>10}  {:>10}  {:>10}",
+                "-".repeat(pattern_width),
+                "-----",
+                "-----",
+                "----"
+            ),
+        ];
+        for p in &self.patterns {
+            let first = p
+                .first_seen
+                .map(|t| format!("+{}", format_duration(t - self.started)))
+                .unwrap_or_else(|| "-".to_string());
+            let last = p
+                .last_seen
+                .map(|t| format!("{} ago", format_duration(now - t)))
+                .unwrap_or_else(|| "-".to_string());
+            rows.push(format!(
+                "{}  {:>10}  {:>10}  {:>10}",
+                width::pad(&p.pattern, pattern_width),
+                p.count,
+                first,
+                last
+            ));
+        }
+        rows.push(String::new());
+        rows.push(format!(
+            "{} lines in {}",
+            self.lines,
+            format_duration(now - self.started)
+        ));
+        rows
+    }
+
+    /// Summary of the session written by `--stats-json`.
+    pub fn summary(&mut self, now: Instant) -> Value {
+        let offset = |t: Option<Instant>| t.map(|t| (t - self.started).as_secs_f64());
+        let patterns: Vec<Value> = self
+            .patterns
+            .iter()
+            .map(|p| {
+                json!({
+                    "pattern": p.pattern,
+                    "count": p.count,
+                    "first_seen_secs": offset(p.first_seen),
+                    "last_seen_secs": offset(p.last_seen),
+                })
+            })
+            .collect();
+        let mut summary = json!({
+            "duration_secs": (now - self.started).as_secs_f64(),
+            "lines": self.lines,
+            "dropped": self.dropped,
+            "patterns": patterns,
+            "matches_per_minute": self.histogram.counts(Resolution::Minute, now),
+        });
+        if !self.distinct.is_empty() {
+            summary["distinct"] = json!({
+                "count": self.distinct.count(),
+                "estimated": self.distinct.is_estimate(),
+            });
+        }
+        if self.field.count > 0 {
+            summary["field"] = json!({
+                "count": self.field.count,
+                "min": self.field.min,
+                "max": self.field.max,
+                "avg": self.field.mean(),
+                "p95": self.field.p95(),
+            });
+        }
+        summary
+    }
+}
+
+/// Compact duration such as `42s`, `3m05s` or `2h10m`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_counts_matches() {
+        let start = Instant::now();
+        let mut stats = Stats::new(start);
+        let later = start + Duration::from_secs(5);
+        stats.record("an ERROR here", &[("ERROR", true), ("WARN", false)], start);
+        stats.record("ERROR again", &[("ERROR", true), ("WARN", false)], later);
+        stats.record("nothing", &[("ERROR", false)], later);
+
+        assert_eq!(stats.lines, 3);
+        assert_eq!(stats.patterns[0].count, 2);
+        assert_eq!(stats.patterns[0].first_seen, Some(start));
+        assert_eq!(stats.patterns[0].last_seen, Some(later));
+        assert_eq!(stats.patterns[1].count, 0);
+        assert_eq!(stats.patterns[1].first_seen, None);
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let start = Instant::now();
+        let mut stats = Stats::new(start);
+        stats.record("ERROR one", &[("ERROR", true)], start);
+        stats.dropped = 1;
+        let summary = stats.summary(start + Duration::from_secs(61));
+        assert_eq!(summary["lines"], 1);
+        assert_eq!(summary["dropped"], 1);
+        assert_eq!(summary["patterns"][0]["pattern"], "ERROR");
+        assert_eq!(summary["patterns"][0]["count"], 1);
+        assert_eq!(summary["matches_per_minute"], json!([1, 0]));
+        assert!(summary.get("field").is_none());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(185)), "3m05s");
+        assert_eq!(format_duration(Duration::from_secs(7800)), "2h10m");
+    }
+}
@@ -0,0 +1,117 @@
+//! Find and replace on the lines shown, previewed against recent lines before it applies.
+
+use regex::Regex;
+use std::borrow::Cow;
+
+/// A regex and what to put in place of each match; `$1` or `${name}` bring back a group.
+#[derive(Debug, Clone)]
+pub struct Substitution {
+    regex: Regex,
+    replacement: String,
+    /// The text it was parsed from, as typed.
+    pub source: String,
+}
+
+impl Substitution {
+    /// Parse `pattern/replacement`, where `\/` is a slash in the pattern. Empty text is no
+    /// substitution at all.
+    pub fn parse(text: &str) -> Result<Option<Substitution>, String> {
+        if text.is_empty() {
+            return Ok(None);
+        }
+        let mut pattern = String::new();
+        let mut chars = text.char_indices();
+        let mut replacement = None;
+        while let Some((at, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, '/')) => pattern.push('/'),
+                    Some((_, next)) => {
+                        pattern.push('\\');
+                        pattern.push(next);
+                    }
+                    None => pattern.push('\\'),
+                },
+                '/' => {
+                    replacement = Some(&text[at + 1..]);
+                    break;
+                }
+                c => pattern.push(c),
+            }
+        }
+        let Some(replacement) = replacement else {
+            return Err("expected pattern/replacement".to_string());
+        };
+        let regex = Regex::new(&pattern).map_err(|e| format!("bad pattern: {}", e))?;
+        Ok(Some(Substitution {
+            regex,
+            replacement: replacement.to_string(),
+            source: text.to_string(),
+        }))
+    }
+
+    pub fn apply<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        self.regex.replace_all(line, self.replacement.as_str())
+    }
+
+    /// `line` with what would be replaced struck through in red and what would take its
+    /// place underlined in green, or `None` if it would not change.
+    pub fn preview(&self, line: &str) -> Option<String> {
+        let mut preview = String::new();
+        let mut copied = 0;
+        for captures in self.regex.captures_iter(line) {
+            let found = captures.get(0).unwrap();
+            let mut inserted = String::new();
+            captures.expand(&self.replacement, &mut inserted);
+            if inserted == found.as_str() {
+                continue;
+            }
+            preview.push_str(&line[copied..found.start()]);
+            if !found.is_empty() {
+                preview.push_str(&format!("\x1B[9;31m{}\x1B[0m", found.as_str()));
+            }
+            if !inserted.is_empty() {
+                preview.push_str(&format!("\x1B[4;32m{}\x1B[0m", inserted));
+            }
+            copied = found.end();
+        }
+        if preview.is_empty() {
+            return None;
+        }
+        preview.push_str(&line[copied..]);
+        Some(preview)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_apply() {
+        let substitution = Substitution::parse(r"user=(\w+)/user=<$1>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            substitution.apply("login user=bob ok"),
+            "login user=<bob> ok"
+        );
+        let slashes = Substitution::parse(r"a\/b/c").unwrap().unwrap();
+        assert_eq!(slashes.apply("/a/b/"), "/c/");
+        assert!(Substitution::parse("").unwrap().is_none());
+        assert!(Substitution::parse("no slash").is_err());
+        assert!(Substitution::parse("(/x").is_err());
+    }
+
+    #[test]
+    fn test_preview_marks_changes() {
+        let substitution = Substitution::parse("cat/dog").unwrap().unwrap();
+        assert_eq!(
+            substitution.preview("a cat sat").as_deref(),
+            Some("a \x1B[9;31mcat\x1B[0m\x1B[4;32mdog\x1B[0m sat")
+        );
+        assert_eq!(substitution.preview("a dog sat"), None);
+        let same = Substitution::parse("(dog)/$1").unwrap().unwrap();
+        assert_eq!(same.preview("a dog"), None);
+    }
+}
@@ -0,0 +1,233 @@
+//! Raw syslog lines: the `<PRI>` prefix decoded, RFC 5424 headers tidied, severity colored.
+
+use crate::theme::Theme;
+
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+const SEVERITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// A syslog line split into its priority and the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub facility: u8,
+    pub severity: u8,
+    /// The line after the priority, with an RFC 5424 header reduced to its present fields.
+    pub text: String,
+}
+
+/// Decode `line` if it starts with a `<PRI>` prefix.
+pub fn parse(line: &str) -> Option<Message> {
+    let rest = line.strip_prefix('<')?;
+    let (pri, rest) = rest.split_once('>')?;
+    if pri.is_empty() || pri.len() > 3 || !pri.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let pri: u8 = pri.parse().ok().filter(|pri| *pri < 192)?;
+    let text = match rest.strip_prefix("1 ") {
+        Some(header) => rfc5424(header),
+        None => rest.to_string(),
+    };
+    Some(Message {
+        facility: pri / 8,
+        severity: pri % 8,
+        text,
+    })
+}
+
+/// `TIMESTAMP HOST APP PROCID MSGID [SD] MSG` with the `-` placeholders left out.
+fn rfc5424(header: &str) -> String {
+    let mut parts = header.splitn(5, ' ');
+    let mut next = || parts.next().filter(|part| *part != "-");
+    let (timestamp, host, app, procid) = (next(), next(), next(), next());
+    let rest = next().unwrap_or("");
+    let (msgid, rest) = match rest.split_once(' ') {
+        Some((msgid, rest)) => (Some(msgid).filter(|id| *id != "-"), rest),
+        None => (Some(rest).filter(|id| *id != "-" && !id.is_empty()), ""),
+    };
+    let (data, message) = structured_data(rest);
+
+    let mut text: Vec<String> = [timestamp, host]
+        .iter()
+        .flatten()
+        .map(|s| s.to_string())
+        .collect();
+    match (app, procid) {
+        (Some(app), Some(procid)) => text.push(format!("{}[{}]:", app, procid)),
+        (Some(app), None) => text.push(format!("{}:", app)),
+        (None, _) => {}
+    }
+    text.extend(msgid.map(str::to_string));
+    text.extend(data.filter(|data| *data != "-").map(str::to_string));
+    text.extend(
+        Some(message.trim_start_matches('\u{feff}'))
+            .filter(|m| !m.is_empty())
+            .map(str::to_string),
+    );
+    text.join(" ")
+}
+
+/// Split off the structured data, `-` or one or more `[id key="value"]` elements.
+fn structured_data(rest: &str) -> (Option<&str>, &str) {
+    if let Some(message) = rest.strip_prefix('-') {
+        return (Some("-"), message.trim_start());
+    }
+    let mut end = 0;
+    let mut escaped = false;
+    let mut depth = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' if depth == 0 => depth = 1,
+            ']' if depth == 1 => {
+                depth = 0;
+                end = i + 1;
+            }
+            _ if depth == 0 => break,
+            _ => {}
+        }
+    }
+    match end {
+        0 => (None, rest),
+        _ => (Some(&rest[..end]), rest[end..].trim_start()),
+    }
+}
+
+impl Message {
+    /// `facility.severity`, as in syslog.conf.
+    pub fn label(&self) -> String {
+        format!(
+            "{}.{}",
+            FACILITIES[self.facility as usize], SEVERITIES[self.severity as usize]
+        )
+    }
+
+    /// The message as pieces of text with the SGR parameters to color them with, the
+    /// label marked by severity in `theme`'s colors and symbols.
+    pub fn tokens(&self, theme: Theme) -> Vec<(String, Option<&'static str>)> {
+        let label = match theme.symbol(self.severity) {
+            Some(symbol) => format!("{} {}", symbol, self.label()),
+            None => self.label(),
+        };
+        vec![
+            (label, theme.severity(self.severity)),
+            (" ".to_string(), None),
+            (self.text.clone(), None),
+        ]
+    }
+}
+
+/// Which messages to show, by facility and minimum severity.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Show messages at this severity or more severe (lower numbers).
+    max_severity: Option<u8>,
+    /// Show only these facilities; empty for all.
+    facilities: Vec<u8>,
+}
+
+impl Filter {
+    /// `severity` is a single name, `facilities` a comma-separated list of names.
+    pub fn new(severity: Option<&str>, facilities: Option<&str>) -> Result<Filter, String> {
+        let position = |names: &[&str], name: &str| {
+            names
+                .iter()
+                .position(|n| n.eq_ignore_ascii_case(name.trim()))
+                .map(|i| i as u8)
+        };
+        let max_severity = match severity {
+            Some(name) => Some(
+                position(&SEVERITIES, name)
+                    .ok_or_else(|| format!("unknown severity '{}'", name))?,
+            ),
+            None => None,
+        };
+        let facilities = facilities
+            .unwrap_or("")
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| {
+                position(&FACILITIES, name).ok_or_else(|| format!("unknown facility '{}'", name))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Filter {
+            max_severity,
+            facilities,
+        })
+    }
+
+    pub fn allows(&self, message: &Message) -> bool {
+        self.max_severity.is_none_or(|max| message.severity <= max)
+            && (self.facilities.is_empty() || self.facilities.contains(&message.facility))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bsd() {
+        let message = parse("<34>Oct 11 22:14:15 mymachine su: 'su root' failed").unwrap();
+        assert_eq!(message.facility, 4);
+        assert_eq!(message.severity, 2);
+        assert_eq!(message.label(), "auth.crit");
+        assert_eq!(
+            message.text,
+            "Oct 11 22:14:15 mymachine su: 'su root' failed"
+        );
+        assert_eq!(parse("no priority"), None);
+        assert_eq!(parse("<999>too big"), None);
+    }
+
+    #[test]
+    fn test_parse_rfc5424() {
+        let message = parse(
+            r#"<165>1 2003-10-11T22:14:15.003Z host evntslog - ID47 [exampleSDID@32473 iut="3"] An application event"#,
+        )
+        .unwrap();
+        assert_eq!(message.label(), "local4.notice");
+        assert_eq!(
+            message.text,
+            r#"2003-10-11T22:14:15.003Z host evntslog: ID47 [exampleSDID@32473 iut="3"] An application event"#
+        );
+        let message = parse("<13>1 - - app 42 - - hello").unwrap();
+        assert_eq!(message.text, "app[42]: hello");
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = Filter::new(Some("warning"), Some("auth,daemon")).unwrap();
+        assert!(filter.allows(&parse("<34>x").unwrap()));
+        assert!(!filter.allows(&parse("<38>x").unwrap()));
+        assert!(!filter.allows(&parse("<10>x").unwrap()));
+        assert!(Filter::new(Some("loud"), None).is_err());
+        assert!(Filter::new(None, Some("auth,nope")).is_err());
+    }
+}
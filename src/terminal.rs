@@ -0,0 +1,335 @@
+//! Platform specific access to the controlling terminal.
+//!
+//! Output from the pipe goes to stdout, while the status area and key input use the
+//! terminal directly: `/dev/tty` on Unix and the `CONOUT$`/`CONIN$` console devices on
+//! Windows.
+
+use std::io::{self, Write};
+
+#[cfg(unix)]
+pub use unix::*;
+#[cfg(windows)]
+pub use windows::*;
+
+/// Width and height of the terminal in cells.
+///
+/// Some ptys (notably serial consoles and freshly created BSD ptys) report a size of
+/// zero; fall back to `$COLUMNS`/`$LINES` and then to 80x24 rather than trusting it.
+pub fn size() -> io::Result<(u16, u16)> {
+    let (columns, rows) = crossterm::terminal::size()?;
+    if columns > 0 && rows > 0 {
+        return Ok((columns, rows));
+    }
+    let from_env = |name: &str, default: u16| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(default)
+    };
+    Ok((from_env("COLUMNS", 80), from_env("LINES", 24)))
+}
+
+/// Name of the terminal multiplexer we are running inside, if any.
+///
+/// tmux and screen keep their own idea of the scroll region and saved cursor per pane,
+/// which can drift from ours when panes are resized or switched.
+pub fn multiplexer() -> Option<&'static str> {
+    let term = std::env::var("TERM").unwrap_or_default();
+    if std::env::var_os("TMUX").is_some() || term.starts_with("tmux") {
+        Some("tmux")
+    } else if std::env::var_os("STY").is_some() || term.starts_with("screen") {
+        Some("screen")
+    } else {
+        None
+    }
+}
+
+/// Restrict scrolling to the zero based rows `top..=bottom`.
+pub fn set_scroll_region<W: Write>(out: &mut W, top: u16, bottom: u16) -> io::Result<()> {
+    write!(out, "\x1B[{};{}r", top + 1, bottom + 1)?;
+    out.flush()
+}
+
+pub fn reset_scroll_region<W: Write>(out: &mut W) -> io::Result<()> {
+    write!(out, "\x1B[0r")?;
+    out.flush()
+}
+
+/// Ask the terminal to mark pasted text, so it can be told apart from typed keys.
+pub fn set_bracketed_paste<W: Write>(out: &mut W, enabled: bool) -> io::Result<()> {
+    write!(out, "\x1B[?2004{}", if enabled { 'h' } else { 'l' })?;
+    out.flush()
+}
+
+/// Ring the bell and ask for a desktop notification, with both the OSC 777 form (VTE,
+/// foot, ghostty) and the OSC 9 form (iTerm2, WezTerm); terminals ignore the ones they
+/// do not know.
+pub fn alert<W: Write>(out: &mut W, message: &str) -> io::Result<()> {
+    let message: String = message.chars().filter(|c| !c.is_control()).collect();
+    write!(
+        out,
+        "\x07\x1B]777;notify;pipe_tools;{}\x07\x1B]9;{}\x07",
+        message.replace(';', ","),
+        message
+    )?;
+    out.flush()
+}
+
+/// Set the window or tab title. With `save`, the title before it is kept first, and
+/// `restore_title` brings it back.
+pub fn set_title<W: Write>(out: &mut W, title: &str, save: bool) -> io::Result<()> {
+    if save {
+        write!(out, "\x1B[22;2t")?;
+    }
+    write!(out, "\x1B]2;{}\x07", title)?;
+    out.flush()
+}
+
+pub fn restore_title<W: Write>(out: &mut W) -> io::Result<()> {
+    write!(out, "\x1B[23;2t")?;
+    out.flush()
+}
+
+#[cfg(unix)]
+mod unix {
+    use nix::errno::Errno;
+    use nix::fcntl::{fcntl, FcntlArg, OFlag};
+    use nix::sys::signal::{signal, SigHandler, Signal};
+    use nix::sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg, Termios};
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read};
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::time::Duration;
+
+    /// Ignore SIGPIPE so broken stdout does not panic.
+    pub fn ignore_sigpipe() {
+        let _ = unsafe { signal(Signal::SIGPIPE, SigHandler::SigIgn) };
+    }
+
+    pub fn open_output() -> io::Result<File> {
+        OpenOptions::new().write(true).open("/dev/tty")
+    }
+
+    pub fn open_input() -> io::Result<File> {
+        OpenOptions::new().read(true).append(true).open("/dev/tty")
+    }
+
+    /// Terminal settings in effect before `RawMode::enable`, restored on drop.
+    pub struct RawMode {
+        fd: RawFd,
+        original: Termios,
+    }
+
+    impl RawMode {
+        /// Turn off line buffering and echo, and make reads from `tty` nonblocking.
+        pub fn enable(tty: &File) -> io::Result<RawMode> {
+            let fd = tty.as_raw_fd();
+
+            // Readiness is checked before every read, so O_NONBLOCK is only a safety net;
+            // carry on if the tty driver refuses it.
+            if let Ok(flags) = fcntl(fd, FcntlArg::F_GETFL) {
+                let flags = OFlag::from_bits_truncate(flags);
+                let _ = fcntl(fd, FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK));
+            }
+
+            let original = tcgetattr(fd)?;
+            let mut raw = original.clone();
+            raw.local_flags.remove(LocalFlags::ICANON);
+            raw.local_flags.remove(LocalFlags::ECHO);
+            tcsetattr(fd, SetArg::TCSANOW, &raw)?;
+
+            Ok(RawMode { fd, original })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            let _ = tcsetattr(self.fd, SetArg::TCSANOW, &self.original);
+        }
+    }
+
+    /// Bytes typed at the terminal, read without blocking the caller indefinitely.
+    pub struct KeyReader {
+        tty: File,
+    }
+
+    impl KeyReader {
+        pub fn new(tty: File) -> Self {
+            KeyReader { tty }
+        }
+
+        /// Wait up to `timeout` for the next byte typed at the terminal.
+        pub fn read_byte(&mut self, timeout: Duration) -> io::Result<Option<u8>> {
+            match wait_readable(self.tty.as_raw_fd(), timeout) {
+                Ok(false) | Err(Errno::EINTR) => return Ok(None),
+                Ok(true) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let mut buf = [0u8; 1];
+            match self.tty.read(&mut buf) {
+                Ok(1) => Ok(Some(buf[0])),
+                Ok(_) => Err(io::ErrorKind::UnexpectedEof.into()),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for `fd` to become readable.
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    fn wait_readable(fd: RawFd, timeout: Duration) -> nix::Result<bool> {
+        use nix::poll::{poll, PollFd, PollFlags};
+
+        let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+        Ok(poll(&mut fds, timeout.as_millis() as i32)? > 0)
+    }
+
+    /// Wait up to `timeout` for `fd` to become readable.
+    ///
+    /// poll() on macOS reports POLLNVAL for /dev/tty, so use select() there instead.
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    fn wait_readable(fd: RawFd, timeout: Duration) -> nix::Result<bool> {
+        use nix::sys::select::{select, FdSet};
+        use nix::sys::time::{TimeVal, TimeValLike};
+
+        let mut fds = FdSet::new();
+        fds.insert(fd);
+        let mut timeout = TimeVal::milliseconds(timeout.as_millis() as i64);
+        Ok(select(fd + 1, &mut fds, None, None, &mut timeout)? > 0)
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read};
+    use std::os::windows::io::AsRawHandle;
+    use std::time::{Duration, Instant};
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::winerror::WAIT_TIMEOUT;
+    use winapi::um::consoleapi::{GetConsoleMode, ReadConsoleInputW, SetConsoleMode};
+    use winapi::um::synchapi::WaitForSingleObject;
+    use winapi::um::winbase::WAIT_OBJECT_0;
+    use winapi::um::wincon::{
+        PeekConsoleInputW, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT,
+        ENABLE_VIRTUAL_TERMINAL_INPUT,
+    };
+    use winapi::um::wincontypes::{INPUT_RECORD, KEY_EVENT};
+    use winapi::um::winnt::HANDLE;
+
+    /// Windows has no SIGPIPE; writes to a closed pipe simply fail.
+    pub fn ignore_sigpipe() {}
+
+    pub fn open_output() -> io::Result<File> {
+        OpenOptions::new().read(true).write(true).open("CONOUT$")
+    }
+
+    pub fn open_input() -> io::Result<File> {
+        OpenOptions::new().read(true).write(true).open("CONIN$")
+    }
+
+    /// Console input mode in effect before `RawMode::enable`, restored on drop.
+    pub struct RawMode {
+        tty: File,
+        original: DWORD,
+    }
+
+    impl RawMode {
+        /// Turn off line input and echo, and make sure escape sequences are interpreted.
+        ///
+        /// Keys such as the arrows are asked for as escape sequences, so the key
+        /// listener reads the same bytes as from a Unix tty.
+        pub fn enable(tty: &File) -> io::Result<RawMode> {
+            if !crossterm::ansi_support::supports_ansi() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "console does not support ANSI escape sequences",
+                ));
+            }
+            let handle = tty.as_raw_handle() as HANDLE;
+            let mut original: DWORD = 0;
+            if unsafe { GetConsoleMode(handle, &mut original) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let raw = (original
+                & !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT | ENABLE_PROCESSED_INPUT))
+                | ENABLE_VIRTUAL_TERMINAL_INPUT;
+            if unsafe { SetConsoleMode(handle, raw) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(RawMode {
+                tty: tty.try_clone()?,
+                original,
+            })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            unsafe { SetConsoleMode(self.tty.as_raw_handle() as HANDLE, self.original) };
+        }
+    }
+
+    /// Bytes typed at the console, read without blocking the caller indefinitely.
+    pub struct KeyReader {
+        tty: File,
+    }
+
+    impl KeyReader {
+        pub fn new(tty: File) -> Self {
+            KeyReader { tty }
+        }
+
+        /// Wait up to `timeout` for the next byte typed at the console.
+        pub fn read_byte(&mut self, timeout: Duration) -> io::Result<Option<u8>> {
+            if !self.wait_typed(timeout)? {
+                return Ok(None);
+            }
+
+            let mut buf = [0u8; 1];
+            match self.tty.read(&mut buf)? {
+                1 => Ok(Some(buf[0])),
+                _ => Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+        }
+
+        /// Wait up to `timeout` for a character to be typed.
+        ///
+        /// The console handle is also signalled for key releases, focus and mouse
+        /// events, which a read would block on, so those are discarded along the way.
+        fn wait_typed(&self, timeout: Duration) -> io::Result<bool> {
+            let handle = self.tty.as_raw_handle() as HANDLE;
+            let deadline = Instant::now() + timeout;
+            loop {
+                let left = deadline.saturating_duration_since(Instant::now());
+                // Capped below INFINITE, which would never time out.
+                let millis = left.as_millis().min(u128::from(DWORD::MAX - 1)) as DWORD;
+                match unsafe { WaitForSingleObject(handle, millis) } {
+                    WAIT_OBJECT_0 => {}
+                    WAIT_TIMEOUT => return Ok(false),
+                    _ => return Err(io::Error::last_os_error()),
+                }
+
+                let mut record: INPUT_RECORD = unsafe { std::mem::zeroed() };
+                let mut count: DWORD = 0;
+                if unsafe { PeekConsoleInputW(handle, &mut record, 1, &mut count) } == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if count == 0 {
+                    continue;
+                }
+                if record.EventType == KEY_EVENT {
+                    let key = unsafe { record.Event.KeyEvent() };
+                    if key.bKeyDown != 0 && unsafe { *key.uChar.UnicodeChar() } != 0 {
+                        return Ok(true);
+                    }
+                }
+                if unsafe { ReadConsoleInputW(handle, &mut record, 1, &mut count) } == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+    }
+}
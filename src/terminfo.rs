@@ -0,0 +1,259 @@
+//! Just enough terminfo support to pick the escape sequences the status area needs.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// Indexes into the terminfo string capability table (see term.h).
+const CHANGE_SCROLL_REGION: usize = 3;
+const ENTER_CA_MODE: usize = 28;
+const EXIT_CA_MODE: usize = 40;
+const RESTORE_CURSOR: usize = 126;
+const SAVE_CURSOR: usize = 128;
+
+/// Terminal families known to handle ANSI scroll regions and DEC cursor save/restore.
+const KNOWN_TERMS: [&str; 16] = [
+    "xterm",
+    "screen",
+    "tmux",
+    "rxvt",
+    "linux",
+    "vt100",
+    "vt220",
+    "ansi",
+    "alacritty",
+    "kitty",
+    "foot",
+    "wezterm",
+    "st",
+    "konsole",
+    "gnome",
+    "iterm",
+];
+
+/// The escape sequences used to drive the terminal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Whether the terminal can restrict scrolling to part of the screen.
+    pub scroll_region: bool,
+    pub save_cursor: String,
+    pub restore_cursor: String,
+    /// Switch to and from the alternate screen, when the terminal has one.
+    pub alt_screen: Option<(String, String)>,
+}
+
+impl Capabilities {
+    /// Sequences understood by practically every terminal emulator in use today.
+    pub fn ansi() -> Self {
+        Capabilities {
+            scroll_region: true,
+            save_cursor: "\x1B7".to_string(),
+            restore_cursor: "\x1B8".to_string(),
+            alt_screen: Some(("\x1B[?1049h".to_string(), "\x1B[?1049l".to_string())),
+        }
+    }
+
+    /// A terminal that can do nothing beyond printing text.
+    pub fn none() -> Self {
+        Capabilities {
+            scroll_region: false,
+            save_cursor: String::new(),
+            restore_cursor: String::new(),
+            alt_screen: None,
+        }
+    }
+
+    /// Work out the capabilities of the terminal named by `$TERM`.
+    pub fn detect() -> Self {
+        if cfg!(windows) {
+            return Capabilities::ansi();
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        Capabilities::for_term(&term, load(&term).as_deref())
+    }
+
+    /// Capabilities for `term`, given its compiled terminfo entry if one was found.
+    pub fn for_term(term: &str, entry: Option<&[u8]>) -> Self {
+        if term.is_empty() || term == "dumb" || term == "unknown" {
+            return Capabilities::none();
+        }
+        if let Some(strings) = entry.and_then(parse_strings) {
+            let get = |index: usize| strings.get(index).cloned().flatten();
+            let (save, restore) = match (get(SAVE_CURSOR), get(RESTORE_CURSOR)) {
+                (Some(save), Some(restore)) => (save, restore),
+                _ => (String::new(), String::new()),
+            };
+            return Capabilities {
+                scroll_region: get(CHANGE_SCROLL_REGION).is_some(),
+                save_cursor: save,
+                restore_cursor: restore,
+                alt_screen: get(ENTER_CA_MODE).zip(get(EXIT_CA_MODE)),
+            };
+        }
+        // No terminfo database: trust well-known names, otherwise play it safe.
+        let family = term.split(['-', '.']).next().unwrap_or(term);
+        if KNOWN_TERMS.contains(&family) {
+            Capabilities::ansi()
+        } else {
+            Capabilities {
+                alt_screen: None,
+                ..Capabilities::ansi()
+            }
+        }
+    }
+}
+
+/// Read the compiled terminfo entry for `term` from the usual locations.
+fn load(term: &str) -> Option<Vec<u8>> {
+    let first = term.chars().next()?;
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Some(dir) = env::var_os("TERMINFO") {
+        dirs.push(dir.into());
+    }
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Ok(list) = env::var("TERMINFO_DIRS") {
+        dirs.extend(list.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    }
+    for dir in [
+        "/etc/terminfo",
+        "/lib/terminfo",
+        "/usr/share/terminfo",
+        "/usr/lib/terminfo",
+    ] {
+        dirs.push(dir.into());
+    }
+
+    dirs.iter().find_map(|dir| {
+        // Linux uses the first letter, macOS its hex code, as the subdirectory.
+        fs::read(dir.join(first.to_string()).join(term))
+            .or_else(|_| fs::read(dir.join(format!("{:x}", first as u32)).join(term)))
+            .ok()
+    })
+}
+
+/// Extract the string capabilities from a compiled terminfo entry.
+fn parse_strings(data: &[u8]) -> Option<Vec<Option<String>>> {
+    let word = |at: usize| -> Option<i16> {
+        let bytes = data.get(at..at + 2)?;
+        Some(i16::from_le_bytes([bytes[0], bytes[1]]))
+    };
+    let number_size = match word(0)? {
+        0o432 => 2,
+        0o1036 => 4, // extended number format
+        _ => return None,
+    };
+    let names_size = word(2)? as usize;
+    let bools = word(4)? as usize;
+    let numbers = word(6)? as usize;
+    let strings = word(8)? as usize;
+    let table_size = word(10)? as usize;
+
+    let mut offset = 12 + names_size + bools;
+    offset += offset % 2; // numbers start on an even byte
+    offset += numbers * number_size;
+    let table = data.get(offset + strings * 2..offset + strings * 2 + table_size)?;
+
+    (0..strings)
+        .map(|i| {
+            let start = word(offset + i * 2)?;
+            if start < 0 {
+                return Some(None);
+            }
+            let rest = table.get(start as usize..)?;
+            let end = rest.iter().position(|b| *b == 0)?;
+            Some(Some(strip_padding(&String::from_utf8_lossy(&rest[..end]))))
+        })
+        .collect()
+}
+
+/// Remove `$<5>` style delay specifications, which are meant for the terminfo library.
+fn strip_padding(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("$<") {
+        result.push_str(&rest[..start]);
+        match rest[start..].find('>') {
+            Some(end) => rest = &rest[start + end + 1..],
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a minimal compiled terminfo entry with the given string capabilities.
+    fn entry(caps: &[(usize, &str)]) -> Vec<u8> {
+        let count = caps.iter().map(|(i, _)| i + 1).max().unwrap_or(0);
+        let mut offsets = vec![-1i16; count];
+        let mut table = Vec::new();
+        for (index, value) in caps {
+            offsets[*index] = table.len() as i16;
+            table.extend_from_slice(value.as_bytes());
+            table.push(0);
+        }
+        let names = b"test\0";
+        let mut data = Vec::new();
+        for value in [
+            0o432,
+            names.len() as i16,
+            0,
+            0,
+            count as i16,
+            table.len() as i16,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(names);
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+        for offset in offsets {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data.extend_from_slice(&table);
+        data
+    }
+
+    #[test]
+    fn test_capabilities_from_terminfo() {
+        let data = entry(&[
+            (CHANGE_SCROLL_REGION, "\x1B[%i%p1%d;%p2%dr"),
+            (SAVE_CURSOR, "\x1B[s$<2>"),
+            (RESTORE_CURSOR, "\x1B[u"),
+        ]);
+        let caps = Capabilities::for_term("custom", Some(&data));
+        assert!(caps.scroll_region);
+        assert_eq!(caps.save_cursor, "\x1B[s");
+        assert_eq!(caps.restore_cursor, "\x1B[u");
+        assert_eq!(caps.alt_screen, None);
+
+        let caps = Capabilities::for_term("custom", Some(&entry(&[(5, "\x1B[H")])));
+        assert!(!caps.scroll_region);
+        assert_eq!(caps.save_cursor, "");
+    }
+
+    #[test]
+    fn test_capabilities_fallback() {
+        assert_eq!(Capabilities::for_term("dumb", None), Capabilities::none());
+        assert_eq!(Capabilities::for_term("", None), Capabilities::none());
+        assert_eq!(
+            Capabilities::for_term("xterm-256color", None),
+            Capabilities::ansi()
+        );
+        assert!(Capabilities::for_term("mystery", None).scroll_region);
+        // Garbage entries fall back to the name-based guess.
+        assert_eq!(
+            Capabilities::for_term("tmux", Some(b"junk")),
+            Capabilities::ansi()
+        );
+    }
+}
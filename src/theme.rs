@@ -0,0 +1,117 @@
+//! Color themes, including palettes that stay readable with color-blindness.
+//!
+//! The color-blind themes pick colors that differ in brightness as well as hue, and mark
+//! syslog severities with a symbol too, so no information is carried by color alone.
+
+/// A named set of colors for highlights, severities and `auto` patterns.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Theme {
+    #[default]
+    Default,
+    /// Red-green, with green weak; orange, yellow and blue in place of red and green.
+    Deuteranopia,
+    /// Red-green, with red weak and dark; brighter oranges and yellows.
+    Protanopia,
+    /// Blue-yellow; reds, pinks and teals in place of yellow and blue.
+    Tritanopia,
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::Default),
+            "deuteranopia" => Some(Theme::Deuteranopia),
+            "protanopia" => Some(Theme::Protanopia),
+            "tritanopia" => Some(Theme::Tritanopia),
+            _ => None,
+        }
+    }
+
+    /// SGR parameters highlighting the filter, unless the config sets its own.
+    pub fn highlight(self) -> &'static str {
+        match self {
+            Theme::Default => "37;101",
+            Theme::Deuteranopia => "30;48;5;208",
+            Theme::Protanopia => "30;48;5;227",
+            Theme::Tritanopia => "97;48;5;160",
+        }
+    }
+
+    /// SGR parameters for a syslog severity (0 emergency to 7 debug), if it is colored.
+    pub fn severity(self, severity: u8) -> Option<&'static str> {
+        let colors = match self {
+            Theme::Default => ["1;31", "31", "33", "36", "2"],
+            Theme::Deuteranopia => ["1;38;5;202", "38;5;208", "38;5;220", "38;5;33", "2"],
+            Theme::Protanopia => ["1;38;5;214", "38;5;214", "38;5;227", "38;5;39", "2"],
+            Theme::Tritanopia => ["1;38;5;196", "38;5;160", "38;5;205", "38;5;37", "2"],
+        };
+        match severity {
+            0..=2 => Some(colors[0]),
+            3 => Some(colors[1]),
+            4 => Some(colors[2]),
+            5 => Some(colors[3]),
+            6 => None,
+            _ => Some(colors[4]),
+        }
+    }
+
+    /// Symbol shown before a syslog severity so it can be told apart without color; the
+    /// default theme has none.
+    pub fn symbol(self, severity: u8) -> Option<&'static str> {
+        if self == Theme::Default {
+            return None;
+        }
+        match severity {
+            0..=2 => Some("‼"),
+            3 => Some("✖"),
+            4 => Some("▲"),
+            5 => Some("●"),
+            6 => None,
+            _ => Some("·"),
+        }
+    }
+
+    /// SGR parameters for the `index`th pattern colored `auto`. Each pattern also gets its
+    /// own weight or underline, so neighbours differ by more than hue.
+    pub fn pattern(self, index: usize) -> String {
+        let colors = match self {
+            Theme::Default => ["31", "33", "32", "35", "36", "34"],
+            Theme::Deuteranopia => [
+                "38;5;208", "38;5;33", "38;5;220", "38;5;169", "38;5;75", "38;5;255",
+            ],
+            Theme::Protanopia => [
+                "38;5;214", "38;5;39", "38;5;227", "38;5;183", "38;5;45", "38;5;255",
+            ],
+            Theme::Tritanopia => [
+                "38;5;160", "38;5;37", "38;5;205", "38;5;250", "38;5;124", "38;5;30",
+            ],
+        };
+        // Shifting the style on each trip round the colors keeps 18 patterns distinct.
+        let styles = ["1", "4", "1;4"];
+        let style = (index + index / colors.len()) % styles.len();
+        format!("{};{}", styles[style], colors[index % colors.len()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_stay_distinct() {
+        for theme in [
+            Theme::Default,
+            Theme::Deuteranopia,
+            Theme::Protanopia,
+            Theme::Tritanopia,
+        ] {
+            let styles: Vec<String> = (0..18).map(|index| theme.pattern(index)).collect();
+            for (index, style) in styles.iter().enumerate() {
+                assert!(!styles[..index].contains(style), "{:?} {}", theme, style);
+            }
+        }
+        assert_eq!(Theme::from_name("protanopia"), Some(Theme::Protanopia));
+        assert_eq!(Theme::Deuteranopia.symbol(3), Some("✖"));
+        assert_eq!(Theme::Default.symbol(3), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! Rewriting timestamps in displayed lines into one timezone and format.
+
+use jiff::civil::DateTime;
+use jiff::tz::{Offset, TimeZone};
+use jiff::{Timestamp, Zoned};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Used when `--time-format` is not given.
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f %Z";
+
+/// `--time-format locale`: the date and time as the locale writes them.
+const LOCALE_FORMAT: &str = "%x %X";
+
+/// ISO 8601 / RFC 3339 timestamps, with or without an offset, and the access log format.
+static PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d{1,9})?(?:Z|[+-]\d{2}:?\d{2})?\b|\b\d{2}/[A-Z][a-z]{2}/\d{4}:\d{2}:\d{2}:\d{2} [+-]\d{4}",
+    )
+    .unwrap()
+});
+
+/// Converts the timestamps it finds into `zone` and prints them with `format`.
+#[derive(Debug, Clone)]
+pub struct Rewriter {
+    zone: TimeZone,
+    format: String,
+}
+
+impl Rewriter {
+    /// `zone` is `local`, `UTC`, an IANA name such as `Europe/Berlin`, or an offset
+    /// such as `+05:30`; `format` uses strftime syntax, with `%c`, `%x` and `%X` following
+    /// the `LC_TIME` locale, or is `locale` for `%x %X`.
+    pub fn new(zone: &str, format: Option<&str>) -> Result<Rewriter, String> {
+        let zone = match zone {
+            "local" => TimeZone::system(),
+            "UTC" | "utc" | "Z" => TimeZone::UTC,
+            _ => match parse_offset(zone) {
+                Some(offset) => TimeZone::fixed(offset),
+                None => TimeZone::get(zone)
+                    .map_err(|e| format!("unknown timezone '{}': {}", zone, e))?,
+            },
+        };
+        let format = match format.unwrap_or(DEFAULT_FORMAT) {
+            "locale" => LOCALE_FORMAT,
+            format => format,
+        };
+        Ok(Rewriter {
+            zone,
+            format: localize(format, &time_locale()),
+        })
+    }
+
+    /// `line` with each recognized timestamp converted; timestamps without an offset
+    /// are taken to be UTC.
+    pub fn rewrite(&self, line: &str) -> String {
+        PATTERN
+            .replace_all(line, |captures: &regex::Captures| {
+                let text = &captures[0];
+                match parse(text) {
+                    Some(timestamp) => Zoned::new(timestamp, self.zone.clone())
+                        .strftime(&self.format)
+                        .to_string(),
+                    None => text.to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// The locale for dates and times, as the C library picks it.
+fn time_locale() -> String {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_else(|| "C".to_string())
+}
+
+/// The date and time formats (`%x`, `%X`) of `locale`, such as `de_DE.UTF-8`. Only the
+/// order and separators follow the locale; names of months and days stay English.
+fn locale_formats(locale: &str) -> (&'static str, &'static str) {
+    let name = locale.split(['.', '@']).next().unwrap_or(locale);
+    let (language, territory) = name.split_once('_').unwrap_or((name, ""));
+    let date = match (language, territory) {
+        ("en", "US" | "PH") => "%m/%d/%Y",
+        ("en", "CA") | ("sv" | "lt", _) => "%Y-%m-%d",
+        ("en", _) | ("fr" | "es" | "it" | "pt" | "el", _) => "%d/%m/%Y",
+        ("de" | "ru" | "pl" | "cs" | "sk" | "fi" | "nb" | "no" | "tr" | "uk" | "ro", _) => {
+            "%d.%m.%Y"
+        }
+        ("nl" | "da", _) => "%d-%m-%Y",
+        ("ja" | "zh", _) => "%Y/%m/%d",
+        ("ko" | "hu", _) => "%Y. %m. %d.",
+        _ => "%m/%d/%y",
+    };
+    let time = match (language, territory) {
+        ("en", "US" | "CA" | "AU" | "PH") => "%I:%M:%S %p",
+        _ => "%H:%M:%S",
+    };
+    (date, time)
+}
+
+/// `format` with the locale-dependent `%c`, `%x` and `%X` spelled out for `locale`.
+fn localize(format: &str, locale: &str) -> String {
+    let (date, time) = locale_formats(locale);
+    let mut localized = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            localized.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('x') => localized.push_str(date),
+            Some('X') => localized.push_str(time),
+            Some('c') => localized.push_str(&format!("%a {} {}", date, time)),
+            Some(other) => {
+                localized.push('%');
+                localized.push(other);
+            }
+            None => localized.push('%'),
+        }
+    }
+    localized
+}
+
+fn parse(text: &str) -> Option<Timestamp> {
+    if let Ok(timestamp) = Timestamp::strptime("%d/%b/%Y:%H:%M:%S %z", text) {
+        return Some(timestamp);
+    }
+    let text = text.replacen(',', ".", 1);
+    if let Ok(timestamp) = text.parse::<Timestamp>() {
+        return Some(timestamp);
+    }
+    let naive: DateTime = text.parse().ok()?;
+    naive
+        .to_zoned(TimeZone::UTC)
+        .ok()
+        .map(|zoned| zoned.timestamp())
+}
+
+/// `+05:30`, `-0800` or `+02`.
+fn parse_offset(text: &str) -> Option<Offset> {
+    let sign = match text.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let digits: String = text[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.bytes().all(|b| b.is_ascii_digit()) || !matches!(digits.len(), 2 | 4) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits
+        .get(2..)
+        .filter(|m| !m.is_empty())
+        .map_or(Some(0), |m| m.parse().ok())?;
+    Offset::from_seconds(sign * (hours * 3600 + minutes * 60)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_formats() {
+        let rewriter = Rewriter::new("+02:00", Some("%H:%M:%S")).unwrap();
+        assert_eq!(
+            rewriter.rewrite("at 2024-03-01T10:00:00Z and 2024-03-01 11:30:00.5+01:00 ok"),
+            "at 12:00:00 and 12:30:00 ok"
+        );
+        assert_eq!(
+            rewriter.rewrite("naive 2024-03-01 10:00:00,123 x"),
+            "naive 12:00:00 x"
+        );
+        assert_eq!(
+            rewriter.rewrite(r#"[10/Oct/2000:13:55:36 -0700] "GET /""#),
+            r#"[22:55:36] "GET /""#
+        );
+        assert_eq!(rewriter.rewrite("no time here 12:00"), "no time here 12:00");
+    }
+
+    #[test]
+    fn test_zones() {
+        let rewriter = Rewriter::new("UTC", None).unwrap();
+        assert_eq!(
+            rewriter.rewrite("2024-03-01T10:00:00.250-05:00"),
+            "2024-03-01 15:00:00.25 UTC"
+        );
+        assert!(Rewriter::new("Mars/Olympus", None).is_err());
+        assert!(Rewriter::new("+99:00", None).is_err());
+    }
+
+    #[test]
+    fn test_localize() {
+        assert_eq!(localize("%x %X", "de_DE.UTF-8"), "%d.%m.%Y %H:%M:%S");
+        assert_eq!(localize("%x %X", "en_US.UTF-8"), "%m/%d/%Y %I:%M:%S %p");
+        assert_eq!(localize("%c", "en_GB"), "%a %d/%m/%Y %H:%M:%S");
+        assert_eq!(localize("100%% at %X", "C"), "100%% at %H:%M:%S");
+        let rewriter = Rewriter {
+            zone: TimeZone::UTC,
+            format: localize("%x %X", "ja_JP.UTF-8"),
+        };
+        assert_eq!(
+            rewriter.rewrite("2024-03-01T10:00:00Z"),
+            "2024/03/01 10:00:00"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Most frequent lines, with the variable parts collapsed so similar lines group together.
+
+use std::collections::HashMap;
+
+/// Frequency counts of normalized lines, bounded in memory.
+#[derive(Debug, Clone)]
+pub struct TopK {
+    counts: HashMap<String, u64>,
+    capacity: usize,
+}
+
+impl TopK {
+    pub fn new(capacity: usize) -> Self {
+        TopK {
+            counts: HashMap::new(),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, line: &str) {
+        *self.counts.entry(normalize(line)).or_insert(0) += 1;
+        if self.counts.len() > self.capacity {
+            // Forget the rarest lines; they are the least likely to ever make the top.
+            let min = self.counts.values().copied().min().unwrap_or(0);
+            self.counts.retain(|_, count| *count > min);
+        }
+    }
+
+    /// The `k` most frequent lines, most frequent first.
+    pub fn top(&self, k: usize) -> Vec<(&str, u64)> {
+        let mut entries: Vec<(&str, u64)> = self
+            .counts
+            .iter()
+            .map(|(line, count)| (line.as_str(), *count))
+            .collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        entries.truncate(k);
+        entries
+    }
+
+    /// Render like `sort | uniq -c | sort -rn`, fitting in `height` rows.
+    pub fn render(&self, height: usize) -> Vec<String> {
+        let mut lines = vec![format!(
+            "most frequent lines ({} distinct)",
+            self.counts.len()
+        )];
+        lines.extend(
+            self.top(height.saturating_sub(1))
+                .into_iter()
+                .map(|(line, count)| format!("{:>8} {}", count, line)),
+        );
+        lines
+    }
+}
+
+/// Collapse UUIDs to `<uuid>` and runs of digits to `#`.
+pub fn normalize(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut normalized = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_uuid(&bytes[i..]) && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric()) {
+            normalized.push_str("<uuid>");
+            i += 36;
+        } else if bytes[i].is_ascii_digit() {
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            normalized.push('#');
+        } else {
+            // Copy the whole (possibly multi-byte) character.
+            let ch = line[i..].chars().next().unwrap();
+            normalized.push(ch);
+            i += ch.len_utf8();
+        }
+    }
+    normalized
+}
+
+/// Whether `bytes` starts with a UUID such as `123e4567-e89b-12d3-a456-426614174000`.
+fn is_uuid(bytes: &[u8]) -> bool {
+    const GROUPS: [usize; 5] = [8, 4, 4, 4, 12];
+    if bytes.len() < 36 {
+        return false;
+    }
+    let mut at = 0;
+    for (n, len) in GROUPS.iter().enumerate() {
+        if !bytes[at..at + len].iter().all(u8::is_ascii_hexdigit) {
+            return false;
+        }
+        at += len;
+        if n < GROUPS.len() - 1 {
+            if bytes[at] != b'-' {
+                return false;
+            }
+            at += 1;
+        }
+    }
+    bytes.get(36).is_none_or(|b| !b.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("GET /user/42 took 17ms"), "GET /user/# took #ms");
+        assert_eq!(
+            normalize("req 123e4567-e89b-12d3-a456-426614174000 done"),
+            "req <uuid> done"
+        );
+        assert_eq!(normalize("héllo 3"), "héllo #");
+    }
+
+    #[test]
+    fn test_top_lines() {
+        let mut topk = TopK::new(100);
+        for line in ["a 1", "a 2", "b", "a 3", "b", "c"] {
+            topk.record(line);
+        }
+        assert_eq!(topk.top(2), vec![("a #", 3), ("b", 2)]);
+    }
+
+    #[test]
+    fn test_capacity_drops_rare_lines() {
+        let mut topk = TopK::new(2);
+        topk.record("x");
+        topk.record("x");
+        topk.record("y");
+        topk.record("z");
+        assert_eq!(topk.top(10), vec![("x", 2)]);
+    }
+}
@@ -0,0 +1,80 @@
+//! Undoing and redoing changes to the filter and toggles.
+
+use std::time::{Duration, Instant};
+
+/// Changes kept to undo; the oldest are forgotten beyond this.
+const LIMIT: usize = 100;
+
+/// Edits closer together than this undo as one, so a word typed is undone in one go.
+const RUN: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct Undo<T> {
+    done: Vec<T>,
+    undone: Vec<T>,
+    /// When the last change that may join a run was recorded.
+    run: Option<Instant>,
+}
+
+impl<T> Default for Undo<T> {
+    fn default() -> Self {
+        Undo {
+            done: Vec::new(),
+            undone: Vec::new(),
+            run: None,
+        }
+    }
+}
+
+impl<T> Undo<T> {
+    /// Note a change away from `before`. With `joins`, as for typing, a change soon after
+    /// another such change is undone along with it.
+    pub fn record(&mut self, before: T, joins: bool, now: Instant) {
+        self.undone.clear();
+        let in_run = self.run.is_some_and(|last| now - last < RUN);
+        self.run = joins.then_some(now);
+        if joins && in_run {
+            return;
+        }
+        if self.done.len() == LIMIT {
+            self.done.remove(0);
+        }
+        self.done.push(before);
+    }
+
+    /// The state before the last change, given the `current` one to redo back to.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let before = self.done.pop()?;
+        self.undone.push(current);
+        self.run = None;
+        Some(before)
+    }
+
+    /// The state the last undo went back from.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let after = self.undone.pop()?;
+        self.done.push(current);
+        self.run = None;
+        Some(after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typing_undoes_as_one() {
+        let mut undo = Undo::default();
+        let start = Instant::now();
+        undo.record("", true, start);
+        undo.record("e", true, start + Duration::from_millis(200));
+        undo.record("er", false, start + Duration::from_millis(300));
+        assert_eq!(undo.undo("er!"), Some("er"));
+        assert_eq!(undo.undo("er"), Some(""));
+        assert_eq!(undo.undo(""), None);
+        assert_eq!(undo.redo(""), Some("er"));
+        assert_eq!(undo.redo("er"), Some("er!"));
+        assert_eq!(undo.redo("er!"), None);
+    }
+}
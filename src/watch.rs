@@ -0,0 +1,121 @@
+//! `watch`: rerun a command on an interval, showing its latest output in place with what
+//! changed since the run before marked.
+
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How often the command is rerun unless `-n` says otherwise.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Output of the latest run, and of the one before to compare with.
+#[derive(Debug, Clone, Default)]
+pub struct Watch {
+    pub lines: Vec<String>,
+    previous: Option<Vec<String>>,
+    pub runs: u64,
+}
+
+impl Watch {
+    fn replace(&mut self, lines: Vec<String>) {
+        let previous = std::mem::replace(&mut self.lines, lines);
+        self.previous = (self.runs > 0).then_some(previous);
+        self.runs += 1;
+    }
+
+    /// Line `index` cut into pieces that are each unchanged or changed (`true`) since the
+    /// previous run, comparing characters in the same column. Nothing is marked after the
+    /// first run.
+    pub fn pieces(&self, index: usize) -> Vec<(String, bool)> {
+        let line = &self.lines[index];
+        let Some(previous) = &self.previous else {
+            return vec![(line.clone(), false)];
+        };
+        let mut before = previous.get(index).map_or("", String::as_str).chars();
+        let mut pieces: Vec<(String, bool)> = Vec::new();
+        for c in line.chars() {
+            let changed = before.next() != Some(c);
+            match pieces.last_mut() {
+                Some((text, last)) if *last == changed => text.push(c),
+                _ => pieces.push((c.to_string(), changed)),
+            }
+        }
+        pieces
+    }
+}
+
+/// Run `command` with the shell, returning its output (stderr after stdout) as lines.
+pub fn run(command: &str) -> Vec<String> {
+    let output = match Command::new("sh").arg("-c").arg(command).output() {
+        Ok(output) => output,
+        Err(e) => return vec![format!("{}: {}", command, e)],
+    };
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    text.lines()
+        .map(|line| expand_tabs(line.trim_end_matches('\r')))
+        .collect()
+}
+
+/// `line` with tabs turned into spaces up to the next multiple of 8 columns, so changes
+/// line up.
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = 8 - column % 8;
+            expanded.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+/// Rerun `command` every `interval` in the background, keeping `watch` up to date.
+pub fn spawn(command: String, interval: Duration, watch: Arc<Mutex<Watch>>) {
+    thread::spawn(move || loop {
+        let lines = run(&command);
+        watch.lock().unwrap().replace(lines);
+        thread::sleep(interval);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_columns() {
+        let mut watch = Watch::default();
+        watch.replace(vec!["web-1  Running  2".to_string()]);
+        assert_eq!(watch.pieces(0), [("web-1  Running  2".to_string(), false)]);
+        watch.replace(vec![
+            "web-1  Pending  2".to_string(),
+            "web-2  Running".to_string(),
+        ]);
+        assert_eq!(
+            watch.pieces(0),
+            [
+                ("web-1  ".to_string(), false),
+                ("Pe".to_string(), true),
+                ("n".to_string(), false),
+                ("d".to_string(), true),
+                ("ing  2".to_string(), false),
+            ]
+        );
+        assert_eq!(watch.pieces(1), [("web-2  Running".to_string(), true)]);
+    }
+
+    #[test]
+    fn test_run_collects_output() {
+        assert_eq!(
+            run("printf 'a\\tb\\n'; echo oops >&2"),
+            ["a       b", "oops"]
+        );
+    }
+}
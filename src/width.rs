@@ -0,0 +1,122 @@
+//! Display width of text on the terminal: escape sequences take no space, wide
+//! characters take two columns and combining characters none.
+
+use unicode_width::UnicodeWidthChar;
+
+/// Widest a column of the logfmt, CSV and table views grows to fit its values, so one
+/// long value doesn't push the rest off the screen.
+pub const MAX_COLUMN: usize = 32;
+
+/// Split `text` into escape sequences (`true`) and single characters (`false`).
+pub fn pieces(text: &str) -> impl Iterator<Item = (&str, bool)> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let length = if c == '\x1B' {
+            escape_length(rest)
+        } else {
+            c.len_utf8()
+        };
+        let (piece, tail) = rest.split_at(length);
+        rest = tail;
+        Some((piece, c == '\x1B'))
+    })
+}
+
+/// Length in bytes of the escape sequence at the start of `text`.
+fn escape_length(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    match bytes.get(1) {
+        // CSI: parameters and intermediates up to a final byte in @..~
+        Some(b'[') => bytes[2..]
+            .iter()
+            .position(|b| (0x40..=0x7E).contains(b))
+            .map_or(bytes.len(), |end| end + 3),
+        // OSC (and DCS): up to BEL or ST
+        Some(b']' | b'P') => {
+            let mut i = 2;
+            while i < bytes.len() {
+                match bytes[i] {
+                    7 => return i + 1,
+                    0x1B if bytes.get(i + 1) == Some(&b'\\') => return i + 2,
+                    _ => i += 1,
+                }
+            }
+            bytes.len()
+        }
+        Some(c) if c.is_ascii() => 2,
+        Some(_) => 1,
+        None => 1,
+    }
+}
+
+/// Columns `text` takes up on the terminal.
+pub fn width(text: &str) -> usize {
+    pieces(text)
+        .filter(|(_, escape)| !escape)
+        .map(|(c, _)| c.chars().next().and_then(|c| c.width()).unwrap_or(0))
+        .sum()
+}
+
+/// `text` without its escape sequences.
+pub fn strip(text: &str) -> String {
+    pieces(text)
+        .filter(|(_, escape)| !escape)
+        .map(|(piece, _)| piece)
+        .collect()
+}
+
+/// `text` cut to at most `max` columns, keeping its escape sequences and resetting the
+/// style if anything was cut off.
+pub fn truncate(text: &str, max: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut used = 0;
+    let mut styled = false;
+    for (piece, escape) in pieces(text) {
+        if escape {
+            styled = true;
+            result.push_str(piece);
+            continue;
+        }
+        let columns = piece.chars().next().and_then(|c| c.width()).unwrap_or(0);
+        if used + columns > max {
+            if styled {
+                result.push_str("\x1B[0m");
+            }
+            return result;
+        }
+        used += columns;
+        result.push_str(piece);
+    }
+    result
+}
+
+/// `text` padded with spaces to `columns` wide.
+pub fn pad(text: &str, columns: usize) -> String {
+    let padding = columns.saturating_sub(width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width() {
+        assert_eq!(width("plain"), 5);
+        assert_eq!(width("\x1B[1;31mred\x1B[0m"), 3);
+        assert_eq!(width("\x1B]8;;http://x\x1B\\link\x1B]8;;\x1B\\"), 4);
+        assert_eq!(width("日本"), 4);
+        assert_eq!(width("e\u{301}"), 1);
+        assert_eq!(strip("\x1B[1;31mred\x1B[0m ok"), "red ok");
+    }
+
+    #[test]
+    fn test_truncate_and_pad() {
+        assert_eq!(truncate("hello", 3), "hel");
+        assert_eq!(truncate("\x1B[31mhello\x1B[0m", 2), "\x1B[31mhe\x1B[0m");
+        assert_eq!(truncate("日本語", 3), "日");
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(pad("日", 4), "日  ");
+    }
+}
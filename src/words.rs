@@ -0,0 +1,73 @@
+//! Literal words, each with its SGR style, found in a line in one Aho-Corasick pass rather
+//! than one search per word, for configured patterns and plain terms on busy pipes.
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Default)]
+pub struct Words {
+    /// Styles of the words, in the order given.
+    styles: Vec<String>,
+    /// `None` when there are no words.
+    automaton: Option<AhoCorasick>,
+}
+
+impl Words {
+    /// The words of `words` with their styles, where one listed earlier wins over one
+    /// starting at the same place. `ignore_case` folds ASCII letters only.
+    pub fn new(words: &[(String, String)], ignore_case: bool) -> Words {
+        let words: Vec<_> = words.iter().filter(|(word, _)| !word.is_empty()).collect();
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostFirst)
+            .ascii_case_insensitive(ignore_case)
+            .build(words.iter().map(|(word, _)| word))
+            .ok()
+            .filter(|_| !words.is_empty());
+        Words {
+            styles: words.into_iter().map(|(_, style)| style.clone()).collect(),
+            automaton,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.automaton.is_none()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.automaton
+            .as_ref()
+            .is_some_and(|automaton| automaton.is_match(text))
+    }
+
+    /// Byte ranges of the words in `text`, in order and without overlaps, with their styles.
+    pub fn ranges(&self, text: &str) -> Vec<(Range<usize>, &str)> {
+        let Some(automaton) = &self.automaton else {
+            return Vec::new();
+        };
+        automaton
+            .find_iter(text)
+            .map(|found| (found.range(), self.styles[found.pattern()].as_str()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_pass() {
+        let word = |text: &str, style: &str| (text.to_string(), style.to_string());
+        let words = Words::new(
+            &[word("ERROR", "31"), word("ERR", "33"), word("", "0")],
+            false,
+        );
+        assert_eq!(
+            words.ranges("ERR then ERROR"),
+            vec![(0..3, "33"), (9..14, "31")]
+        );
+        assert!(!words.is_match("error"));
+        assert!(Words::new(&[word("error", "31")], true).is_match("An ERROR"));
+        assert!(Words::new(&[], false).is_empty());
+    }
+}